@@ -0,0 +1,70 @@
+//! Parsing a large monitor response the way `parse_api_response` does
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[allow(dead_code)]
+#[path = "../src/occupancy.rs"]
+mod occupancy;
+#[allow(dead_code)]
+#[path = "../src/wiener_linien.rs"]
+mod wiener_linien;
+
+use wiener_linien::{WienerLinienMonitor, WienerLinienResponse};
+
+const MONITORS: usize = 200;
+const LINES: usize = 8;
+const DEPARTURES: usize = 12;
+
+/// A response with as many monitors as a whole station group asks for, each
+/// with a few lines and a full list of departures
+fn fixture() -> String {
+    let departure = |countdown: usize| {
+        format!(
+            r#"{{"departureTime":{{"timePlanned":"2026-10-16T09:{0:02}:00.000+0200","timeReal":"2026-10-16T09:{0:02}:30.000+0200","countdown":{0}}},"vehicle":{{"trafficjam":false}}}}"#,
+            countdown % 60
+        )
+    };
+    let line = |line: usize| {
+        let departures = (0..DEPARTURES)
+            .map(|d| departure(d * 5))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"name":"{line}","towards":"Destination {line}","direction":"H","platform":"{line}","type":"ptTram","trafficjam":false,"departures":{{"departure":[{departures}]}}}}"#
+        )
+    };
+    let monitor = |rbl: usize| {
+        let lines = (0..LINES).map(line).collect::<Vec<_>>().join(",");
+        format!(
+            r#"{{"locationStop":{{"type":"Feature","geometry":{{"type":"Point","coordinates":[16.37,48.2]}},"properties":{{"name":"60200{rbl}","title":"Stop {rbl}","attributes":{{"rbl":{rbl}}}}}}},"lines":[{lines}]}}"#
+        )
+    };
+    let monitors = (0..MONITORS).map(monitor).collect::<Vec<_>>().join(",");
+    format!(
+        r#"{{"data":{{"monitors":[{monitors}],"trafficInfos":[{{"priority":"1","name":"ma_1","title":"Disruption","description":"Delays","relatedLines":["1"]}}]}},"message":{{"value":"OK","messageCode":1}}}}"#
+    )
+}
+
+fn parse(c: &mut Criterion) {
+    let text = fixture();
+    c.bench_function("parse monitor response", |b| {
+        b.iter(|| {
+            let response: WienerLinienResponse = serde_json::from_str(black_box(&text)).unwrap();
+            let data = response.data.unwrap();
+            let mut departures = 0;
+            for monitor in data.monitors.unwrap() {
+                let monitor = serde_json::from_str::<WienerLinienMonitor>(monitor.get()).unwrap();
+                departures += monitor
+                    .lines
+                    .iter()
+                    .map(|line| line.departures.departure.len())
+                    .sum::<usize>();
+            }
+            assert_eq!(departures, MONITORS * LINES * DEPARTURES);
+            (departures, data.traffic_infos)
+        })
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
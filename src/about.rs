@@ -0,0 +1,154 @@
+use clap::ValueEnum;
+use comfy_table::{Cell, Table};
+use std::{env, path::Path};
+
+use crate::{
+    output::Output,
+    settings::Settings,
+    table_style::{Separators, TableStyle},
+};
+
+/// Build information and what the configuration enables, shown with the help on
+/// the board and printed by `--version --verbose`
+pub fn entries(
+    config: Option<&Path>,
+    settings: Option<&Settings>,
+    output: Output,
+) -> Vec<(&'static str, String)> {
+    let enabled = |value: Option<String>| value.unwrap_or_else(|| "off".to_string());
+    let mut entries = vec![
+        ("Version", env!("CARGO_PKG_VERSION").to_string()),
+        ("Commit", env!("GIT_HASH").to_string()),
+        (
+            "Build",
+            format!(
+                "{}-{}, {}",
+                env::consts::ARCH,
+                env::consts::OS,
+                if cfg!(debug_assertions) {
+                    "debug"
+                } else {
+                    "release"
+                }
+            ),
+        ),
+        (
+            "Config",
+            config.map_or_else(
+                || "none, defaults and environment".to_string(),
+                |path| path.display().to_string(),
+            ),
+        ),
+        (
+            "Output",
+            output
+                .to_possible_value()
+                .map_or_else(String::new, |value| value.get_name().to_string()),
+        ),
+    ];
+    let Some(settings) = settings else {
+        entries.push(("Settings", "invalid configuration".to_string()));
+        return entries;
+    };
+    entries.extend([
+        (
+            "City",
+            settings
+                .city
+                .to_possible_value()
+                .map_or_else(String::new, |value| value.get_name().to_string()),
+        ),
+        (
+            "MQTT",
+            enabled(
+                settings
+                    .mqtt
+                    .as_ref()
+                    .map(|mqtt| format!("{}:{}", mqtt.host, mqtt.port)),
+            ),
+        ),
+        (
+            "HTTP server",
+            enabled(settings.http.as_ref().map(|http| http.listen.to_string())),
+        ),
+        (
+            "Control socket",
+            enabled(
+                settings
+                    .control_socket
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+        ),
+        (
+            "Daemon",
+            enabled(
+                settings
+                    .daemon_socket
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+        ),
+        (
+            "Row script",
+            enabled(
+                settings
+                    .script
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+        ),
+    ]);
+    entries
+}
+
+/// `entries` one per line, for the terminal
+pub fn text(entries: &[(&str, String)]) -> String {
+    let width = entries
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    entries
+        .iter()
+        .map(|(name, value)| format!("{:width$}  {}\n", name, value, width = width))
+        .collect()
+}
+
+/// The help replacing the board: the bound `keys` with what they do, then `entries`
+///
+/// Rows aren't separated whatever the table style, so the help fits on small screens.
+pub fn screen(
+    keys: &[(String, &str)],
+    entries: &[(&str, String)],
+    style: &TableStyle,
+    width: u16,
+) -> String {
+    let table = |header: [&str; 2], rows: Vec<[&str; 2]>| {
+        let compact = TableStyle {
+            separators: Separators::None,
+            ..style.clone()
+        };
+        let mut table = Table::new();
+        compact.apply(&mut table);
+        table
+            .set_width(width)
+            .set_header(header.map(Cell::new))
+            .add_rows(rows.into_iter().map(|row| row.map(Cell::new)));
+        table.to_string()
+    };
+    let keys = table(
+        ["Keys", "Action"],
+        keys.iter()
+            .map(|(keys, action)| [keys.as_str(), action])
+            .collect(),
+    );
+    let about = table(
+        [env!("CARGO_PKG_NAME"), ""],
+        entries
+            .iter()
+            .map(|(name, value)| [*name, value.as_str()])
+            .collect(),
+    );
+    format!("{}\n{}", keys, about)
+}
@@ -0,0 +1,140 @@
+use iso8601_timestamp::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::{scheduler::ApiResponse, theme::Severity, Departure, WienerLinienTrafficInfo};
+
+/// Seconds a departure is remembered past its planned time, as one running
+/// late is still shown until it actually left
+const DEPARTED_AFTER: i64 = 60 * 60;
+
+/// Fires once per departure of `line` when it is `minutes` or less away
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertRule {
+    pub line: String,
+    pub minutes: i64,
+    /// Only consider departures from this station
+    pub station: Option<String>,
+}
+
+impl AlertRule {
+    fn matches(&self, dep: &Departure) -> bool {
+        dep.line.name == self.line
+            && dep.countdown <= self.minutes
+            && self
+                .station
+                .as_ref()
+                .is_none_or(|station| *station == dep.station_name)
+    }
+}
+
+/// Text of the notification sent for an alert
+pub fn message(dep: &Departure) -> String {
+    format!(
+        "{} to {} leaves {} in {} min",
+        dep.line.name, dep.destination_name, dep.station_name, dep.countdown
+    )
+}
+
+/// Remembers what was already reported, so every alert is only sent once
+pub struct AlertState {
+    rules: Vec<AlertRule>,
+    reported: Reported,
+}
+
+/// Line, station, destination and planned departure in seconds since the epoch
+type DepartureId = (String, String, String, i64);
+
+/// Departures and disruptions already reported, kept across restarts
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Reported {
+    fired: HashSet<DepartureId>,
+    seen_disruptions: HashSet<String>,
+}
+
+fn departure_id(dep: &Departure) -> DepartureId {
+    (
+        dep.line.name.clone(),
+        dep.station_name.clone(),
+        dep.destination_name.clone(),
+        dep.time_planned
+            .duration_since(Timestamp::UNIX_EPOCH)
+            .whole_seconds(),
+    )
+}
+
+impl AlertState {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        AlertState {
+            rules,
+            reported: Reported::default(),
+        }
+    }
+
+    pub fn reported(&self) -> &Reported {
+        &self.reported
+    }
+
+    /// Continue from what was reported before a restart
+    pub fn restore(&mut self, reported: Reported) {
+        self.reported = reported;
+    }
+
+    /// Replace the rules, e.g. after the config was reloaded
+    pub fn set_rules(&mut self, rules: Vec<AlertRule>) {
+        self.rules = rules;
+    }
+
+    /// Departures that newly matched a rule
+    pub fn check_departures<'a>(&mut self, departures: &'a [Departure]) -> Vec<&'a Departure> {
+        let mut matched = vec![];
+        for dep in departures {
+            if !self.rules.iter().any(|rule| rule.matches(dep)) {
+                continue;
+            }
+            if self.reported.fired.insert(departure_id(dep)) {
+                matched.push(dep);
+            }
+        }
+        matched
+    }
+
+    /// Severe disruptions that newly appeared on a line one of the rules watches
+    pub fn check_disruptions<'a>(
+        &mut self,
+        traffic_info: &'a [WienerLinienTrafficInfo],
+    ) -> Vec<&'a WienerLinienTrafficInfo> {
+        traffic_info
+            .iter()
+            .filter(|info| Severity::of(info) == Severity::Major)
+            .filter(|info| {
+                info.related_lines
+                    .iter()
+                    .any(|line| self.rules.iter().any(|rule| rule.line == *line))
+            })
+            .filter(|info| self.reported.seen_disruptions.insert(info.name.clone()))
+            .collect()
+    }
+
+    /// Forget departures that left and disruptions no longer in the latest `data`
+    /// of any job, so what is remembered doesn't grow for as long as the monitor runs
+    ///
+    /// Departures are forgotten by their time rather than by missing from the
+    /// data, as a batch that is stale for a while would otherwise have its
+    /// alerts sent again once it answers.
+    pub fn prune<'a>(&mut self, data: impl Iterator<Item = &'a ApiResponse>) {
+        let mut disruptions = HashSet::new();
+        for (_, traffic_info) in data {
+            disruptions.extend(traffic_info.iter().flatten().map(|info| &info.name));
+        }
+        let now = Timestamp::now_utc()
+            .duration_since(Timestamp::UNIX_EPOCH)
+            .whole_seconds();
+        self.reported
+            .fired
+            .retain(|(_, _, _, planned)| *planned + DEPARTED_AFTER > now);
+        self.reported
+            .seen_disruptions
+            .retain(|name| disruptions.contains(name));
+    }
+}
@@ -0,0 +1,100 @@
+use iso8601_timestamp::Timestamp;
+use std::time::{Duration, Instant};
+
+use crate::departures::TrackedDeparture;
+
+/// How long a departure stays highlighted after its countdown reached zero
+const DUE_HIGHLIGHT: Duration = Duration::from_secs(5);
+
+/// How long a departure that vanished before its time stays on the board as likely cancelled
+const CANCELLED_SHOWN: Duration = Duration::from_secs(60);
+
+/// Seconds a departure must still have been away from its time when it vanished
+/// to count as likely cancelled, closer to it a refresh just missed it leaving
+const CANCELLED_MARGIN: i64 = 120;
+
+/// Number of frames a leaving row takes to be wiped off the board
+///
+/// A row is a single terminal line, so the rows below can't slide up into its
+/// place bit by bit. Its text is wiped from the right instead, and the rows
+/// below move up by the one line once it is gone.
+pub const LEAVING_STEPS: u8 = 5;
+
+/// Time between frames while a row is leaving, instead of the usual second
+pub const FRAME: Duration = Duration::from_millis(100);
+
+/// How a departure row is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RowState {
+    Normal,
+    /// Countdown reached zero, the row is highlighted
+    Due,
+    /// The row's text is being wiped from the right, the step counts up to [`LEAVING_STEPS`]
+    Leaving(u8),
+    /// Left this many minutes ago, still shown as configured with `keep_departed`
+    Departed(i64),
+    /// Vanished from the API data well before its time, so it was likely cancelled
+    Cancelled,
+}
+
+impl RowState {
+    /// State of a tracked departure at `now`, `None` once it left the board
+    ///
+    /// A departure leaves once it was highlighted long enough. One that vanished
+    /// from the API data well before its time is shown as cancelled for a while
+    /// first. With `keep_departed`, a departure whose time has passed stays
+    /// instead of leaving.
+    pub fn of(entry: &TrackedDeparture, now: Instant, keep_departed: Duration) -> Option<RowState> {
+        let highlighted = entry.due.is_some_and(|due| now >= due + DUE_HIGHLIGHT);
+        let dep = &entry.departure;
+        let since = Timestamp::now_utc()
+            .duration_since(dep.time_real.unwrap_or(dep.time_planned))
+            .whole_seconds();
+        // one that vanished well before it was due may have been cancelled
+        let cancelled = entry.due.is_none()
+            && entry.removed.is_some_and(|removed| {
+                since - now.saturating_duration_since(removed).as_secs() as i64 <= -CANCELLED_MARGIN
+            });
+        if !keep_departed.is_zero() && (highlighted || entry.removed.is_some()) && !cancelled {
+            return (since < keep_departed.as_secs() as i64)
+                .then_some(RowState::Departed(since.max(0) / 60));
+        }
+        let leaving_since = match (entry.due, entry.removed) {
+            (Some(due), _) if now < due + DUE_HIGHLIGHT => return Some(RowState::Due),
+            (Some(due), _) => due + DUE_HIGHLIGHT,
+            (None, Some(removed)) if cancelled && now < removed + CANCELLED_SHOWN => {
+                return Some(RowState::Cancelled)
+            }
+            (None, Some(removed)) if cancelled => removed + CANCELLED_SHOWN,
+            (None, Some(removed)) => removed,
+            (None, None) => return Some(RowState::Normal),
+        };
+        let step = now.saturating_duration_since(leaving_since).as_millis() / FRAME.as_millis();
+        (step < LEAVING_STEPS as u128).then_some(RowState::Leaving(step as u8))
+    }
+
+    pub fn is_leaving(&self) -> bool {
+        matches!(self, RowState::Leaving(_))
+    }
+
+    /// Whether the departure is gone, or about to be, so only the board shows it
+    pub fn has_left(&self) -> bool {
+        matches!(
+            self,
+            RowState::Leaving(_) | RowState::Departed(_) | RowState::Cancelled
+        )
+    }
+
+    /// Cut `text` down to what is still visible at this step of leaving, the
+    /// row itself keeps its place until the wipe is done
+    pub fn wipe(&self, text: &str) -> String {
+        match self {
+            RowState::Leaving(step) => {
+                let len = text.chars().count();
+                let visible = len * (LEAVING_STEPS - 1 - step) as usize / LEAVING_STEPS as usize;
+                text.chars().take(visible).collect()
+            }
+            _ => text.to_string(),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, process::Command, sync::Mutex};
+
+use crate::{filter, Departure};
+
+/// Held while an announcement plays, so announcements never talk over each other
+static SPEAKING: Mutex<()> = Mutex::new(());
+
+/// Spoken announcements of departures that match an alert rule
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnnouncementSettings {
+    /// Text-to-speech command, the announcement is passed as last argument; empty for chime only
+    pub speak: Vec<String>,
+    /// Sound file played before the announcement
+    pub chime: Option<PathBuf>,
+    /// Command playing the chime, the file is passed as last argument
+    pub player: Vec<String>,
+    /// Stay silent from this time of day...
+    #[serde(with = "filter::time_of_day")]
+    pub quiet_from: Option<NaiveTime>,
+    /// ...until this time of day
+    #[serde(with = "filter::time_of_day")]
+    pub quiet_until: Option<NaiveTime>,
+}
+
+impl Default for AnnouncementSettings {
+    fn default() -> Self {
+        AnnouncementSettings {
+            speak: vec!["espeak-ng".to_string()],
+            chime: None,
+            player: vec!["aplay".to_string(), "-q".to_string()],
+            quiet_from: None,
+            quiet_until: None,
+        }
+    }
+}
+
+impl AnnouncementSettings {
+    fn is_quiet(&self, time: NaiveTime) -> bool {
+        self.quiet_from.is_some()
+            && self.quiet_until.is_some()
+            && filter::time_between(time, self.quiet_from, self.quiet_until)
+    }
+
+    /// Play the chime and speak the departure in the background, failures are dropped
+    pub fn announce(&self, dep: &Departure) {
+        if self.is_quiet(Local::now().time()) {
+            return;
+        }
+        let text = format!(
+            "Line {} to {} departing in {} {}",
+            dep.line.name,
+            dep.destination_name,
+            dep.countdown,
+            if dep.countdown == 1 {
+                "minute"
+            } else {
+                "minutes"
+            }
+        );
+        let chime = self
+            .chime
+            .as_ref()
+            .and_then(|chime| command(&self.player, chime.as_os_str()));
+        let speech = command(&self.speak, text.as_ref());
+
+        tokio::task::spawn_blocking(move || {
+            let _speaking = SPEAKING.lock();
+            for mut command in chime.into_iter().chain(speech) {
+                let _ = command.status();
+            }
+        });
+    }
+}
+
+/// `program` with `argument` appended, `None` if no program is configured
+fn command(program: &[String], argument: &std::ffi::OsStr) -> Option<Command> {
+    let (program, args) = program.split_first()?;
+    let mut command = Command::new(program);
+    command.args(args).arg(argument);
+    Some(command)
+}
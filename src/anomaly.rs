@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use crate::Departure;
+
+/// History a line needs before its delays count as unusual
+const MIN_HISTORY: Duration = Duration::from_secs(15 * 60);
+
+/// When to point out lines running much later than usual
+#[derive(Debug, Clone, Hash, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnomalySettings {
+    /// Minutes a line has to be later than usual to be pointed out, never if 0
+    pub threshold: i64,
+    /// Minutes of history the usual delay is taken from
+    pub baseline: u64,
+}
+
+impl Default for AnomalySettings {
+    fn default() -> Self {
+        AnomalySettings {
+            threshold: 5,
+            baseline: 120,
+        }
+    }
+}
+
+/// A line running later than usual, delays in minutes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    pub line: String,
+    pub current: f64,
+    pub typical: f64,
+}
+
+/// Rolling history of the average delay of every line, kept in memory
+#[derive(Debug, Default)]
+pub struct DelayBaseline {
+    samples: HashMap<String, VecDeque<(Instant, f64)>>,
+}
+
+impl DelayBaseline {
+    /// Remember the average delay per line of a refresh, forgetting what is older than the baseline
+    pub fn record(&mut self, departures: &[Departure], settings: &AnomalySettings) {
+        let now = Instant::now();
+        let mut delays: BTreeMap<&str, Vec<i64>> = BTreeMap::new();
+        // departures without realtime data say nothing about the delay
+        for dep in departures.iter().filter(|dep| dep.time_real.is_some()) {
+            delays
+                .entry(&dep.line.name)
+                .or_default()
+                .push(dep.delay_minutes());
+        }
+        for (line, delays) in delays {
+            let average = delays.iter().sum::<i64>() as f64 / delays.len() as f64;
+            self.samples
+                .entry(line.to_string())
+                .or_default()
+                .push_back((now, average));
+        }
+
+        let baseline = Duration::from_secs(settings.baseline * 60);
+        for samples in self.samples.values_mut() {
+            while samples
+                .front()
+                .is_some_and(|(time, _)| now.duration_since(*time) > baseline)
+            {
+                samples.pop_front();
+            }
+        }
+        self.samples.retain(|_, samples| !samples.is_empty());
+    }
+
+    /// Lines whose latest delay is at least the threshold above their median, the worst first
+    pub fn anomalies(&self, settings: &AnomalySettings) -> Vec<Anomaly> {
+        if settings.threshold <= 0 {
+            return vec![];
+        }
+        let mut anomalies: Vec<Anomaly> = self
+            .samples
+            .iter()
+            .filter(|(_, samples)| {
+                samples
+                    .front()
+                    .is_some_and(|(time, _)| time.elapsed() >= MIN_HISTORY)
+            })
+            .filter_map(|(line, samples)| {
+                let (_, current) = *samples.back()?;
+                let mut delays: Vec<f64> = samples.iter().map(|(_, delay)| *delay).collect();
+                delays.sort_by(f64::total_cmp);
+                let typical = delays[delays.len() / 2];
+                (current - typical >= settings.threshold as f64).then(|| Anomaly {
+                    line: line.clone(),
+                    current,
+                    typical,
+                })
+            })
+            .collect();
+        anomalies.sort_by(|a, b| {
+            (b.current - b.typical)
+                .total_cmp(&(a.current - a.typical))
+                .then_with(|| a.line.cmp(&b.line))
+        });
+        anomalies
+    }
+}
+
+/// E.g. "43 currently +9 min vs typical +1", `None` without anomalies
+pub fn banner(anomalies: &[Anomaly]) -> Option<String> {
+    let mut banner = String::new();
+    for anomaly in anomalies {
+        if !banner.is_empty() {
+            banner.push_str(" · ");
+        }
+        let _ = write!(
+            banner,
+            "{} currently {:+.0} min vs typical {:+.0}",
+            anomaly.line, anomaly.current, anomaly.typical
+        );
+    }
+    (!banner.is_empty()).then_some(banner)
+}
@@ -0,0 +1,125 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::{
+    animation::RowState, clock::ClockSettings, disruptions::Disruption, header::HeaderSettings,
+    journey::JourneySettings, locale::Lang, table_style::TableStyle, theme::Palette,
+    walking::WalkingSettings, widgets::Layout, Departure,
+};
+
+/// Stands in for the clock while a board is rendered, so the rendered board
+/// stays valid for the whole minute and only the clock text has to be replaced
+pub const CLOCK_PLACEHOLDER: &str = "88:88:88";
+
+/// The last rendered board, reused as long as nothing shown on it changed
+#[derive(Default)]
+pub struct BoardCache {
+    key: Option<u64>,
+    content: String,
+}
+
+/// Everything that influences how the board looks, apart from the clock seconds
+pub struct BoardKey<'a> {
+    pub departures: &'a [(&'a Departure, RowState)],
+    pub disruptions: &'a [Disruption<'a>],
+    pub notice: Option<&'a str>,
+    /// Stale batches, for the footer template
+    pub stale: Option<&'a str>,
+    /// Lines running much later than usual
+    pub attention: Option<&'a str>,
+    /// Row picked with the arrow keys
+    pub selected: Option<usize>,
+    pub width: u16,
+    pub height: u16,
+    pub page: Option<usize>,
+    /// Pages of departures turned so far, see `auto_page` in the config
+    pub auto_page: Option<usize>,
+    pub minute: u32,
+    pub palette: Palette,
+    pub lang: Lang,
+    pub walking: &'a WalkingSettings,
+    pub journey: Option<&'a JourneySettings>,
+    pub footer: Option<&'a str>,
+    pub header: &'a HeaderSettings,
+    pub table: &'a TableStyle,
+    pub clock: &'a ClockSettings,
+    pub layout: Option<&'a Layout>,
+    /// Latest text of the widgets fetching it in the background
+    pub feeds: &'a [Option<String>],
+}
+
+impl BoardKey<'_> {
+    fn hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (dep, state) in self.departures {
+            // label and color come from the line overrides, which a reload may change
+            (&dep.line.name, &dep.line.label, dep.line.color).hash(&mut hasher);
+            (
+                &dep.station_name,
+                &dep.platform,
+                &dep.destination_name,
+                dep.stop_id,
+                dep.time_planned,
+                dep.time_real,
+                dep.countdown,
+                dep.occupancy,
+                dep.traffic_jam,
+                &dep.note,
+                dep.countdowns,
+                state,
+            )
+                .hash(&mut hasher);
+        }
+        for disruption in self.disruptions {
+            let info = disruption.info;
+            (
+                &info.title,
+                &info.description,
+                &info.priority,
+                disruption.is_new,
+            )
+                .hash(&mut hasher);
+        }
+        (
+            self.notice,
+            self.stale,
+            self.attention,
+            self.selected,
+            self.width,
+            self.height,
+            self.page,
+            self.auto_page,
+            self.minute,
+            self.palette,
+            self.lang,
+        )
+            .hash(&mut hasher);
+        (
+            self.walking,
+            self.journey,
+            self.footer,
+            self.header,
+            self.table,
+            self.clock,
+            self.layout,
+            self.feeds,
+        )
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl BoardCache {
+    /// Rendered board for `key`, calling `render` only if it differs from the last one
+    pub fn get_or_render<E>(
+        &mut self,
+        key: &BoardKey,
+        render: impl FnOnce() -> Result<String, E>,
+    ) -> Result<&str, E> {
+        let hash = key.hash();
+        if self.key != Some(hash) {
+            self.content = render()?;
+            self.key = Some(hash);
+        }
+        Ok(&self.content)
+    }
+}
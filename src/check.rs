@@ -0,0 +1,247 @@
+use config::ConfigError;
+use std::{collections::BTreeSet, fs, path::Path};
+
+use crate::{
+    script::RowScript,
+    settings::{self, Settings},
+};
+
+/// Something wrong with the configuration, found by `--check-config`
+struct Problem {
+    /// Dotted path of the setting, if the problem is about one
+    key: Option<String>,
+    message: String,
+    /// Only worth a warning, the monitor runs as intended anyway
+    warning: bool,
+}
+
+impl Problem {
+    fn error(key: Option<String>, message: String) -> Self {
+        Problem {
+            key,
+            message,
+            warning: false,
+        }
+    }
+
+    fn warning(key: &str, message: String) -> Self {
+        Problem {
+            key: Some(key.to_string()),
+            message,
+            warning: true,
+        }
+    }
+}
+
+/// Check the configuration at `path` and `profile`, printing every problem with
+/// the line of the config file it is on
+///
+/// Returns the number of errors, warnings aren't counted.
+pub fn run(path: Option<&Path>, profile: Option<&str>) -> usize {
+    let file = settings::config_file(path);
+    let text = file
+        .as_deref()
+        .and_then(|file| fs::read_to_string(file).ok())
+        .unwrap_or_default();
+    let name = file.as_ref().map_or_else(
+        || "environment".to_string(),
+        |file| file.display().to_string(),
+    );
+
+    let problems = check(path, profile);
+    for problem in &problems {
+        let line = problem
+            .key
+            .as_deref()
+            .and_then(|key| line_of(&text, key))
+            .map_or_else(String::new, |line| format!(":{}", line));
+        let key = problem
+            .key
+            .as_deref()
+            .map_or_else(String::new, |key| format!("`{}`: ", key));
+        println!(
+            "{}{}{}: {}{}",
+            if problem.warning { "warning: " } else { "" },
+            name,
+            line,
+            key,
+            problem.message
+        );
+    }
+    let errors = problems.iter().filter(|problem| !problem.warning).count();
+    if problems.is_empty() {
+        println!("{}: no problems found", name);
+    }
+    errors
+}
+
+fn check(path: Option<&Path>, profile: Option<&str>) -> Vec<Problem> {
+    let config = match settings::sources(path) {
+        Ok(config) => config,
+        // syntax errors come with their position already
+        Err(e) => return vec![Problem::error(None, e.to_string())],
+    };
+    let mut problems = vec![];
+
+    let mut unknown = vec![];
+    let mut track = |path: serde_ignored::Path| unknown.push(path.to_string());
+    let deserializer = serde_ignored::Deserializer::new(config, &mut track);
+    let result: Result<Settings, _> = serde_path_to_error::deserialize(deserializer);
+    for key in unknown {
+        problems.push(Problem::error(Some(key), "unknown key".to_string()));
+    }
+    let settings = match result {
+        Ok(settings) => settings,
+        Err(e) => {
+            let key = e.path().to_string();
+            let message = match e.into_inner() {
+                ConfigError::Type {
+                    unexpected,
+                    expected,
+                    ..
+                } => format!("invalid type: {}, expected {}", unexpected, expected),
+                e => e.to_string(),
+            };
+            problems.push(Problem::error(Some(key), message));
+            return problems;
+        }
+    };
+
+    check_stops("stops", &settings.stops, &mut problems);
+    for (name, profile) in &settings.profiles {
+        check_stops(
+            &format!("profiles.{}.stops", name),
+            &profile.stops,
+            &mut problems,
+        );
+    }
+    for (name, stops) in &settings.stop_groups {
+        check_stops(&format!("stop_groups.{}", name), stops, &mut problems);
+    }
+    for (i, view) in settings.carousel.views.iter().enumerate() {
+        check_stops(
+            &format!("carousel.views.{}.stops", i),
+            &view.stops,
+            &mut problems,
+        );
+    }
+    for (name, profile) in &settings.profiles {
+        for group in &profile.groups {
+            if !settings.stop_groups.contains_key(group) {
+                problems.push(Problem::error(
+                    Some(format!("profiles.{}.groups", name)),
+                    format!("no stop group `{}`", group),
+                ));
+            }
+        }
+    }
+    if let Some(filter) = &settings.filter {
+        for group in filter.groups() {
+            if !settings.stop_groups.contains_key(group) {
+                problems.push(Problem::error(
+                    Some("filter".to_string()),
+                    format!("no stop group `{}`", group),
+                ));
+            }
+        }
+    }
+    let profiles: Vec<_> = settings
+        .profiles
+        .iter()
+        .map(|(name, profile)| (name, settings.profile_stops(profile).unwrap_or_default()))
+        .collect();
+    for (i, (name, stops)) in profiles.iter().enumerate() {
+        let stops: BTreeSet<_> = stops.iter().collect();
+        for (other_name, other) in &profiles[i + 1..] {
+            let others: BTreeSet<_> = other.iter().collect();
+            let shared: Vec<String> = stops
+                .intersection(&others)
+                .map(|stop| stop.to_string())
+                .collect();
+            if stops == others {
+                problems.push(Problem::warning(
+                    &format!("profiles.{}", other_name),
+                    format!("monitors the same stops as profile `{}`", name),
+                ));
+            } else if !shared.is_empty() {
+                problems.push(Problem::warning(
+                    &format!("profiles.{}", other_name),
+                    format!("shares stops {} with profile `{}`", shared.join(", "), name),
+                ));
+            }
+        }
+    }
+    if let Some(profile) = profile.filter(|profile| !settings.profiles.contains_key(*profile)) {
+        problems.push(Problem::error(
+            Some("profiles".to_string()),
+            format!("no profile `{}`", profile),
+        ));
+    }
+
+    if let Some(script) = &settings.script {
+        if let Err(e) = RowScript::load(script) {
+            problems.push(Problem::error(Some("script".to_string()), e.to_string()));
+        }
+    }
+    // what is only found when loading, like an unreadable stops file
+    if let Err(e) = Settings::load(path, None) {
+        problems.push(Problem::error(None, e.to_string()));
+    }
+    problems
+}
+
+/// Stop IDs that can't be RBL numbers, and ones given twice
+fn check_stops(key: &str, stops: &[i32], problems: &mut Vec<Problem>) {
+    let mut seen = BTreeSet::new();
+    for stop in stops {
+        if *stop <= 0 {
+            problems.push(Problem::error(
+                Some(key.to_string()),
+                format!("{} is no stop ID", stop),
+            ));
+        } else if !seen.insert(stop) {
+            problems.push(Problem::warning(
+                key,
+                format!("stop {} is listed twice", stop),
+            ));
+        }
+    }
+}
+
+/// Line of the config file `text` where the setting at the dotted `key` is,
+/// found by looking for its parts one after the other
+fn line_of(text: &str, key: &str) -> Option<usize> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut found = None;
+    let mut start = 0;
+    // array indices aren't written in the file
+    for part in key.split('.').filter(|part| part.parse::<usize>().is_err()) {
+        match (start..lines.len()).find(|i| defines(lines[*i], part)) {
+            Some(line) => {
+                found = Some(line + 1);
+                start = line;
+            }
+            None => return found,
+        }
+    }
+    found
+}
+
+/// Whether `line` sets `part`, as a key or a TOML table header
+fn defines(line: &str, part: &str) -> bool {
+    let line = line.trim();
+    if let Some(header) = line.strip_prefix('[') {
+        return header
+            .trim_matches(|c| c == '[' || c == ']')
+            .split('.')
+            .any(|name| name.trim().trim_matches('"') == part);
+    }
+    let line = line
+        .trim_start_matches("- ")
+        .trim_start_matches(['"', '\'']);
+    line.strip_prefix(part).is_some_and(|rest| {
+        rest.trim_start_matches(['"', '\''])
+            .trim_start()
+            .starts_with(['=', ':'])
+    })
+}
@@ -0,0 +1,196 @@
+use chrono::{NaiveDateTime, NaiveTime};
+use clap::{Parser, Subcommand};
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    expression::Filter,
+    locale::Lang,
+    output::Output,
+    power::PowerSource,
+    provider::City,
+    theme::{Palette, ThemePreset},
+    widgets::LayoutPreset,
+};
+
+/// Realtime departure monitor for Wiener Linien stops, and those of other Austrian cities
+#[derive(Parser, Debug)]
+#[command(about, disable_version_flag = true)]
+pub struct Args {
+    /// Print the version
+    #[arg(long, short = 'V')]
+    pub version: bool,
+
+    /// With `--version`, also print the commit, build and what the configuration enables
+    #[arg(long, requires = "version")]
+    pub verbose: bool,
+
+    /// Config file to load instead of `oeffimonitor.{toml,yaml,json,...}` in the working directory,
+    /// TOML, YAML or JSON depending on the extension
+    #[arg(long, short)]
+    pub config: Option<PathBuf>,
+
+    /// Monitor the stops of this profile from the config file
+    #[arg(long, short)]
+    pub profile: Option<String>,
+
+    /// Only show departures leaving within this time span (e.g. `45m`, `1h30m`)
+    #[arg(long, global = true, value_parser = humantime::parse_duration)]
+    pub within: Option<Duration>,
+
+    /// Only show departures leaving at or after this time of day (`HH:MM`)
+    #[arg(long, global = true, value_parser = parse_time_of_day)]
+    pub after: Option<NaiveTime>,
+
+    /// Only show departures leaving before this time of day (`HH:MM`)
+    #[arg(long, global = true, value_parser = parse_time_of_day)]
+    pub before: Option<NaiveTime>,
+
+    /// Only show departures matching this expression,
+    /// e.g. `line in [43,44] && countdown >= 5 && type != nightbus`
+    #[arg(long, global = true)]
+    pub filter: Option<Filter>,
+
+    /// Colors of the board
+    #[arg(long, value_enum)]
+    pub palette: Option<Palette>,
+
+    /// Language of times and durations on the board
+    #[arg(long, value_enum)]
+    pub lang: Option<Lang>,
+
+    /// Palette and table borders in one, e.g. `wl` to look like the station displays
+    #[arg(long, value_enum)]
+    pub theme: Option<ThemePreset>,
+
+    /// Layout replacing the one in the config file, e.g. `portrait` for monitors mounted upright
+    #[arg(long, value_enum)]
+    pub layout: Option<LayoutPreset>,
+
+    /// Transit network the stops belong to, Vienna unless set in the config file
+    #[arg(long, value_enum)]
+    pub city: Option<City>,
+
+    /// Show made up departures and disruptions, to try the board without network access
+    #[arg(long, conflicts_with = "city")]
+    pub demo: bool,
+
+    /// Show the board as of this time (`YYYY-MM-DDTHH:MM`) from the static `timetable`
+    #[arg(long, conflicts_with = "demo", value_parser = parse_date_time)]
+    pub at: Option<NaiveDateTime>,
+
+    /// Power source to assume instead of detecting it, on battery refreshes and redraws
+    /// are throttled as configured in `[battery]`
+    #[arg(long, value_enum, default_value_t)]
+    pub power: PowerSource,
+
+    /// How to draw the board
+    #[arg(long, value_enum, default_value_t, env = "OEFFI_OUTPUT")]
+    pub output: Output,
+
+    /// Screen reader friendly output, short for `--output a11y`
+    #[arg(long, conflicts_with = "output")]
+    pub a11y: bool,
+
+    /// Run in a container: plain log output, short for `--output plain`
+    #[arg(long, conflicts_with_all = ["output", "a11y"], env = "OEFFI_CONTAINER")]
+    pub container: bool,
+
+    /// Draw this many frames, then print how long fetching, parsing and drawing took
+    #[arg(long, value_name = "FRAMES", num_args = 0..=1, default_missing_value = "60")]
+    pub profile_render: Option<usize>,
+
+    /// Write the board to this file once there is data to show, then exit
+    #[arg(long, value_name = "FILE")]
+    pub snapshot: Option<PathBuf>,
+
+    /// Keep the colors of the snapshot as ANSI codes
+    #[arg(long, requires = "snapshot")]
+    pub snapshot_ansi: bool,
+
+    /// Print the effective configuration as annotated TOML and exit
+    #[arg(long)]
+    pub explain_config: bool,
+
+    /// Check the configuration for unknown keys, invalid stop IDs and overlapping profiles,
+    /// print every problem with its line and exit, unsuccessfully if there were errors
+    #[arg(long)]
+    pub check_config: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the next departures in another format instead of showing the board
+    #[command(subcommand)]
+    Export(ExportFormat),
+
+    /// Request the API for the monitors connecting to `daemon_socket`, so several screens
+    /// share one stream of requests
+    Daemon,
+
+    /// Check API access, unknown response fields, the configured stops and the terminal, and print a report
+    Doctor,
+
+    /// Print the next itineraries between two stops or addresses, e.g. `route Rathaus Praterstern`
+    Route {
+        from: String,
+        to: String,
+
+        /// Number of itineraries to print
+        #[arg(long, short = 'n', default_value_t = 3)]
+        count: usize,
+    },
+
+    /// Print the other stop IDs of the station a stop belongs to, e.g. the opposite direction,
+    /// and offer to add them to the config file; Vienna only
+    Siblings {
+        stop_id: i32,
+
+        /// Copy of the Wiener Linien stops dataset (`wienerlinien-ogd-haltepunkte.csv`)
+        /// to look them up in instead of downloading it
+        #[arg(long)]
+        dataset: Option<PathBuf>,
+    },
+
+    /// Replace this binary with the one of the latest GitHub release, after verifying its checksum
+    SelfUpdate {
+        /// Only print whether a newer version is available
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Request the API and pretty-print the raw JSON response
+    Raw {
+        /// Stop to request, may be given multiple times; the configured stops if none
+        #[arg(long = "stop-id", short)]
+        stop_ids: Vec<i32>,
+
+        /// Only print the part of the response at this JSON pointer, e.g. `/data/monitors/0`
+        #[arg(long)]
+        pointer: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportFormat {
+    /// iCalendar, one event per departure
+    Ics {
+        /// Number of departures to export
+        #[arg(long, short = 'n', default_value_t = 10)]
+        count: usize,
+
+        /// Only export departures of this line, e.g. `u3` or `43/44`, may be given multiple times
+        #[arg(long = "line", short)]
+        lines: Vec<String>,
+    },
+}
+
+fn parse_time_of_day(input: &str) -> Result<NaiveTime, chrono::ParseError> {
+    NaiveTime::parse_from_str(input, "%H:%M")
+}
+
+fn parse_date_time(input: &str) -> Result<NaiveDateTime, chrono::ParseError> {
+    NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M")
+}
@@ -0,0 +1,44 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{
+    env,
+    io::{self, stdout, Write},
+};
+
+/// The system clipboard, or the terminal's where it can't be reached
+pub struct Clipboard {
+    /// Kept open for as long as the monitor runs, on X11 what was copied is
+    /// only handed out while it is
+    system: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    /// Open the system clipboard, unless there is none, e.g. without a display
+    /// server, or it is another machine's, over SSH
+    pub fn new() -> Self {
+        let remote = env::var_os("SSH_CONNECTION").is_some() || env::var_os("SSH_TTY").is_some();
+        Clipboard {
+            system: match remote {
+                true => None,
+                false => arboard::Clipboard::new().ok(),
+            },
+        }
+    }
+
+    /// Put `text` on the system clipboard, falling back to the terminal's
+    pub fn copy(&mut self, text: &str) -> io::Result<()> {
+        match self.system.as_mut().map(|system| system.set_text(text)) {
+            Some(Ok(())) => Ok(()),
+            _ => osc52(text),
+        }
+    }
+}
+
+/// Put `text` on the clipboard through the terminal, with an OSC 52 escape sequence
+///
+/// This also works over SSH and needs no display server, but some terminals
+/// have to be told to allow it.
+fn osc52(text: &str) -> io::Result<()> {
+    let mut stdout = stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
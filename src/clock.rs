@@ -0,0 +1,56 @@
+use chrono::{DateTime, Datelike, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::locale::Lang;
+
+const WEEKDAYS_DE: [&str; 7] = [
+    "Montag",
+    "Dienstag",
+    "Mittwoch",
+    "Donnerstag",
+    "Freitag",
+    "Samstag",
+    "Sonntag",
+];
+
+/// What the footer clock shows besides the time
+#[derive(Debug, Clone, Default, Hash, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ClockSettings {
+    /// Date as day.month.year
+    pub date: bool,
+    pub weekday: bool,
+    /// Weekday names in German, "Freitag" instead of "Friday", also with `--lang de`
+    pub german: bool,
+    /// ISO week number
+    pub week: bool,
+}
+
+impl ClockSettings {
+    /// Weekday, date and week shown before the time, `None` if all are off
+    ///
+    /// This only changes once a day, so it is rendered into the board directly.
+    pub fn date_label(&self, now: &DateTime<Local>, lang: Lang) -> Option<String> {
+        let german = self.german || lang == Lang::De;
+        let mut parts = vec![];
+        if self.weekday {
+            parts.push(if german {
+                WEEKDAYS_DE[now.weekday().num_days_from_monday() as usize].to_string()
+            } else {
+                now.format("%A").to_string()
+            });
+        }
+        if self.date {
+            parts.push(now.format("%d.%m.%Y").to_string());
+        }
+        if self.week {
+            let week = now.iso_week().week();
+            parts.push(if german {
+                format!("KW {}", week)
+            } else {
+                format!("W{}", week)
+            });
+        }
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
@@ -0,0 +1,78 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{io, os::unix::fs::FileTypeExt, path::Path};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{mpsc, oneshot},
+};
+
+/// A command sent to the control socket as one JSON object per line,
+/// e.g. `{"command": "set-profile", "profile": "work"}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum ControlCommand {
+    ReloadConfig,
+    SetProfile { profile: String },
+    Pause,
+    Resume,
+    DumpDepartures,
+}
+
+/// A command together with the channel its JSON response goes back on
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply: oneshot::Sender<Value>,
+}
+
+/// Listen on a Unix socket at `path` and forward incoming commands to the returned channel
+pub fn listen(path: &Path) -> io::Result<mpsc::Receiver<ControlRequest>> {
+    // a socket left behind by a previous run would make binding fail
+    if std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle_connection(stream, tx.clone()));
+        }
+    });
+    Ok(rx)
+}
+
+async fn handle_connection(stream: UnixStream, tx: mpsc::Sender<ControlRequest>) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let response = match serde_json::from_str::<ControlCommand>(&line) {
+            Ok(command) => {
+                let (reply, response) = oneshot::channel();
+                if tx.send(ControlRequest { command, reply }).await.is_err() {
+                    break;
+                }
+                response
+                    .await
+                    .unwrap_or_else(|_| error("monitor shut down"))
+            }
+            Err(e) => error(&e.to_string()),
+        };
+        if write
+            .write_all(format!("{}\n", response).as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+pub fn ok() -> Value {
+    json!({ "ok": true })
+}
+
+pub fn error(message: &str) -> Value {
+    json!({ "ok": false, "error": message })
+}
@@ -0,0 +1,55 @@
+use iso8601_timestamp::Timestamp;
+use serde::{Deserialize, Serialize};
+
+use crate::Departure;
+
+/// Where the minutes until a departure come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CountdownSource {
+    /// The `countdown` the API reports
+    #[default]
+    Api,
+    /// Counted from the realtime, or else the planned, departure time
+    Computed,
+}
+
+/// Which countdown to show, see `[countdown]` in the config
+///
+/// The API's countdown and the one computed from the departure time are off
+/// by a minute now and then, `debug` shows both next to each other.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CountdownSettings {
+    pub source: CountdownSource,
+    /// Add a column with the API's and the computed countdown
+    pub debug: bool,
+}
+
+impl CountdownSettings {
+    /// Count down the departures as configured, right after they were parsed
+    pub fn apply(&self, departures: &mut [Departure]) {
+        if self.source == CountdownSource::Api && !self.debug {
+            return;
+        }
+        let now = Timestamp::now_utc();
+        for dep in departures {
+            let computed = computed(dep, now);
+            if self.debug {
+                dep.countdowns = Some((dep.countdown, computed));
+            }
+            if self.source == CountdownSource::Computed {
+                dep.countdown = computed;
+            }
+        }
+    }
+}
+
+/// Whole minutes from `now` until `dep` leaves
+pub fn computed(dep: &Departure, now: Timestamp) -> i64 {
+    dep.time_real
+        .unwrap_or(dep.time_planned)
+        .duration_since(now)
+        .whole_minutes()
+        .max(0)
+}
@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    os::unix::fs::FileTypeExt,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+use crate::{provider::Provider, shutdown::Shutdown, ApiRequestError};
+
+/// Latest responses for a set of stops and when they were requested, locked while requesting
+type Entry = Arc<tokio::sync::Mutex<Option<(Instant, Vec<String>)>>>;
+
+/// A monitor asking the daemon for responses, one JSON object per line
+#[derive(Debug, Deserialize, Serialize)]
+struct DaemonRequest {
+    stops: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum DaemonReply {
+    /// Raw responses as [`Provider::fetch`] returns them
+    Responses(Vec<String>),
+    Error {
+        message: String,
+        /// Seconds the API asked to wait after a rate limit
+        retry_after: Option<u64>,
+    },
+}
+
+/// Answer monitors connecting to the Unix socket at `path` with responses of
+/// `provider`, requesting each set of stops at most once per `max_age`, until
+/// SIGTERM or SIGINT
+///
+/// Monitors with `daemon_socket` set thereby share one stream of API requests,
+/// however many screens show them.
+pub async fn run(path: &Path, provider: Provider, max_age: Duration) -> Result<()> {
+    // a socket left behind by a previous run would make binding fail
+    if std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    let shutdown = Shutdown::new().context("Failed to listen for signals")?;
+    let entries: Arc<Mutex<HashMap<Vec<i32>, Entry>>> = Arc::default();
+
+    while !shutdown.is_requested() {
+        tokio::select! {
+            accepted = listener.accept() => {
+                if let Ok((stream, _)) = accepted {
+                    tokio::spawn(serve(stream, provider.clone(), entries.clone(), max_age));
+                }
+            }
+            _ = shutdown.sleep(Duration::MAX) => {}
+        }
+    }
+    std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+async fn serve(
+    stream: UnixStream,
+    provider: Provider,
+    entries: Arc<Mutex<HashMap<Vec<i32>, Entry>>>,
+    max_age: Duration,
+) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let reply = match serde_json::from_str::<DaemonRequest>(&line) {
+            Ok(request) => {
+                let entry = match entries.lock() {
+                    Ok(mut entries) => entries.entry(request.stops.clone()).or_default().clone(),
+                    Err(_) => break,
+                };
+                // monitors asking for the same stops meanwhile wait for this request
+                let mut entry = entry.lock().await;
+                match entry.as_ref() {
+                    Some((requested, responses)) if requested.elapsed() < max_age => {
+                        DaemonReply::Responses(responses.clone())
+                    }
+                    _ => match provider.fetch(&request.stops).await {
+                        Ok(responses) => {
+                            *entry = Some((Instant::now(), responses.clone()));
+                            DaemonReply::Responses(responses)
+                        }
+                        Err(e) => DaemonReply::Error {
+                            message: e.to_string(),
+                            retry_after: match e {
+                                ApiRequestError::RateLimited { retry_after } => {
+                                    retry_after.map(|after| after.as_secs())
+                                }
+                                _ => None,
+                            },
+                        },
+                    },
+                }
+            }
+            Err(e) => DaemonReply::Error {
+                message: e.to_string(),
+                retry_after: None,
+            },
+        };
+        let Ok(reply) = serde_json::to_string(&reply) else {
+            break;
+        };
+        if write
+            .write_all(format!("{}\n", reply).as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Raw responses for `stops` from the daemon listening at `path`, `None` if it isn't running
+pub async fn fetch(path: &Path, stops: &[i32]) -> Option<Result<Vec<String>, ApiRequestError>> {
+    let stream = UnixStream::connect(path).await.ok()?;
+    let (read, mut write) = stream.into_split();
+    let request = serde_json::to_string(&DaemonRequest {
+        stops: stops.to_vec(),
+    })
+    .ok()?;
+    write
+        .write_all(format!("{}\n", request).as_bytes())
+        .await
+        .ok()?;
+    let line = BufReader::new(read).lines().next_line().await.ok()??;
+    Some(match serde_json::from_str(&line) {
+        Ok(DaemonReply::Responses(responses)) => Ok(responses),
+        Ok(DaemonReply::Error {
+            retry_after: Some(after),
+            ..
+        }) => Err(ApiRequestError::RateLimited {
+            retry_after: Some(Duration::from_secs(after)),
+        }),
+        Ok(DaemonReply::Error { message, .. }) => Err(ApiRequestError::Daemon(message)),
+        Err(e) => Err(ApiRequestError::JsonParsingFailed(e)),
+    })
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Duration, Local, Timelike};
+use serde_json::{json, Value};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// Departures generated per line
+const DEPARTURES_PER_LINE: i64 = 3;
+
+/// Name, vehicle type, destination, platform and minutes between departures of a line
+type DemoLine = (&'static str, &'static str, &'static str, &'static str, i64);
+
+/// Stations the demo stops are named after, with the lines leaving there
+const STATIONS: &[(&str, &[DemoLine])] = &[
+    (
+        "Schottentor",
+        &[
+            ("43", "ptTram", "Neuwaldegg", "C", 6),
+            ("44", "ptTram", "Maroltingergasse", "C", 8),
+            ("U2", "ptMetro", "Seestadt", "2", 5),
+            ("40A", "ptBusCity", "Döblinger Friedhof", "A", 10),
+        ],
+    ),
+    (
+        "Volkstheater",
+        &[
+            ("U3", "ptMetro", "Simmering", "1", 4),
+            ("49", "ptTram", "Hütteldorf, Bujattigasse", "B", 7),
+            ("48A", "ptBusCity", "Baumgartner Höhe", "A", 9),
+        ],
+    ),
+    (
+        "Auerspergstraße",
+        &[
+            ("46", "ptTram", "Joachimsthalerplatz", "", 8),
+            ("N46", "ptBusNight", "Hütteldorfer Straße", "", 30),
+        ],
+    ),
+    (
+        "Karlsplatz",
+        &[
+            ("U1", "ptMetro", "Leopoldau", "1", 4),
+            ("U4", "ptMetro", "Heiligenstadt", "2", 5),
+            ("62", "ptTram", "Lainz, Wolkersbergenstraße", "D", 10),
+            ("2A", "ptBusCity", "Graben, Petersplatz", "A", 12),
+        ],
+    ),
+];
+
+const OCCUPANCY: &[&str] = &[
+    "MANY_SEATS_AVAILABLE",
+    "FEW_SEATS_AVAILABLE",
+    "STANDING_ROOM_ONLY",
+    "FULL",
+];
+
+/// A Wiener Linien monitor response with made up departures and disruptions for `stops`,
+/// each named after one of a few stations
+///
+/// Departures follow a fixed interval per line, delays and occupancy are
+/// derived from the departure itself, so refreshes agree with each other.
+pub fn response(stops: &[i32]) -> String {
+    let now = Local::now().with_second(0).unwrap_or_else(Local::now);
+    let mut monitors = vec![];
+    // every station once, further stops stay empty
+    for (stop, (station, lines)) in stops.iter().zip(STATIONS) {
+        monitors.push(json!({
+            "locationStop": {
+                "properties": { "title": station, "attributes": { "rbl": stop } }
+            },
+            "lines": lines
+                .iter()
+                .map(|line| demo_line(line, *stop, now))
+                .collect::<Vec<_>>(),
+        }));
+    }
+
+    // a disruption coming and going every quarter hour, next to one that stays
+    let mut traffic_infos = vec![json!({
+        "name": "demo_elevator",
+        "priority": "3",
+        "title": "U3 Volkstheater: Aufzug außer Betrieb",
+        "description": "Der Aufzug zur Bahnsteigebene ist außer Betrieb.",
+        "relatedLines": ["U3"],
+    })];
+    if (now.minute() / 15).is_multiple_of(2) {
+        traffic_infos.push(json!({
+            "name": "demo_obstruction",
+            "priority": "1",
+            "title": "43: Fahrtbehinderung",
+            "description": "Wegen eines Verkehrsunfalls kommt es zu unregelmäßigen Intervallen.",
+            "relatedLines": ["43", "44"],
+        }));
+    }
+
+    json!({
+        "data": { "monitors": monitors, "trafficInfos": traffic_infos },
+        "message": { "value": "OK", "messageCode": 1 },
+    })
+    .to_string()
+}
+
+fn demo_line(
+    (name, vehicle_type, towards, platform, interval): &DemoLine,
+    stop: i32,
+    now: DateTime<Local>,
+) -> Value {
+    let minute_of_day = i64::from(now.hour() * 60 + now.minute());
+    // lines don't all leave at the same minute
+    let offset = (noise(&(name, stop)) % *interval as u64) as i64;
+    let first = (interval - (minute_of_day + offset) % interval) % interval;
+
+    let departures: Vec<Value> = (0..DEPARTURES_PER_LINE)
+        .map(|n| {
+            let countdown = first + n * interval;
+            let planned = now + Duration::minutes(countdown);
+            let seed = noise(&(name, stop, planned.timestamp()));
+            // most departures are on time, some a few minutes late
+            let delay = match seed % 10 {
+                0..=6 => 0,
+                7 | 8 => 1 + (seed / 10 % 3) as i64,
+                _ => 4 + (seed / 10 % 6) as i64,
+            };
+            let real = planned + Duration::minutes(delay);
+            json!({
+                "departureTime": {
+                    "timePlanned": timestamp(planned),
+                    "timeReal": timestamp(real),
+                    "countdown": countdown + delay,
+                },
+                "occupancy": OCCUPANCY[(seed / 100) as usize % OCCUPANCY.len()],
+            })
+        })
+        .collect();
+
+    json!({
+        "name": name,
+        "towards": towards,
+        "type": vehicle_type,
+        "platform": platform,
+        "trafficjam": *vehicle_type == "ptBusCity" && noise(&(name, minute_of_day / 10)) % 5 == 0,
+        "departures": { "departure": departures },
+    })
+}
+
+fn noise(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn timestamp(time: DateTime<Local>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string()
+}
@@ -0,0 +1,140 @@
+use iso8601_timestamp::Timestamp;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::{animation::RowState, Departure};
+
+/// Identifies a departure across refreshes, its realtime data may change
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DepartureKey {
+    line: String,
+    station: String,
+    destination: String,
+    time_planned: Timestamp,
+}
+
+impl DepartureKey {
+    pub fn of(dep: &Departure) -> Self {
+        DepartureKey {
+            line: dep.line.name.clone(),
+            station: dep.station_name.clone(),
+            destination: dep.destination_name.clone(),
+            time_planned: dep.time_planned,
+        }
+    }
+}
+
+/// Drop departures of a line and destination planned within `tolerance` of an earlier one
+///
+/// Monitoring several platforms of one station can report a departure once per platform.
+pub fn dedup(departures: Vec<Departure>, tolerance: Duration) -> Vec<Departure> {
+    let tolerance = tolerance.as_secs_f64();
+    let mut kept: Vec<Departure> = Vec::with_capacity(departures.len());
+    for dep in departures {
+        let duplicate = kept.iter().any(|other| {
+            other.line.name == dep.line.name
+                && other.destination_name == dep.destination_name
+                && dep
+                    .time_planned
+                    .duration_since(other.time_planned)
+                    .abs()
+                    .as_seconds_f64()
+                    <= tolerance
+        });
+        if !duplicate {
+            kept.push(dep);
+        }
+    }
+    kept
+}
+
+/// A departure together with when it vanished from the API data
+#[derive(Debug, Clone)]
+pub struct TrackedDeparture {
+    pub departure: Departure,
+    /// Set once a refresh no longer contained the departure
+    pub removed: Option<Instant>,
+    /// Set once the countdown of the departure reached zero
+    pub due: Option<Instant>,
+}
+
+/// Departures kept across refreshes, updated in place instead of replaced
+///
+/// Departures missing from a refresh are only marked as removed, so the board
+/// can still show them on their way out; [`DepartureSet::prune`] drops them.
+#[derive(Debug, Default)]
+pub struct DepartureSet {
+    entries: HashMap<DepartureKey, TrackedDeparture>,
+}
+
+impl DepartureSet {
+    /// Merge the departures of a refresh into the set
+    pub fn apply(&mut self, departures: &[Departure]) {
+        let now = Instant::now();
+        let mut seen = HashSet::with_capacity(departures.len());
+
+        for dep in departures {
+            let key = DepartureKey::of(dep);
+            let entry = self
+                .entries
+                .entry(key.clone())
+                .or_insert_with(|| TrackedDeparture {
+                    departure: dep.clone(),
+                    removed: None,
+                    due: None,
+                });
+            entry.departure.line = dep.line.clone();
+            entry.departure.time_real = dep.time_real;
+            entry.departure.countdown = dep.countdown;
+            entry.departure.countdowns = dep.countdowns;
+            entry.departure.occupancy = dep.occupancy;
+            entry.departure.traffic_jam = dep.traffic_jam;
+            entry.departure.platform = dep.platform.clone();
+            // set by the row script on every refresh, e.g. from the countdown
+            entry.departure.note = dep.note.clone();
+            entry.removed = None;
+            if dep.countdown <= 0 && entry.due.is_none() {
+                entry.due = Some(now);
+            }
+            seen.insert(key);
+        }
+
+        for (key, entry) in self.entries.iter_mut() {
+            if entry.removed.is_none() && !seen.contains(key) {
+                entry.removed = Some(now);
+            }
+        }
+    }
+
+    /// Forget departures that were removed before `before`
+    pub fn prune(&mut self, before: Instant) {
+        self.entries
+            .retain(|_, entry| entry.removed.is_none_or(|removed| removed >= before));
+    }
+
+    /// Departures of the latest refresh, in board order
+    pub fn current(&self) -> Vec<&Departure> {
+        let mut departures: Vec<&Departure> = self
+            .entries
+            .values()
+            .filter(|entry| entry.removed.is_none())
+            .map(|entry| &entry.departure)
+            .collect();
+        departures.sort();
+        departures
+    }
+
+    /// Departures to draw at `now` and how, including those still on their way out
+    /// and those that left less than `keep_departed` ago
+    pub fn board(&self, now: Instant, keep_departed: Duration) -> Vec<(&Departure, RowState)> {
+        let mut rows: Vec<(&Departure, RowState)> = self
+            .entries
+            .values()
+            .filter_map(|entry| Some((&entry.departure, RowState::of(entry, now, keep_departed)?)))
+            .collect();
+        rows.sort_by(|a, b| a.0.cmp(b.0));
+        rows
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::Departure;
+
+/// Words kept in lower case inside a name, "Strasshof an der Nordbahn"
+const LOWERCASE_WORDS: [&str; 14] = [
+    "am", "an", "auf", "bei", "der", "die", "das", "dem", "den", "des", "im", "und", "zum", "zur",
+];
+
+/// Abbreviations the API uses, expanded where they make up a whole word
+const ABBREVIATIONS: [(&str, &str); 5] = [
+    ("Bf.", "Bahnhof"),
+    ("Bhf.", "Bahnhof"),
+    ("Hbf.", "Hauptbahnhof"),
+    ("Str.", "Straße"),
+    ("Pl.", "Platz"),
+];
+
+/// Abbreviations the API uses, expanded where they end a word, "Wolkersbergenstr.",
+/// with the letters the word has to have before them at least
+///
+/// "g." needs a longer stem, so titles like "Ing." aren't taken for a "gasse".
+const SUFFIXES: [(&str, &str, usize); 4] = [
+    ("str.", "straße", 1),
+    ("pl.", "platz", 1),
+    ("g.", "gasse", 4),
+    ("friedh.", "friedhof", 1),
+];
+
+/// Cleanup of the destination names the API reports, see `[destinations]` in the config
+///
+/// Names in all caps like "FRIEDRICH-ENGELS-PLATZ" are written the way German
+/// is, "Friedrich-Engels-Platz", and abbreviations are spelled out.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DestinationNames {
+    /// Write names in all caps in upper and lower case
+    pub title_case: bool,
+    /// Spell out the abbreviations the API is known to use
+    pub expand: bool,
+    /// Further words to replace, e.g. `"Krkhs." = "Krankenhaus"`
+    pub abbreviations: BTreeMap<String, String>,
+}
+
+impl Default for DestinationNames {
+    fn default() -> Self {
+        DestinationNames {
+            title_case: true,
+            expand: true,
+            abbreviations: BTreeMap::new(),
+        }
+    }
+}
+
+impl DestinationNames {
+    /// Clean up the destination name of every departure
+    pub fn apply(&self, departures: &mut [Departure]) {
+        for dep in departures {
+            dep.destination_name = self.clean(&dep.destination_name);
+        }
+    }
+
+    fn clean(&self, name: &str) -> String {
+        let name = match self.title_case && !name.chars().any(char::is_lowercase) {
+            true => title_case(name),
+            false => name.to_string(),
+        };
+        name.split(' ')
+            .map(|word| self.expand(word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// `word` spelled out if it is an abbreviation or ends with one
+    fn expand(&self, word: &str) -> String {
+        if let Some(expanded) = self.abbreviations.get(word) {
+            return expanded.clone();
+        }
+        if !self.expand {
+            return word.to_string();
+        }
+        if let Some((_, expanded)) = ABBREVIATIONS
+            .iter()
+            .find(|(abbreviation, _)| abbreviation.eq_ignore_ascii_case(word))
+        {
+            return expanded.to_string();
+        }
+        for (suffix, expanded, min_stem) in SUFFIXES {
+            let Some(stem) = word.len().checked_sub(suffix.len()).and_then(|at| {
+                word.get(at..)
+                    .filter(|end| end.eq_ignore_ascii_case(suffix))
+                    .map(|_| &word[..at])
+            }) else {
+                continue;
+            };
+            // "G." alone is too short to tell what it stands for, as is the
+            // "In" of "Dipl.-Ing."
+            let letters = stem.chars().rev().take_while(|c| c.is_alphabetic()).count();
+            if letters >= min_stem {
+                return format!("{}{}", stem, expanded);
+            }
+        }
+        word.to_string()
+    }
+}
+
+/// "STRASSHOF AN DER NORDBAHN" as "Strasshof an der Nordbahn"
+///
+/// Parts of a word after a hyphen or slash start upper case too, words like
+/// "U4" or "S45" mixing letters and digits stay as they are. "STRASSE" at the
+/// end of a word is written "Straße", which all caps can't show.
+fn title_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for (index, word) in name.split(' ').enumerate() {
+        if index > 0 {
+            result.push(' ');
+        }
+        let lower = eszett(&word.to_lowercase());
+        if index > 0 && LOWERCASE_WORDS.contains(&lower.as_str()) {
+            result.push_str(&lower);
+        } else if word.chars().any(|c| c.is_ascii_digit()) {
+            result.push_str(word);
+        } else {
+            let mut start = true;
+            for c in lower.chars() {
+                match start {
+                    true => result.extend(c.to_uppercase()),
+                    false => result.push(c),
+                }
+                start = matches!(c, '-' | '/' | '(' | '.');
+            }
+        }
+    }
+    result
+}
+
+/// "strasse" at the end of `word`, or of a part of it between hyphens, as "straße"
+fn eszett(word: &str) -> String {
+    word.split('-')
+        .map(|part| match part.strip_suffix("strasse") {
+            Some(stem) => format!("{}straße", stem),
+            None => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_suffixes_after_a_long_enough_stem() {
+        let names = DestinationNames::default();
+        assert_eq!(names.clean("Wolkersbergenstr."), "Wolkersbergenstraße");
+        assert_eq!(names.clean("Schulg."), "Schulgasse");
+        assert_eq!(names.clean("Ing."), "Ing.");
+        assert_eq!(names.clean("Dipl.-Ing."), "Dipl.-Ing.");
+        assert_eq!(names.clean("G."), "G.");
+    }
+
+    #[test]
+    fn writes_strasse_with_eszett() {
+        let names = DestinationNames::default();
+        assert_eq!(names.clean("MARIAHILFER STRASSE"), "Mariahilfer Straße");
+        assert_eq!(names.clean("HAUPTSTRASSE-NORD"), "Hauptstraße-Nord");
+        assert_eq!(
+            names.clean("STRASSHOF AN DER NORDBAHN"),
+            "Strasshof an der Nordbahn"
+        );
+    }
+}
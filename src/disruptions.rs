@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant};
+
+use crate::WienerLinienTrafficInfo;
+
+/// How long a newly appeared disruption is marked as new
+const NEW_BADGE: Duration = Duration::from_secs(5 * 60);
+
+/// A disruption together with when it first appeared
+#[derive(Debug, Clone)]
+struct TrackedDisruption {
+    info: WienerLinienTrafficInfo,
+    /// `None` for disruptions that were already there on the first refresh
+    appeared: Option<Instant>,
+}
+
+/// A disruption as shown on the board
+#[derive(Debug, Clone, Copy)]
+pub struct Disruption<'a> {
+    pub info: &'a WienerLinienTrafficInfo,
+    /// Appeared within the last few minutes
+    pub is_new: bool,
+}
+
+/// Disruptions of the latest refresh without duplicates, remembering when each appeared
+#[derive(Debug, Default)]
+pub struct DisruptionSet {
+    entries: Vec<TrackedDisruption>,
+    refreshed: bool,
+}
+
+impl DisruptionSet {
+    /// Replace the disruptions with those of a refresh
+    ///
+    /// Entries are the same disruption if they share a name, or title and
+    /// description, or affect the same lines under the same title apart from
+    /// case, punctuation and spacing; only the first of them is kept.
+    pub fn apply(&mut self, infos: &[WienerLinienTrafficInfo]) {
+        let now = Instant::now();
+        let mut entries: Vec<TrackedDisruption> = Vec::with_capacity(infos.len());
+
+        for info in infos {
+            if entries.iter().any(|entry| is_same(&entry.info, info)) {
+                continue;
+            }
+            let previous = self.entries.iter().find(|entry| is_same(&entry.info, info));
+            let appeared = match previous {
+                Some(previous) => previous.appeared,
+                None if self.refreshed => Some(now),
+                None => None,
+            };
+            entries.push(TrackedDisruption {
+                info: info.clone(),
+                appeared,
+            });
+        }
+
+        self.entries = entries;
+        self.refreshed = true;
+    }
+
+    /// Disruptions to show at `now`, in API order
+    pub fn current(&self, now: Instant) -> Vec<Disruption<'_>> {
+        self.entries
+            .iter()
+            .map(|entry| Disruption {
+                info: &entry.info,
+                is_new: entry
+                    .appeared
+                    .is_some_and(|appeared| now.saturating_duration_since(appeared) < NEW_BADGE),
+            })
+            .collect()
+    }
+}
+
+/// Whether `a` and `b` report the same disruption
+///
+/// The API repeats a disruption once per stop, with the text worded slightly
+/// differently each time, so the title is compared loosely.
+fn is_same(a: &WienerLinienTrafficInfo, b: &WienerLinienTrafficInfo) -> bool {
+    if !a.name.is_empty() && a.name == b.name {
+        return true;
+    }
+    if a.title == b.title && a.description == b.description {
+        return true;
+    }
+    normalized(&a.title) == normalized(&b.title) && lines(a) == lines(b)
+}
+
+/// `text` in lower case with only its letters and digits, one space between words
+fn normalized(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Lines a disruption affects, in order and without repeats
+fn lines(info: &WienerLinienTrafficInfo) -> Vec<&str> {
+    let mut lines: Vec<&str> = info.related_lines.iter().map(String::as_str).collect();
+    lines.sort_unstable();
+    lines.dedup();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str, title: &str, description: &str, lines: &[&str]) -> WienerLinienTrafficInfo {
+        WienerLinienTrafficInfo {
+            priority: None,
+            name: name.to_string(),
+            title: title.to_string(),
+            description: description.to_string(),
+            related_lines: lines.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn keeps_one_of_near_duplicates() {
+        let mut set = DisruptionSet::default();
+        set.apply(&[
+            info(
+                "ma_1",
+                "U6: Fahrtbehinderung",
+                "Zwischen Spittelau und Floridsdorf",
+                &["U6"],
+            ),
+            info("ma_2", "U6 - Fahrtbehinderung ", "Bei Spittelau", &["U6"]),
+            info(
+                "ma_3",
+                "U6: Fahrtbehinderung",
+                "Bei Handelskai",
+                &["U6", "31"],
+            ),
+        ]);
+        let names: Vec<&str> = set
+            .current(Instant::now())
+            .iter()
+            .map(|disruption| disruption.info.name.as_str())
+            .collect();
+        assert_eq!(names, ["ma_1", "ma_3"]);
+    }
+}
@@ -0,0 +1,187 @@
+use crossterm::terminal::size;
+use serde_json::Value;
+use std::{
+    env,
+    io::{stdout, IsTerminal},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    get_data_from_api,
+    provider::Provider,
+    settings::Settings,
+    theme::{self, ColorSupport},
+    unknown_fields, WienerLinienAPIRequest, API_URL,
+};
+
+/// Requests slower than this are reported as a warning
+const SLOW_REQUEST: Duration = Duration::from_secs(2);
+
+enum Status {
+    Ok,
+    Warning,
+    Failed,
+}
+
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn check(&mut self, status: Status, name: &str, detail: &str) {
+        let mark = match status {
+            Status::Ok => "✓",
+            Status::Warning => "!",
+            Status::Failed => {
+                self.failures += 1;
+                "✗"
+            }
+        };
+        println!("{} {:<24} {}", mark, name, detail);
+    }
+}
+
+/// Check the API, its response fields, every configured stop and the terminal, printing a report
+///
+/// Returns the number of failed checks.
+pub async fn run(settings: &Settings) -> usize {
+    let mut report = Report { failures: 0 };
+
+    println!("API");
+    let provider = settings.provider();
+    let started = Instant::now();
+    let response = provider.fetch(&settings.stops).await;
+    let latency = started.elapsed();
+    match &response {
+        Ok(_) if latency > SLOW_REQUEST => report.check(
+            Status::Warning,
+            "reachable",
+            &format!("slow response after {} ms", latency.as_millis()),
+        ),
+        Ok(_) => report.check(
+            Status::Ok,
+            "reachable",
+            &format!("responded in {} ms", latency.as_millis()),
+        ),
+        Err(e) => report.check(Status::Failed, "reachable", &e.to_string()),
+    }
+
+    // EFA and HAFAS responses are read leniently and have no known set of fields
+    if let (
+        Ok(responses),
+        Provider::WienerLinien | Provider::Mirror { .. } | Provider::Demo | Provider::Timetable(_),
+    ) = (&response, &provider)
+    {
+        println!("\nSchema");
+        let mut unknown = vec![];
+        for text in responses {
+            match unknown_fields(text) {
+                Ok(fields) => unknown.extend(fields),
+                Err(e) => report.check(Status::Failed, "response", &format!("invalid JSON: {}", e)),
+            }
+        }
+        unknown.sort_unstable();
+        unknown.dedup();
+        if unknown.is_empty() {
+            report.check(Status::Ok, "fields", "no unknown fields");
+        }
+        for field in unknown {
+            report.check(
+                Status::Warning,
+                &field,
+                "unknown field, renamed or new in the API?",
+            );
+        }
+    }
+
+    // checking the stops one by one would fail the same way if the API is unreachable
+    let stops = if report.failures == 0 {
+        println!("\nStops");
+        settings.stops.as_slice()
+    } else {
+        &[]
+    };
+    for stop in stops {
+        let name = stop.to_string();
+        if !matches!(provider, Provider::WienerLinien) {
+            // only Wiener Linien reports unknown stops, elsewhere they just have no departures
+            match provider
+                .fetch(&[*stop])
+                .await
+                .and_then(|responses| provider.parse(&responses, &settings.lines))
+            {
+                Ok(((departures, _), _)) if !departures.is_empty() => {
+                    let mut lines: Vec<_> = departures
+                        .iter()
+                        .map(|dep| dep.line.name.as_str())
+                        .collect();
+                    lines.sort_unstable();
+                    lines.dedup();
+                    let detail = format!("{} ({})", departures[0].station_name, lines.join(", "));
+                    report.check(Status::Ok, &name, &detail)
+                }
+                Ok(_) => report.check(Status::Warning, &name, "no departures, unknown stop?"),
+                Err(e) => report.check(Status::Failed, &name, &e.to_string()),
+            }
+            continue;
+        }
+        let response = get_data_from_api(&WienerLinienAPIRequest {
+            url: API_URL.to_string(),
+            traffic_info: "stoerunglang".to_string(),
+            stop_id: vec![*stop],
+        })
+        .await;
+        match response.map(|text| serde_json::from_str::<Value>(&text)) {
+            Ok(Ok(json)) => match json["data"]["monitors"].as_array() {
+                Some(monitors) if !monitors.is_empty() => {
+                    let title = monitors[0]["locationStop"]["properties"]["title"]
+                        .as_str()
+                        .unwrap_or("unnamed");
+                    let lines = monitors
+                        .iter()
+                        .filter_map(|monitor| monitor["lines"].as_array())
+                        .flatten()
+                        .filter_map(|line| line["name"].as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    report.check(Status::Ok, &name, &format!("{} ({})", title, lines));
+                }
+                _ => report.check(
+                    Status::Failed,
+                    &name,
+                    json["message"]["value"]
+                        .as_str()
+                        .unwrap_or("unknown stop, no monitor data"),
+                ),
+            },
+            Ok(Err(e)) => report.check(Status::Failed, &name, &format!("invalid JSON: {}", e)),
+            Err(e) => report.check(Status::Failed, &name, &e.to_string()),
+        }
+    }
+
+    println!("\nTerminal");
+    if stdout().is_terminal() {
+        report.check(Status::Ok, "stdout", "is a terminal");
+    } else {
+        report.check(Status::Warning, "stdout", "is not a terminal");
+    }
+    match size() {
+        Ok((width, height)) if width < 60 || height < 10 => report.check(
+            Status::Warning,
+            "size",
+            &format!("{}x{}, the board needs at least 60x10", width, height),
+        ),
+        Ok((width, height)) => report.check(Status::Ok, "size", &format!("{}x{}", width, height)),
+        Err(e) => report.check(Status::Failed, "size", &e.to_string()),
+    }
+    let color = match ColorSupport::detect() {
+        _ if theme::no_color() => "disabled by NO_COLOR".to_string(),
+        ColorSupport::TrueColor => "truecolor".to_string(),
+        ColorSupport::Ansi256 => "256 colors".to_string(),
+        ColorSupport::Ansi16 => format!("basic ({})", env::var("TERM").unwrap_or_default()),
+        ColorSupport::None => "none".to_string(),
+    };
+    report.check(Status::Ok, "colors", &color);
+
+    report.failures
+}
@@ -0,0 +1,613 @@
+use std::{collections::HashSet, fmt::Write};
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::settings::Settings;
+
+/// Description of every option, keyed by its dotted path
+/// (entries of arrays of tables use the path of the array)
+const DOCS: &[(&str, &str)] = &[
+    ("stops", "Stop IDs (RBL numbers) to monitor"),
+    (
+        "stops_file",
+        "File with further stop IDs to monitor, one per line with `#` comments, watched so edits apply right away",
+    ),
+    (
+        "city",
+        "\"vienna\", \"linz\", \"graz\" or \"oebb\", where departures are requested from, stops are IDs of its network (`--city`)",
+    ),
+    (
+        "profiles",
+        "Named alternative stop sets, selected with `--profile`",
+    ),
+    ("profiles.*.stops", "Stop IDs monitored by this profile"),
+    (
+        "profiles.*.groups",
+        "Stop groups monitored by this profile on top of its stops, by name",
+    ),
+    (
+        "stop_groups",
+        "Named lists of stop IDs, e.g. schottentor = [1212, 1303], included by profiles and matched with `group == name` in filters",
+    ),
+    (
+        "timetable",
+        "Directory of an extracted GTFS feed, shown as of the time given with `--at`; stops are matched by stop_code or numeric stop_id",
+    ),
+    (
+        "refresh",
+        "Seconds between API requests, unless a view sets its own interval",
+    ),
+    (
+        "refresh_phase",
+        "Seconds into every refresh interval requests are made at, e.g. 0 requests on :00, :10, :20 with a refresh of 10; unset, a request follows the previous one by the interval",
+    ),
+    (
+        "adaptive_refresh",
+        "Refresh more often while the next departure is close and less often while it is far out, e.g. overnight",
+    ),
+    (
+        "adaptive_refresh.near",
+        "Minutes within which the next departure counts as close",
+    ),
+    (
+        "adaptive_refresh.near_refresh",
+        "Seconds between requests while the next departure is close, if shorter than the interval",
+    ),
+    (
+        "adaptive_refresh.far",
+        "Minutes beyond which the next departure counts as far out",
+    ),
+    (
+        "adaptive_refresh.far_refresh",
+        "Seconds between requests while the next departure is far out or there is none, if longer than the interval",
+    ),
+    (
+        "battery",
+        "Throttling while running on battery, detected from sysfs unless `--power` says otherwise",
+    ),
+    (
+        "battery.refresh_factor",
+        "Refresh intervals are this many times as long on battery, not throttled if 1",
+    ),
+    (
+        "battery.redraw",
+        "Seconds between redraws on battery, the clock then skips seconds",
+    ),
+    (
+        "max_fps",
+        "Frames drawn per second at most, e.g. 2 for a slow serial console; frames over the cap are drawn late rather than left out",
+    ),
+    (
+        "quiet_hours",
+        "Hours the monitor makes no requests and only shows a dim clock, resuming by itself afterwards",
+    ),
+    (
+        "quiet_hours.from",
+        "Start of the quiet hours as time of day, \"HH:MM\"",
+    ),
+    (
+        "quiet_hours.until",
+        "End of the quiet hours as time of day, \"HH:MM\", before `from` to span midnight",
+    ),
+    (
+        "quiet_hours.days",
+        "Days the quiet hours start on, e.g. [\"Mon\", \"Tue\"], every day if empty",
+    ),
+    (
+        "batching",
+        "Request the stops in several concurrent batches, so a slow one doesn't hold up the others",
+    ),
+    (
+        "batching.size",
+        "Stops per request, all stops in one request if 0",
+    ),
+    (
+        "batching.deadline",
+        "Seconds after which a batch that is still loading is marked stale",
+    ),
+    (
+        "batching.learn_limit",
+        "Learn how many stops the API accepts per request from refused requests and make batches no larger, kept in the cache directory for a day",
+    ),
+    (
+        "failover",
+        "Request a secondary provider while the city's keeps failing, with a banner on the board, and the city's again once it answers",
+    ),
+    (
+        "failover.after",
+        "Failed requests in a row after which the secondary provider is requested",
+    ),
+    (
+        "failover.mirror",
+        "Wiener Linien compatible API to request instead, the static `timetable` as of now if unset",
+    ),
+    (
+        "countdown",
+        "Which minutes until departure to show, the API's countdown and the computed one disagree by a minute now and then",
+    ),
+    (
+        "countdown.source",
+        "\"api\" for the countdown the API reports, \"computed\" to count it from the realtime or planned departure time",
+    ),
+    (
+        "countdown.debug",
+        "Add a column with both countdowns, the API's and the computed one",
+    ),
+    (
+        "dedup_tolerance",
+        "Seconds apart departures of a line and destination may be planned and still be shown once, for stops of the same station",
+    ),
+    (
+        "keep_departed",
+        "Minutes departures stay on the board after they left, dimmed and with how long ago, never if 0",
+    ),
+    (
+        "window",
+        "Restrict the board to departures inside a time window",
+    ),
+    (
+        "window.within",
+        "Only show departures leaving within this time span, e.g. \"45m\" (`--within`)",
+    ),
+    (
+        "window.after",
+        "Only show departures leaving at or after this time of day, \"HH:MM\" (`--after`)",
+    ),
+    (
+        "window.before",
+        "Only show departures leaving before this time of day, \"HH:MM\" (`--before`)",
+    ),
+    (
+        "filter",
+        "Only show departures matching this expression, e.g. \"line in [43,44] && countdown >= 5\" (`--filter`)",
+    ),
+    (
+        "palette",
+        "Board colors: \"default\", \"high-contrast\", \"deuteranopia\" or \"wiener-linien\" (`--palette`)",
+    ),
+    (
+        "lang",
+        "Language of times and durations on the board, \"en\" or \"de\" for \"in 3 Min.\" (`--lang`)",
+    ),
+    (
+        "theme",
+        "Palette and table borders in one, \"wl\" looks like the displays at Wiener Linien stations (`--theme`)",
+    ),
+    ("header", "Title banner and column names of the board"),
+    (
+        "header.title",
+        "Banner shown above the board, e.g. \"WG Monitor – Josefstädter Straße\"",
+    ),
+    (
+        "header.align",
+        "Alignment of the banner: \"left\", \"center\" or \"right\"",
+    ),
+    (
+        "header.columns",
+        "Column names replacing the default ones, e.g. Departure = \"Abfahrt\"",
+    ),
+    ("header.columns.*", "Name shown instead of this column name"),
+    ("table", "Borders and striping of the board tables"),
+    (
+        "table.separators",
+        "\"full\" grid, \"horizontal\" lines between rows only or \"none\" but the outer border",
+    ),
+    ("table.corners", "\"round\" or \"square\""),
+    ("table.zebra", "Shade every other departure row"),
+    (
+        "table.columns",
+        "Departure lists side by side at most, filled top to bottom, one per 100 characters of width and up to 3 if unset; 1 for a single list",
+    ),
+    (
+        "table.align",
+        "Alignment of a column's cells keyed by its default name, e.g. Departure = \"right\"",
+    ),
+    ("table.align.*", "\"left\", \"center\" or \"right\""),
+    ("clock", "What the footer clock shows besides the time"),
+    ("clock.date", "Show the date as day.month.year"),
+    ("clock.weekday", "Show the weekday"),
+    (
+        "clock.german",
+        "Weekday names in German, \"Freitag\" instead of \"Friday\"",
+    ),
+    ("clock.week", "Show the ISO week number"),
+    (
+        "footer",
+        "Template replacing the footer, with {clock}, {page}, {notice}, {stale} and {disruption_count}",
+    ),
+    ("lines", "Display overrides per line, keyed by line name"),
+    (
+        "destinations",
+        "Clean up destination names, e.g. FRIEDRICH-ENGELS-PLATZ as Friedrich-Engels-Platz, enabled by the table being present",
+    ),
+    (
+        "destinations.title_case",
+        "Write destination names in all caps in upper and lower case, keeping particles like \"an der\" lower case",
+    ),
+    (
+        "destinations.expand",
+        "Spell out abbreviations like Bhf., Hbf., Str. and -pl. in destination names",
+    ),
+    (
+        "destinations.abbreviations",
+        "Further words to replace in destination names, e.g. \"Krkhs.\" = \"Krankenhaus\"",
+    ),
+    (
+        "script",
+        "Rhai script defining `fn transform(dep)`, which gets every departure as a map and returns it changed, or false to hide it; label, destination, station, platform and note are taken over",
+    ),
+    (
+        "lines.*.color",
+        "Color of the line name as [red, green, blue], instead of the palette's",
+    ),
+    (
+        "lines.*.label",
+        "Shown instead of the line name, e.g. \"U3 Ott\"",
+    ),
+    ("lines.*.hide", "Leave the line's departures off the board"),
+    (
+        "lines.*.pin",
+        "Keep the line's departures at the top of the board",
+    ),
+    (
+        "walking",
+        "Walking times to the stops, adds a \"Leave in\" column to the board",
+    ),
+    (
+        "walking.stops",
+        "Minutes it takes to walk to a stop, keyed by stop ID",
+    ),
+    ("walking.stops.*", "Minutes it takes to walk to this stop"),
+    (
+        "walking.sort",
+        "Order departures by when to leave instead of when they depart",
+    ),
+    (
+        "anomalies",
+        "Banner pointing out lines running much later than usual",
+    ),
+    (
+        "anomalies.threshold",
+        "Minutes a line has to be later than its usual delay to be pointed out, never if 0",
+    ),
+    (
+        "anomalies.baseline",
+        "Minutes of history the usual delay of a line is taken from, kept in memory",
+    ),
+    (
+        "headway",
+        "Banner pointing out gaps between departures of a line, like a cancelled tram the disruptions don't mention",
+    ),
+    (
+        "headway.factor",
+        "How many times its usual interval a gap has to be to be pointed out, never if 0",
+    ),
+    (
+        "headway.baseline",
+        "Minutes of history the usual interval of a line is taken from, kept in memory",
+    ),
+    (
+        "headway.notify",
+        "Also send every gap to ntfy, once",
+    ),
+    (
+        "journey",
+        "Two-leg journey shown by journey views, both stops must be monitored",
+    ),
+    ("journey.first", "First leg of the journey"),
+    (
+        "journey.first.stop",
+        "Stop ID the first line is boarded at, this also selects its direction",
+    ),
+    ("journey.first.line", "Line taken on the first leg"),
+    ("journey.second", "Second leg of the journey, from the interchange"),
+    (
+        "journey.second.stop",
+        "Stop ID at the interchange the second line is boarded at",
+    ),
+    ("journey.second.line", "Line taken on the second leg"),
+    (
+        "journey.ride",
+        "Minutes the first leg takes to the interchange",
+    ),
+    (
+        "journey.transfer",
+        "Minutes needed at least to change to the second leg",
+    ),
+    (
+        "auto_page",
+        "Seconds each page of departures is shown when not all fit, like airport displays; off if unset",
+    ),
+    ("carousel", "Cycle through several views on one screen"),
+    ("carousel.interval", "Seconds each view is shown"),
+    (
+        "carousel.views",
+        "Views in the order they are shown, carousel mode is off without any",
+    ),
+    ("carousel.views.name", "Name shown in the page indicator"),
+    (
+        "carousel.views.kind",
+        "\"departures\", \"disruptions\" or \"journey\"",
+    ),
+    (
+        "carousel.views.vehicle_types",
+        "Vehicle types shown: \"tram\", \"metro\", \"citybus\", \"nightbus\", \"train\"; all if empty",
+    ),
+    (
+        "carousel.views.stops",
+        "Stop IDs (RBL numbers) this view shows, `stops` if empty",
+    ),
+    (
+        "carousel.views.refresh",
+        "Seconds between API requests for this view, overrides `refresh`; views showing the same stops share the shortest",
+    ),
+    (
+        "layout",
+        "Widgets composing the board, the departure board alone if unset; `--layout portrait` replaces it",
+    ),
+    ("layout.rows", "Rows of widgets, from top to bottom"),
+    (
+        "layout.rows.weight",
+        "Share of the height this row gets, relative to the other rows",
+    ),
+    ("layout.rows.columns", "Widgets of the row, from left to right"),
+    (
+        "layout.rows.columns.weight",
+        "Share of the width this widget gets, relative to the others in the row",
+    ),
+    (
+        "layout.rows.columns.type",
+        "\"departures\", \"disruptions\", \"clock\", \"big_clock\", \"weather\" or \"text\"",
+    ),
+    (
+        "layout.rows.columns.latitude",
+        "Latitude of the place a weather widget shows",
+    ),
+    (
+        "layout.rows.columns.longitude",
+        "Longitude of the place a weather widget shows",
+    ),
+    ("layout.rows.columns.text", "Text a text widget shows"),
+    (
+        "layout.rows.columns.file",
+        "File a text widget shows, read again every `refresh` seconds",
+    ),
+    (
+        "layout.rows.columns.url",
+        "URL a text widget shows, requested again every `refresh` seconds",
+    ),
+    (
+        "layout.rows.columns.refresh",
+        "Seconds between reading the file or URL of a text widget",
+    ),
+    (
+        "alerts",
+        "Departure alerts, their lines also select which disruptions are reported",
+    ),
+    ("alerts.line", "Line to watch"),
+    (
+        "alerts.minutes",
+        "Alert when a departure is this many minutes or less away",
+    ),
+    (
+        "alerts.station",
+        "Only consider departures from this station",
+    ),
+    ("ntfy", "Push alerts through an ntfy server"),
+    ("ntfy.server", "Server URL"),
+    ("ntfy.topic", "Topic to publish to"),
+    ("ntfy.token", "Access token"),
+    (
+        "ntfy.username",
+        "Basic authentication user, used if no token is set",
+    ),
+    ("ntfy.password", "Basic authentication password"),
+    (
+        "announcements",
+        "Speak departures that match an alert rule, e.g. for visually impaired users",
+    ),
+    (
+        "announcements.speak",
+        "Text-to-speech command, the announcement is passed as last argument; empty for chime only",
+    ),
+    (
+        "announcements.chime",
+        "Sound file played before every announcement",
+    ),
+    (
+        "announcements.player",
+        "Command playing the chime, the file is passed as last argument",
+    ),
+    (
+        "announcements.quiet_from",
+        "Stay silent from this time of day, \"HH:MM\"",
+    ),
+    (
+        "announcements.quiet_until",
+        "Stay silent until this time of day, \"HH:MM\"",
+    ),
+    (
+        "mqtt",
+        "Publish departures to an MQTT broker, with Home Assistant discovery",
+    ),
+    ("mqtt.host", "Broker host"),
+    ("mqtt.port", "Broker port"),
+    ("mqtt.username", "Broker user"),
+    ("mqtt.password", "Broker password"),
+    ("mqtt.topic", "Prefix of the state and attribute topics"),
+    (
+        "mqtt.discovery_prefix",
+        "Prefix Home Assistant listens on for discovery messages",
+    ),
+    (
+        "control_socket",
+        "Unix socket accepting JSON control commands",
+    ),
+    (
+        "daemon_socket",
+        "Unix socket of `oeffimonitor-cli daemon`, which requests the API for every monitor connected to it, each set of stops once per `refresh`; monitors request on their own while it isn't running",
+    ),
+    (
+        "state_file",
+        "File the latest departures, disruptions and reported alerts are saved to on shutdown, so a restarted monitor shows data right away and doesn't alert again",
+    ),
+    (
+        "usage_stats",
+        "Directory a summary of refreshes, failed requests and render time percentiles is written to every week, nothing is counted if unset and nothing is ever sent anywhere",
+    ),
+    (
+        "shared_cache",
+        "Directory the monitors running on this machine share API responses in, with a lock file per request, so monitors of the same stops query the API once per interval; with `batching.size = 1` overlapping stops are shared too",
+    ),
+    (
+        "http",
+        "HTTP server, GET / shows the live board, GET /ws pushes the departures of a view as JSON right after every request and GET /healthz answers 200 while the monitor is running",
+    ),
+    (
+        "http.listen",
+        "Address and port to listen on, changing it needs a restart",
+    ),
+    (
+        "http.url",
+        "Address other devices reach the live board at, found out from the network if not set",
+    ),
+    (
+        "http.qr",
+        "Show a QR code of the live board address in the bottom right corner of the full screen board",
+    ),
+    (
+        "keys",
+        "Keys of the full screen board by action, as lists like [\"q\", \"ctrl-x\", \"f10\", \"esc\"]; Ctrl-C always quits, changes apply after a restart",
+    ),
+    ("keys.quit", "Exit the monitor"),
+    ("keys.refresh", "Request all views again right away"),
+    ("keys.down", "Select the next row"),
+    ("keys.up", "Select the previous row"),
+    ("keys.deselect", "Select no row anymore"),
+    ("keys.copy", "Copy the selected departure to the clipboard"),
+    ("keys.snapshot", "Write the board to a file"),
+    (
+        "keys.snapshot_ansi",
+        "Write the board to a file, keeping its colors as ANSI codes",
+    ),
+    (
+        "keys.graph",
+        "Show the departures of the next hour on a timeline per line instead of the board",
+    ),
+    (
+        "keys.help",
+        "Show the keys and the version and build information instead of the board",
+    ),
+    (
+        "led_matrix",
+        "LED matrix panel drawn on with `--output led-matrix`",
+    ),
+    (
+        "led_matrix.path",
+        "Framebuffer device or pipe the frames are written to",
+    ),
+    ("led_matrix.width", "Panel width in pixels"),
+    ("led_matrix.height", "Panel height in pixels"),
+    (
+        "led_matrix.format",
+        "\"rgb565\" (16 bits per pixel, little endian), \"rgb24\" or \"xrgb8888\"",
+    ),
+    (
+        "led_matrix.color",
+        "Color of lit pixels as [red, green, blue]",
+    ),
+];
+
+const REDACTED: &str = "<redacted>";
+
+/// Render the effective settings as TOML, with a comment on every option
+/// and a list of the options that are not set at the end
+pub fn explain(settings: &Settings) -> Result<String, toml::ser::Error> {
+    let mut settings = settings.clone();
+    if let Some(ntfy) = &mut settings.ntfy {
+        redact(&mut ntfy.token);
+        redact(&mut ntfy.password);
+    }
+    if let Some(mqtt) = &mut settings.mqtt {
+        redact(&mut mqtt.password);
+    }
+
+    let mut document: DocumentMut = toml::to_string(&settings)?
+        .parse()
+        .expect("serialized settings are valid TOML");
+
+    let mut present = HashSet::new();
+    annotate(document.as_table_mut(), "", &mut present);
+
+    let mut output = document.to_string();
+    let missing: Vec<_> = DOCS
+        .iter()
+        .filter(|(path, _)| !present.contains(*path) && !path.contains('*'))
+        .filter(|(path, _)| {
+            // options of an unset section are covered by the section itself
+            path.rsplit_once('.')
+                .is_none_or(|(parent, _)| present.contains(parent))
+        })
+        .collect();
+    if !missing.is_empty() {
+        output.push_str("\n# Not set:\n");
+        for (path, doc) in missing {
+            let _ = writeln!(output, "#   {}: {}", path, doc);
+        }
+    }
+    Ok(output)
+}
+
+fn redact(value: &mut Option<String>) {
+    if value.is_some() {
+        *value = Some(REDACTED.to_string());
+    }
+}
+
+fn doc(path: &str) -> Option<&'static str> {
+    DOCS.iter().find(|(p, _)| *p == path).map(|(_, doc)| *doc)
+}
+
+fn comment(doc: &str) -> String {
+    format!("# {}\n", doc)
+}
+
+fn annotate(table: &mut Table, prefix: &str, present: &mut HashSet<String>) {
+    // profiles, stop groups, lines, walking times and column names are keyed by name or stop, document them once under a wildcard
+    let wildcard = matches!(
+        prefix,
+        "profiles" | "stop_groups" | "lines" | "walking.stops" | "header.columns"
+    );
+
+    for (mut key, item) in table.iter_mut() {
+        let path = match (prefix, wildcard) {
+            ("", _) => key.get().to_string(),
+            (prefix, true) => format!("{}.*", prefix),
+            (prefix, false) => format!("{}.{}", prefix, key.get()),
+        };
+        let doc = doc(&path);
+
+        match item {
+            Item::Table(child) => {
+                if let Some(doc) = doc {
+                    child.decor_mut().set_prefix(format!("\n{}", comment(doc)));
+                }
+                annotate(child, &path, present);
+            }
+            Item::ArrayOfTables(array) => {
+                for (i, child) in array.iter_mut().enumerate() {
+                    if let (0, Some(doc)) = (i, doc) {
+                        child.decor_mut().set_prefix(format!("\n{}", comment(doc)));
+                    }
+                    annotate(child, &path, present);
+                }
+            }
+            Item::Value(_) => {
+                if let Some(doc) = doc {
+                    key.leaf_decor_mut().set_prefix(comment(doc));
+                }
+            }
+            Item::None => {}
+        }
+        present.insert(path);
+    }
+}
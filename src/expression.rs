@@ -0,0 +1,393 @@
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt, iter::Peekable, str::FromStr};
+use thiserror::Error;
+
+use crate::{settings::StopGroups, Departure, WienerLinienVehicleType};
+
+/// Fields of a departure an expression can refer to
+const FIELDS: &[&str] = &[
+    "line",
+    "type",
+    "destination",
+    "station",
+    "platform",
+    "stop",
+    "countdown",
+    "delay",
+    "occupancy",
+    "traffic_jam",
+    "realtime",
+    "group",
+];
+
+#[derive(Error, Debug)]
+pub enum ExpressionError {
+    #[error("unknown field `{0}`, expected one of {fields}", fields = FIELDS.join(", "))]
+    UnknownField(String),
+    #[error("unexpected `{0}`")]
+    Unexpected(String),
+    #[error("unexpected end of the expression")]
+    UnexpectedEnd,
+    #[error("unterminated string")]
+    UnterminatedString,
+}
+
+/// Departures to show, e.g. `line in [43,44] && countdown >= 5 && type != nightbus`
+///
+/// Comparisons of a field and a value can be combined with `&&`, `||`, `!` and
+/// parentheses. Values are compared as numbers if both sides are numbers and
+/// case-insensitively as text otherwise; `traffic_jam` and `realtime` also
+/// work on their own. `group == schottentor` matches departures from the stops
+/// of a stop group, `group != nightbus-stops` leaves them out.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Filter {
+    source: String,
+    expression: Expression,
+}
+
+impl Filter {
+    pub(crate) fn matches(&self, dep: &Departure, groups: &StopGroups) -> bool {
+        self.expression.matches(dep, groups)
+    }
+
+    /// Names of the stop groups the expression refers to
+    pub fn groups(&self) -> Vec<&str> {
+        let mut names = vec![];
+        self.expression.groups(&mut names);
+        names
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ExpressionError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut tokens = tokenize(source)?.into_iter().peekable();
+        let expression = parse_or(&mut tokens)?;
+        match tokens.next() {
+            Some(token) => Err(ExpressionError::Unexpected(token.to_string())),
+            None => Ok(Filter {
+                source: source.to_string(),
+                expression,
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for Filter {
+    type Error = ExpressionError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        source.parse()
+    }
+}
+
+impl From<Filter> for String {
+    fn from(filter: Filter) -> Self {
+        filter.source
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expression {
+    Or(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Compare(&'static str, Operator, String),
+    In(&'static str, Vec<String>),
+    /// A field on its own, true unless it is empty, 0 or false
+    Flag(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Expression {
+    fn matches(&self, dep: &Departure, groups: &StopGroups) -> bool {
+        let in_group = |name: &str| {
+            groups
+                .get(name)
+                .is_some_and(|stops| dep.stop_id.is_some_and(|stop| stops.contains(&stop)))
+        };
+        match self {
+            Expression::Or(left, right) => left.matches(dep, groups) || right.matches(dep, groups),
+            Expression::And(left, right) => left.matches(dep, groups) && right.matches(dep, groups),
+            Expression::Not(inner) => !inner.matches(dep, groups),
+            // a departure is in as many groups as its stop is, not one to compare
+            Expression::Compare("group", operator, name) => match operator {
+                Operator::Equal => in_group(name),
+                Operator::NotEqual => !in_group(name),
+                _ => false,
+            },
+            Expression::In("group", names) => names.iter().any(|name| in_group(name)),
+            Expression::Flag("group") => groups.keys().any(|name| in_group(name)),
+            Expression::Compare(field, operator, value) => {
+                let ordering = compare(&field_value(dep, field), value);
+                match operator {
+                    Operator::Equal => ordering == Ordering::Equal,
+                    Operator::NotEqual => ordering != Ordering::Equal,
+                    Operator::Less => ordering == Ordering::Less,
+                    Operator::LessOrEqual => ordering != Ordering::Greater,
+                    Operator::Greater => ordering == Ordering::Greater,
+                    Operator::GreaterOrEqual => ordering != Ordering::Less,
+                }
+            }
+            Expression::In(field, values) => {
+                let actual = field_value(dep, field);
+                values
+                    .iter()
+                    .any(|value| compare(&actual, value) == Ordering::Equal)
+            }
+            Expression::Flag(field) => {
+                !matches!(field_value(dep, field).as_str(), "" | "0" | "false")
+            }
+        }
+    }
+
+    fn groups<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expression::Or(left, right) | Expression::And(left, right) => {
+                left.groups(names);
+                right.groups(names);
+            }
+            Expression::Not(inner) => inner.groups(names),
+            Expression::Compare("group", _, name) => names.push(name),
+            Expression::In("group", values) => names.extend(values.iter().map(String::as_str)),
+            Expression::Compare(..) | Expression::In(..) | Expression::Flag(_) => {}
+        }
+    }
+}
+
+/// `field` of `dep` as text
+fn field_value(dep: &Departure, field: &str) -> String {
+    match field {
+        "line" => dep.line.name.clone(),
+        "type" => vehicle_type(dep).to_string(),
+        "destination" => dep.destination_name.clone(),
+        "station" => dep.station_name.clone(),
+        "platform" => dep.platform.clone().unwrap_or_default(),
+        "stop" => dep.stop_id.map(|id| id.to_string()).unwrap_or_default(),
+        "countdown" => dep.countdown.to_string(),
+        "delay" => dep.delay_minutes().to_string(),
+        "occupancy" => dep
+            .occupancy
+            .and_then(|occupancy| serde_json::to_value(occupancy).ok())
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default(),
+        "traffic_jam" => dep.traffic_jam.to_string(),
+        "realtime" => dep.time_real.is_some().to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Vehicle type of `dep` as named in the config, e.g. "nightbus"
+pub fn vehicle_type(dep: &Departure) -> &'static str {
+    match dep.line.vehicle_type {
+        WienerLinienVehicleType::Tram => "tram",
+        WienerLinienVehicleType::Metro => "metro",
+        WienerLinienVehicleType::CityBus => "citybus",
+        WienerLinienVehicleType::NightBus => "nightbus",
+        WienerLinienVehicleType::Train => "train",
+    }
+}
+
+/// Numbers by value, anything else as text ignoring case
+fn compare(actual: &str, expected: &str) -> Ordering {
+    match (actual.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(actual), Ok(expected)) => actual.partial_cmp(&expected).unwrap_or(Ordering::Equal),
+        _ => actual.to_lowercase().cmp(&expected.to_lowercase()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Field name or unquoted value
+    Word(String),
+    Text(String),
+    Operator(Operator),
+    And,
+    Or,
+    Not,
+    In,
+    Open,
+    Close,
+    OpenList,
+    CloseList,
+    Comma,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessOrEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterOrEqual => ">=",
+        })
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => f.write_str(word),
+            Token::Text(text) => write!(f, "\"{}\"", text),
+            Token::Operator(operator) => operator.fmt(f),
+            Token::And => f.write_str("&&"),
+            Token::Or => f.write_str("||"),
+            Token::Not => f.write_str("!"),
+            Token::In => f.write_str("in"),
+            Token::Open => f.write_str("("),
+            Token::Close => f.write_str(")"),
+            Token::OpenList => f.write_str("["),
+            Token::CloseList => f.write_str("]"),
+            Token::Comma => f.write_str(","),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ExpressionError> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '[' => Token::OpenList,
+            ']' => Token::CloseList,
+            ',' => Token::Comma,
+            '&' if chars.next_if_eq(&'&').is_some() => Token::And,
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Or,
+            '=' => {
+                // `=` and `==` both compare
+                chars.next_if_eq(&'=');
+                Token::Operator(Operator::Equal)
+            }
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::NotEqual),
+            '!' => Token::Not,
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::LessOrEqual),
+            '<' => Token::Operator(Operator::Less),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::GreaterOrEqual),
+            '>' => Token::Operator(Operator::Greater),
+            '"' | '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some(c) => text.push(c),
+                        None => return Err(ExpressionError::UnterminatedString),
+                    }
+                }
+                Token::Text(text)
+            }
+            c if is_word(c) => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| is_word(*c)) {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "in" => Token::In,
+                    _ => Token::Word(word),
+                }
+            }
+            c => return Err(ExpressionError::Unexpected(c.to_string())),
+        });
+    }
+    Ok(tokens)
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':')
+}
+
+type Tokens = Peekable<std::vec::IntoIter<Token>>;
+
+fn parse_or(tokens: &mut Tokens) -> Result<Expression, ExpressionError> {
+    let mut expression = parse_and(tokens)?;
+    while tokens.next_if_eq(&Token::Or).is_some() {
+        expression = Expression::Or(Box::new(expression), Box::new(parse_and(tokens)?));
+    }
+    Ok(expression)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Expression, ExpressionError> {
+    let mut expression = parse_unary(tokens)?;
+    while tokens.next_if_eq(&Token::And).is_some() {
+        expression = Expression::And(Box::new(expression), Box::new(parse_unary(tokens)?));
+    }
+    Ok(expression)
+}
+
+fn parse_unary(tokens: &mut Tokens) -> Result<Expression, ExpressionError> {
+    match tokens.next().ok_or(ExpressionError::UnexpectedEnd)? {
+        Token::Not => Ok(Expression::Not(Box::new(parse_unary(tokens)?))),
+        Token::Open => {
+            let expression = parse_or(tokens)?;
+            match tokens.next() {
+                Some(Token::Close) => Ok(expression),
+                Some(token) => Err(ExpressionError::Unexpected(token.to_string())),
+                None => Err(ExpressionError::UnexpectedEnd),
+            }
+        }
+        Token::Word(name) => {
+            let field = FIELDS
+                .iter()
+                .find(|field| **field == name)
+                .ok_or(ExpressionError::UnknownField(name))?;
+            match tokens.peek() {
+                Some(Token::Operator(operator)) => {
+                    let operator = *operator;
+                    tokens.next();
+                    Ok(Expression::Compare(field, operator, parse_value(tokens)?))
+                }
+                Some(Token::In) => {
+                    tokens.next();
+                    Ok(Expression::In(field, parse_list(tokens)?))
+                }
+                _ => Ok(Expression::Flag(field)),
+            }
+        }
+        token => Err(ExpressionError::Unexpected(token.to_string())),
+    }
+}
+
+fn parse_value(tokens: &mut Tokens) -> Result<String, ExpressionError> {
+    match tokens.next() {
+        Some(Token::Word(value) | Token::Text(value)) => Ok(value),
+        Some(token) => Err(ExpressionError::Unexpected(token.to_string())),
+        None => Err(ExpressionError::UnexpectedEnd),
+    }
+}
+
+/// Values like `[43, 44]`, the opening bracket not yet taken
+fn parse_list(tokens: &mut Tokens) -> Result<Vec<String>, ExpressionError> {
+    match tokens.next() {
+        Some(Token::OpenList) => {}
+        Some(token) => return Err(ExpressionError::Unexpected(token.to_string())),
+        None => return Err(ExpressionError::UnexpectedEnd),
+    }
+    let mut values = vec![];
+    if tokens.next_if_eq(&Token::CloseList).is_some() {
+        return Ok(values);
+    }
+    loop {
+        values.push(parse_value(tokens)?);
+        match tokens.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::CloseList) => return Ok(values),
+            Some(token) => return Err(ExpressionError::Unexpected(token.to_string())),
+            None => return Err(ExpressionError::UnexpectedEnd),
+        }
+    }
+}
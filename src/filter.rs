@@ -0,0 +1,64 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::Departure;
+
+/// Restricts the board to departures inside a time window
+///
+/// `within` is a horizon relative to now, `after`/`before` bound the time of day.
+/// If `after` is later than `before`, the window wraps around midnight.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TimeWindow {
+    #[serde(with = "humantime_serde")]
+    pub within: Option<Duration>,
+    #[serde(with = "time_of_day")]
+    pub after: Option<NaiveTime>,
+    #[serde(with = "time_of_day")]
+    pub before: Option<NaiveTime>,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, dep: &Departure) -> bool {
+        if let Some(within) = self.within {
+            if dep.countdown * 60 > within.as_secs() as i64 {
+                return false;
+            }
+        }
+
+        time_between(dep.local_time().time(), self.after, self.before)
+    }
+}
+
+/// Whether `time` is at or after `after` and before `before`, wrapping around midnight
+/// if `after` is later than `before`; a missing bound is unbounded
+pub fn time_between(time: NaiveTime, after: Option<NaiveTime>, before: Option<NaiveTime>) -> bool {
+    match (after, before) {
+        (Some(after), Some(before)) if after > before => time >= after || time < before,
+        (after, before) => {
+            after.is_none_or(|after| time >= after) && before.is_none_or(|before| time < before)
+        }
+    }
+}
+
+/// (De)serialize an optional time of day as `HH:MM`
+pub mod time_of_day {
+    use chrono::NaiveTime;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%H:%M";
+
+    pub fn serialize<S: Serializer>(time: &Option<NaiveTime>, s: S) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => s.serialize_str(&time.format(FORMAT).to_string()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<NaiveTime>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|time| NaiveTime::parse_from_str(&time, FORMAT).map_err(D::Error::custom))
+            .transpose()
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Title banner above the board and the names of the table columns
+#[derive(Debug, Clone, Default, Hash, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HeaderSettings {
+    /// Banner shown above the board, e.g. "WG Monitor – Josefstädter Straße"
+    pub title: Option<String>,
+    pub align: Alignment,
+    /// Column names replacing the default ones, keyed by the default name
+    pub columns: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Alignment {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+impl HeaderSettings {
+    /// Name of the column called `name` by default
+    pub fn column<'a>(&'a self, name: &'a str) -> &'a str {
+        self.columns.get(name).map_or(name, String::as_str)
+    }
+
+    /// Renamed column names for a table header
+    pub fn columns<'a>(&'a self, names: &[&'a str]) -> Vec<&'a str> {
+        names.iter().map(|name| self.column(name)).collect()
+    }
+
+    /// The banner line aligned within `width`, `None` without a title
+    pub fn banner(&self, width: u16) -> Option<String> {
+        let title: String = self.title.as_ref()?.chars().take(width as usize).collect();
+        let padding = width as usize - title.chars().count();
+        let left = match self.align {
+            Alignment::Left => 0,
+            Alignment::Center => padding / 2,
+            Alignment::Right => padding,
+        };
+        Some(format!("{}{}", " ".repeat(left), title))
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::{to_local, Departure};
+
+/// Headways a line needs in its history before a gap counts as unusual
+const MIN_SAMPLES: usize = 3;
+
+/// When to point out gaps in the service of a line, like a tram that was
+/// cancelled or turned short without the disruptions saying so
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HeadwaySettings {
+    /// How many times its usual interval a line's gap has to be to be pointed out, never if 0
+    pub factor: f64,
+    /// Minutes of history the usual interval is taken from
+    pub baseline: u64,
+    /// Also send gaps to ntfy, once each
+    pub notify: bool,
+}
+
+impl Default for HeadwaySettings {
+    fn default() -> Self {
+        HeadwaySettings {
+            factor: 2.0,
+            baseline: 120,
+            notify: false,
+        }
+    }
+}
+
+/// Departures of a line from a stop towards one destination
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Direction {
+    line: String,
+    station: String,
+    destination: String,
+}
+
+/// A gap between two departures in one direction, in minutes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+    pub line: String,
+    pub station: String,
+    pub destination: String,
+    pub minutes: i64,
+    pub typical: f64,
+    /// Departure after the gap
+    pub until: DateTime<Local>,
+}
+
+/// Rolling history of the minutes between departures in every direction, kept in memory
+#[derive(Debug, Default)]
+pub struct HeadwayBaseline {
+    samples: HashMap<Direction, VecDeque<(Instant, i64)>>,
+    /// Headways of the latest refresh with the departure ending each
+    latest: BTreeMap<Direction, Vec<(i64, DateTime<Local>)>>,
+    /// Gaps already sent to ntfy, by direction and the departure ending them
+    reported: HashSet<(Direction, DateTime<Local>)>,
+}
+
+impl HeadwayBaseline {
+    /// Remember the headways of a refresh, forgetting what is older than the baseline
+    pub fn record(&mut self, departures: &[Departure], settings: &HeadwaySettings) {
+        let now = Instant::now();
+        let mut times: BTreeMap<Direction, Vec<DateTime<Local>>> = BTreeMap::new();
+        for dep in departures {
+            times
+                .entry(Direction {
+                    line: dep.line.name.clone(),
+                    station: dep.station_name.clone(),
+                    destination: dep.destination_name.clone(),
+                })
+                .or_default()
+                .push(to_local(&dep.time_real.unwrap_or(dep.time_planned)));
+        }
+        for (direction, mut times) in times {
+            times.sort();
+            let headways: Vec<(i64, DateTime<Local>)> = times
+                .windows(2)
+                .map(|pair| ((pair[1] - pair[0]).num_minutes(), pair[1]))
+                .collect();
+            let samples = self.samples.entry(direction.clone()).or_default();
+            samples.extend(headways.iter().map(|(minutes, _)| (now, *minutes)));
+            self.latest.insert(direction, headways);
+        }
+
+        let baseline = Duration::from_secs(settings.baseline * 60);
+        for samples in self.samples.values_mut() {
+            while samples
+                .front()
+                .is_some_and(|(time, _)| now.duration_since(*time) > baseline)
+            {
+                samples.pop_front();
+            }
+        }
+        self.samples.retain(|_, samples| !samples.is_empty());
+        let local_now = Local::now();
+        self.reported.retain(|(_, until)| *until > local_now);
+    }
+
+    /// Gaps of the latest refresh at least `factor` times the usual headway, the longest first
+    pub fn gaps(&self, settings: &HeadwaySettings) -> Vec<Gap> {
+        if settings.factor <= 0.0 {
+            return vec![];
+        }
+        let mut gaps = vec![];
+        for (direction, headways) in &self.latest {
+            let Some(typical) = self.typical(direction) else {
+                continue;
+            };
+            for &(minutes, until) in headways {
+                if minutes as f64 >= typical * settings.factor {
+                    gaps.push(Gap {
+                        line: direction.line.clone(),
+                        station: direction.station.clone(),
+                        destination: direction.destination.clone(),
+                        minutes,
+                        typical,
+                        until,
+                    });
+                }
+            }
+        }
+        gaps.sort_by(|a, b| b.minutes.cmp(&a.minutes).then_with(|| a.line.cmp(&b.line)));
+        gaps
+    }
+
+    /// Gaps not reported before, marked as reported now
+    pub fn unreported(&mut self, settings: &HeadwaySettings) -> Vec<Gap> {
+        let gaps = self.gaps(settings);
+        gaps.into_iter()
+            .filter(|gap| {
+                self.reported.insert((
+                    Direction {
+                        line: gap.line.clone(),
+                        station: gap.station.clone(),
+                        destination: gap.destination.clone(),
+                    },
+                    gap.until,
+                ))
+            })
+            .collect()
+    }
+
+    /// Median headway in `direction`, `None` without enough history
+    fn typical(&self, direction: &Direction) -> Option<f64> {
+        let samples = self.samples.get(direction)?;
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        let mut headways: Vec<i64> = samples.iter().map(|(_, minutes)| *minutes).collect();
+        headways.sort();
+        // departures at the same minute would make every interval look like a gap
+        Some(headways[headways.len() / 2].max(1) as f64)
+    }
+}
+
+/// E.g. "43 → Neuwaldegg: 24 min gap, usually 8", `None` without gaps
+pub fn banner(gaps: &[Gap]) -> Option<String> {
+    (!gaps.is_empty()).then(|| gaps.iter().map(message).collect::<Vec<_>>().join(" · "))
+}
+
+/// Text of a gap in the banner and the notification
+pub fn message(gap: &Gap) -> String {
+    format!(
+        "{} → {}: {} min gap, usually {:.0}",
+        gap.line, gap.destination, gap.minutes, gap.typical
+    )
+}
@@ -0,0 +1,260 @@
+use futures_util::SinkExt;
+use hyper::{
+    header::{
+        HeaderValue, CONNECTION, CONTENT_TYPE, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE,
+    },
+    service::{make_service_fn, service_fn},
+    upgrade::Upgraded,
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    net::{SocketAddr, UdpSocket},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+
+use crate::snapshot::strip_ansi;
+
+/// Seconds after which the web page of the board reloads itself, without JavaScript to receive updates
+const PAGE_REFRESH: u64 = 5;
+/// Updates kept for a `/ws` client that is slow to receive them
+const UPDATE_BACKLOG: usize = 16;
+/// The monitor counts as hung if its loop didn't run for this long
+const LIVENESS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// HTTP server, e.g. for container health checks
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// Address and port to listen on
+    pub listen: SocketAddr,
+    /// Address other devices reach the live board at, found out from the network if not set
+    pub url: Option<String>,
+    /// Show a QR code of the address in a corner of the full screen board
+    pub qr: bool,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            listen: ([0, 0, 0, 0], 8080).into(),
+            url: None,
+            qr: true,
+        }
+    }
+}
+
+impl HttpSettings {
+    /// Address of the live board, `None` if it can't be found out
+    pub fn public_url(&self) -> Option<String> {
+        if let Some(url) = &self.url {
+            return Some(url.clone());
+        }
+        let ip = match self.listen.ip() {
+            ip if ip.is_unspecified() => {
+                // connecting a UDP socket sends nothing, but picks the interface of the default route
+                let socket = UdpSocket::bind(("0.0.0.0", 0)).ok()?;
+                socket.connect(("192.0.2.1", 80)).ok()?;
+                socket.local_addr().ok()?.ip()
+            }
+            ip => ip,
+        };
+        Some(format!(
+            "http://{}/",
+            SocketAddr::new(ip, self.listen.port())
+        ))
+    }
+}
+
+/// What the main loop shares with the server
+pub struct ServerState {
+    last_tick: Mutex<Instant>,
+    board: Mutex<String>,
+    /// Updates sent to the `/ws` clients, as JSON
+    updates: broadcast::Sender<String>,
+    /// Latest update of every view, sent to `/ws` clients when they connect
+    latest: Mutex<BTreeMap<usize, String>>,
+}
+
+impl ServerState {
+    /// Note that the main loop is still running
+    pub fn tick(&self) {
+        if let Ok(mut last_tick) = self.last_tick.lock() {
+            *last_tick = Instant::now();
+        }
+    }
+
+    /// Show `board` on the live web view
+    pub fn publish(&self, board: &str) {
+        if let Ok(mut shown) = self.board.lock() {
+            board.clone_into(&mut shown);
+        }
+    }
+
+    /// Send the departures of the view at `index`, just fetched, to the `/ws`
+    /// clients, with the board as last published
+    pub fn push(&self, index: usize, departures: Value) {
+        let board = self
+            .board
+            .lock()
+            .map(|board| strip_ansi(&board))
+            .unwrap_or_default();
+        let update = json!({ "view": index, "departures": departures, "board": board }).to_string();
+        if let Ok(mut latest) = self.latest.lock() {
+            latest.insert(index, update.clone());
+        }
+        // nobody may be connected
+        let _ = self.updates.send(update);
+    }
+
+    fn is_alive(&self) -> bool {
+        self.last_tick
+            .lock()
+            .is_ok_and(|last_tick| last_tick.elapsed() < LIVENESS_TIMEOUT)
+    }
+}
+
+/// Start serving in the background
+///
+/// `GET /` is a web page of the board updated from `/ws`, `GET /ws` a WebSocket
+/// pushing the departures of a view and the board as JSON right after they
+/// were fetched, and `GET /healthz` answers 200 while the main loop keeps
+/// running and 503 once it hangs.
+pub fn serve(settings: &HttpSettings) -> Result<Arc<ServerState>, hyper::Error> {
+    let state = Arc::new(ServerState {
+        last_tick: Mutex::new(Instant::now()),
+        board: Mutex::new(String::new()),
+        updates: broadcast::channel(UPDATE_BACKLOG).0,
+        latest: Mutex::new(BTreeMap::new()),
+    });
+    let shared = state.clone();
+    let server = Server::try_bind(&settings.listen)?.serve(make_service_fn(move |_| {
+        let state = shared.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let state = state.clone();
+                async move { Ok::<_, Infallible>(handle(req, state)) }
+            }))
+        }
+    }));
+    tokio::spawn(server);
+    Ok(state)
+}
+
+fn handle(req: Request<Body>, state: Arc<ServerState>) -> Response<Body> {
+    match (req.method(), req.uri().path()) {
+        (&Method::GET, "/") => page(&state),
+        (&Method::GET, "/ws") => upgrade(req, state),
+        (&Method::GET, "/healthz") if state.is_alive() => text(StatusCode::OK, "ok"),
+        (&Method::GET, "/healthz") => text(StatusCode::SERVICE_UNAVAILABLE, "hung"),
+        _ => text(StatusCode::NOT_FOUND, "not found"),
+    }
+}
+
+fn text(status: StatusCode, body: &'static str) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+}
+
+/// Answer the WebSocket handshake of `req` and push updates once it is upgraded
+fn upgrade(mut req: Request<Body>, state: Arc<ServerState>) -> Response<Body> {
+    let Some(key) = req
+        .headers()
+        .get(SEC_WEBSOCKET_KEY)
+        .filter(|_| {
+            req.headers()
+                .get(UPGRADE)
+                .is_some_and(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"))
+        })
+        .map(|key| derive_accept_key(key.as_bytes()))
+    else {
+        return text(StatusCode::BAD_REQUEST, "expected a WebSocket handshake");
+    };
+    // subscribed before answering, so no update is missed in between
+    let updates = state.updates.subscribe();
+    let latest: Vec<String> = state
+        .latest
+        .lock()
+        .map(|latest| latest.values().cloned().collect())
+        .unwrap_or_default();
+    tokio::spawn(async move {
+        if let Ok(upgraded) = hyper::upgrade::on(&mut req).await {
+            push_updates(upgraded, latest, updates).await;
+        }
+    });
+
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(CONNECTION, HeaderValue::from_static("Upgrade"));
+    if let Ok(accept) = HeaderValue::from_str(&key) {
+        headers.insert(SEC_WEBSOCKET_ACCEPT, accept);
+    }
+    response
+}
+
+/// Send `latest` and then every update to a WebSocket client, until it disconnects
+async fn push_updates(
+    upgraded: Upgraded,
+    latest: Vec<String>,
+    mut updates: broadcast::Receiver<String>,
+) {
+    let mut socket = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+    for update in latest {
+        if socket.send(Message::Text(update)).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        let update = match updates.recv().await {
+            Ok(update) => update,
+            // a client too slow for the updates just misses some
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if socket.send(Message::Text(update)).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn page(state: &ServerState) -> Response<Body> {
+    let board = state
+        .board
+        .lock()
+        .map(|board| strip_ansi(&board))
+        .unwrap_or_default();
+    let escaped = board
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let html = format!(
+        "<!DOCTYPE html>\n\
+         <html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width\">\
+         <noscript><meta http-equiv=\"refresh\" content=\"{}\"></noscript>\
+         <title>Öffimonitor</title></head>\
+         <body style=\"background:#000;color:#eee\"><pre id=\"board\">{}</pre>\
+         <script>new WebSocket('ws' + location.origin.slice(4) + '/ws').onmessage = \
+         e => document.getElementById('board').textContent = JSON.parse(e.data).board;</script>\
+         </body></html>\n",
+        PAGE_REFRESH, escaped
+    );
+    let mut response = Response::new(Body::from(html));
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+    response
+}
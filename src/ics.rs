@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+use crate::{to_local, Departure};
+
+/// Maximum line length in octets before a line has to be folded (RFC 5545, 3.1)
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Render departures as an iCalendar document with one VEVENT each
+pub fn to_ics(departures: &[Departure]) -> String {
+    let now = Utc::now();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//oeffimonitor-cli//departures//EN".to_string(),
+    ];
+
+    for dep in departures {
+        let planned = to_local(&dep.time_planned).with_timezone(&Utc);
+        let start = dep.local_time().with_timezone(&Utc);
+
+        let mut description = format!("Planned: {}", to_local(&dep.time_planned).format("%H:%M"));
+        if let Some(time_real) = &dep.time_real {
+            let _ = write!(
+                description,
+                "\nRealtime: {}",
+                to_local(time_real).format("%H:%M")
+            );
+        }
+
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@oeffimonitor-cli", uid(dep, &planned)),
+            format!("DTSTAMP:{}", format_utc(&now)),
+            format!("DTSTART:{}", format_utc(&start)),
+            format!("DTEND:{}", format_utc(&start)),
+            format!(
+                "SUMMARY:{} → {}",
+                escape(&dep.line.name),
+                escape(&dep.destination_name)
+            ),
+            format!("LOCATION:{}", escape(&dep.station_name)),
+            format!("DESCRIPTION:{}", escape(&description)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Stable event ID, so re-exported departures update instead of duplicating events
+///
+/// A SHA-256 of the departure, unlike `DefaultHasher` it stays the same across
+/// Rust releases.
+fn uid(dep: &Departure, planned: &DateTime<Utc>) -> String {
+    let fields: [&str; 4] = [
+        &dep.line.name,
+        &dep.destination_name,
+        &dep.station_name,
+        &planned.timestamp().to_string(),
+    ];
+    let key = fields.join("\0");
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn format_utc(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT property value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line into chunks of at most 75 octets, without splitting characters
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            // the leading space of a continuation line counts towards its length
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(escape("a,b;c\\d\ne"), "a\\,b\\;c\\\\d\\ne");
+    }
+
+    #[test]
+    fn folds_at_75_octets() {
+        let line = "x".repeat(160);
+        let folded = fold(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(
+            parts.iter().map(|part| part.len()).collect::<Vec<_>>(),
+            [75, 75, 12]
+        );
+        assert!(parts[1..].iter().all(|part| part.starts_with(' ')));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+        assert_eq!(fold("short"), "short");
+    }
+
+    #[test]
+    fn folds_between_characters() {
+        // "→" is three octets, the first line can't hold the one at octets 74 to 76
+        let line = format!("{}→{}", "x".repeat(73), "→".repeat(30));
+        let folded = fold(&line);
+        for part in folded.split("\r\n") {
+            assert!(part.len() <= MAX_LINE_LENGTH, "{}", part);
+        }
+        assert_eq!(folded.split("\r\n").next(), Some("x".repeat(73).as_str()));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
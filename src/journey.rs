@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Departure;
+
+/// Two-leg journey shown by journey views: one line from a stop, then another line from the interchange
+///
+/// Both stops have to be among the monitored stops.
+#[derive(Debug, Clone, Hash, Deserialize, Serialize)]
+pub struct JourneySettings {
+    pub first: Leg,
+    pub second: Leg,
+    /// Minutes the first leg takes to the interchange
+    pub ride: i64,
+    /// Minutes needed at least to change to the second leg
+    #[serde(default)]
+    pub transfer: i64,
+}
+
+/// A line taken from a stop
+#[derive(Debug, Clone, Hash, Deserialize, Serialize)]
+pub struct Leg {
+    /// Stop ID (RBL number) the line is boarded at, this also selects its direction
+    pub stop: i32,
+    pub line: String,
+}
+
+impl Leg {
+    fn matches(&self, dep: &Departure) -> bool {
+        dep.stop_id == Some(self.stop) && dep.line.name == self.line
+    }
+}
+
+/// A departure of the first leg and the earliest second leg departure it reaches
+pub struct Connection<'a> {
+    pub first: &'a Departure,
+    pub second: &'a Departure,
+    /// Minutes left over at the interchange on top of the transfer time
+    pub spare: i64,
+}
+
+impl JourneySettings {
+    /// Feasible connections, one per departure of the first leg that reaches a second one
+    pub fn connections<'a>(&self, departures: &[&'a Departure]) -> Vec<Connection<'a>> {
+        let mut seconds: Vec<&Departure> = departures
+            .iter()
+            .copied()
+            .filter(|dep| self.second.matches(dep))
+            .collect();
+        seconds.sort_by_key(|dep| dep.countdown);
+
+        departures
+            .iter()
+            .copied()
+            .filter(|dep| self.first.matches(dep))
+            .filter_map(|first| {
+                let earliest = first.countdown + self.ride + self.transfer;
+                let second = seconds.iter().find(|dep| dep.countdown >= earliest)?;
+                Some(Connection {
+                    first,
+                    second,
+                    spare: second.countdown - earliest,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,271 @@
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, io, str::FromStr, sync::Arc, thread};
+use tokio::sync::{mpsc, Notify};
+
+/// What a key pressed on the board asks for, bound in [`KeyBindings`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    /// Write the board to a file, keeping its colors as ANSI codes if `ansi`
+    Snapshot { ansi: bool },
+    /// Also Ctrl-C, which no longer sends SIGINT while keys are read
+    Quit,
+    /// Request all views again right away
+    Refresh,
+    /// Move the selected row by this many rows
+    Select(isize),
+    /// No row is selected anymore
+    Deselect,
+    /// Copy the selected departure to the clipboard
+    Copy,
+    /// Show the departures of the next hour on a timeline instead of the board, or the board again
+    Graph,
+    /// Show the keys and the version and build information instead of the board, or the board again
+    Help,
+    /// Not a key, the terminal was resized to this many columns and rows
+    Resize(u16, u16),
+}
+
+/// A key like `q`, `down` or `ctrl-r`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Key {
+    code: KeyCode,
+    ctrl: bool,
+}
+
+impl Key {
+    const fn char(c: char) -> Self {
+        Key {
+            code: KeyCode::Char(c),
+            ctrl: false,
+        }
+    }
+
+    const fn code(code: KeyCode) -> Self {
+        Key { code, ctrl: false }
+    }
+
+    fn matches(&self, key: &KeyEvent) -> bool {
+        self.code == key.code && self.ctrl == key.modifiers.contains(KeyModifiers::CONTROL)
+    }
+}
+
+/// Names of the keys that aren't characters
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("esc", KeyCode::Esc),
+    ("enter", KeyCode::Enter),
+    ("space", KeyCode::Char(' ')),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+];
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let (ctrl, key) = match name.strip_prefix("ctrl-") {
+            Some(key) => (true, key),
+            None => (false, name),
+        };
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            // a single character keeps its case, `S` is another key than `s`
+            (Some(c), None) => KeyCode::Char(c),
+            _ => NAMED_KEYS
+                .iter()
+                .find(|(named, _)| named.eq_ignore_ascii_case(key))
+                .map(|(_, code)| *code)
+                .or_else(|| {
+                    key.strip_prefix(['f', 'F'])
+                        .and_then(|number| number.parse().ok())
+                        .map(KeyCode::F)
+                })
+                .ok_or_else(|| format!("unknown key `{}`", name))?,
+        };
+        Ok(Key { code, ctrl })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("ctrl-")?;
+        }
+        match (
+            self.code,
+            NAMED_KEYS.iter().find(|(_, code)| *code == self.code),
+        ) {
+            (_, Some((name, _))) => f.write_str(name),
+            (KeyCode::Char(c), None) => write!(f, "{}", c),
+            (KeyCode::F(number), None) => write!(f, "f{}", number),
+            (code, None) => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        key.to_string()
+    }
+}
+
+/// Keys of the full screen board by action, see `[keys]` in the config
+///
+/// Ctrl-C always quits, so the board can't be left without a way out.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub quit: Vec<Key>,
+    pub refresh: Vec<Key>,
+    pub down: Vec<Key>,
+    pub up: Vec<Key>,
+    pub deselect: Vec<Key>,
+    pub copy: Vec<Key>,
+    pub snapshot: Vec<Key>,
+    pub snapshot_ansi: Vec<Key>,
+    pub graph: Vec<Key>,
+    pub help: Vec<Key>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            quit: vec![Key::char('q')],
+            refresh: vec![Key::char('r')],
+            down: vec![Key::code(KeyCode::Down), Key::char('j')],
+            up: vec![Key::code(KeyCode::Up), Key::char('k')],
+            deselect: vec![Key::code(KeyCode::Esc)],
+            copy: vec![Key::char('y')],
+            snapshot: vec![Key::char('s')],
+            snapshot_ansi: vec![Key::char('S')],
+            graph: vec![Key::char('g')],
+            help: vec![Key::char('?')],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Every action with the keys bound to it and what it does
+    fn actions(&self) -> [(&[Key], KeyAction, &'static str); 10] {
+        [
+            (&self.quit, KeyAction::Quit, "quit"),
+            (&self.refresh, KeyAction::Refresh, "refresh now"),
+            (&self.down, KeyAction::Select(1), "select the next row"),
+            (&self.up, KeyAction::Select(-1), "select the previous row"),
+            (&self.deselect, KeyAction::Deselect, "select no row"),
+            (&self.copy, KeyAction::Copy, "copy the selected departure"),
+            (
+                &self.snapshot,
+                KeyAction::Snapshot { ansi: false },
+                "write the board to a file",
+            ),
+            (
+                &self.snapshot_ansi,
+                KeyAction::Snapshot { ansi: true },
+                "write the board to a file with colors",
+            ),
+            (
+                &self.graph,
+                KeyAction::Graph,
+                "show or close the timeline of the next hour",
+            ),
+            (&self.help, KeyAction::Help, "show or close this help"),
+        ]
+    }
+
+    /// The keys of every action, like "q, ctrl-c", and what it does
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        self.actions()
+            .into_iter()
+            .map(|(keys, action, description)| {
+                let mut names: Vec<String> = keys.iter().map(Key::to_string).collect();
+                if action == KeyAction::Quit {
+                    names.push("ctrl-c".to_string());
+                }
+                (names.join(", "), description)
+            })
+            .collect()
+    }
+
+    fn action(&self, key: KeyEvent) -> Option<KeyAction> {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Some(KeyAction::Quit);
+        }
+        self.actions()
+            .into_iter()
+            .find(|(keys, _, _)| keys.iter().any(|bound| bound.matches(&key)))
+            .map(|(_, action, _)| action)
+    }
+}
+
+/// Reads keys pressed on the full screen board and resizes of the terminal,
+/// the terminal is in raw mode until it is dropped
+pub struct KeyListener {
+    actions: mpsc::UnboundedReceiver<KeyAction>,
+    pressed: Arc<Notify>,
+}
+
+impl KeyListener {
+    pub fn new(bindings: KeyBindings) -> io::Result<Self> {
+        enable_raw_mode()?;
+        let (tx, actions) = mpsc::unbounded_channel();
+        let pressed = Arc::new(Notify::new());
+        let notify = pressed.clone();
+        // reading blocks, the thread ends with the process
+        thread::spawn(move || {
+            while let Ok(event) = event::read() {
+                let action = match event {
+                    Event::Key(key) => bindings.action(key),
+                    Event::Resize(width, height) => Some(KeyAction::Resize(width, height)),
+                    _ => None,
+                };
+                if let Some(action) = action {
+                    if tx.send(action).is_err() {
+                        break;
+                    }
+                    notify.notify_one();
+                }
+            }
+        });
+        Ok(KeyListener { actions, pressed })
+    }
+
+    /// Wait until a key is pressed, forever without a listener
+    pub async fn pressed(listener: &Option<KeyListener>) {
+        match listener {
+            Some(listener) => listener.pressed.notified().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// The next key action pressed since the last call, without waiting
+    pub fn next(&mut self) -> Option<KeyAction> {
+        self.actions.try_recv().ok()
+    }
+}
+
+impl Drop for KeyListener {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
@@ -0,0 +1,68 @@
+/// Lines given on the command line, forgiving about case and notation
+///
+/// `u3` matches "U3", `u 3` and `U-3` do too, and `43/44` or `43,44` match
+/// both lines.
+#[derive(Debug, Clone, Default)]
+pub struct LineMatcher {
+    names: Vec<String>,
+}
+
+impl LineMatcher {
+    pub fn new(patterns: &[String]) -> Self {
+        LineMatcher {
+            names: patterns
+                .iter()
+                .flat_map(|pattern| pattern.split(['/', ',']))
+                .map(normalize)
+                .filter(|name| !name.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Whether the line `name` was given, any line if none were
+    pub fn matches(&self, name: &str) -> bool {
+        self.names.is_empty() || self.names.contains(&normalize(name))
+    }
+}
+
+/// `name` in lower case without spaces and hyphens, "U-3" as "u3"
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(patterns: &[&str]) -> LineMatcher {
+        LineMatcher::new(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn ignores_case_and_notation() {
+        for pattern in ["u3", "U3", "u 3", "U-3"] {
+            assert!(matcher(&[pattern]).matches("U3"), "{}", pattern);
+        }
+        assert!(!matcher(&["u3"]).matches("U4"));
+        assert!(!matcher(&["u3"]).matches("U33"));
+    }
+
+    #[test]
+    fn expands_lists() {
+        let lines = matcher(&["43/44", "N25"]);
+        assert!(lines.matches("43"));
+        assert!(lines.matches("44"));
+        assert!(lines.matches("N25"));
+        assert!(!lines.matches("45"));
+        assert!(matcher(&["13A,14a"]).matches("14A"));
+    }
+
+    #[test]
+    fn matches_everything_without_lines() {
+        assert!(matcher(&[]).matches("D"));
+        assert!(matcher(&[" / "]).matches("D"));
+    }
+}
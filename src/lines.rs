@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Display overrides keyed by line name, the `[lines."43"]` tables of the config
+pub type LineOverrides = BTreeMap<String, LineOverride>;
+
+/// How a single line is shown on the board
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LineOverride {
+    /// Color of the line name as red, green and blue, instead of the palette's
+    pub color: Option<[u8; 3]>,
+    /// Shown instead of the line name, e.g. "U3 Ott"
+    pub label: Option<String>,
+    /// Leave the line's departures off the board
+    pub hide: bool,
+    /// Keep the line's departures at the top of the board
+    pub pin: bool,
+}
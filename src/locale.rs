@@ -0,0 +1,74 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Language times and durations on the board are written in, selected with `--lang`
+///
+/// Times are always on the 24-hour clock, as in both languages' timetables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+}
+
+impl Lang {
+    /// Countdown next to a departure time, e.g. "+3" or "in 3 Min."
+    pub fn countdown(self, minutes: i64) -> String {
+        match self {
+            Lang::En => format!("+{}", minutes),
+            Lang::De => format!("in {} Min.", minutes),
+        }
+    }
+
+    /// Duration in minutes, e.g. "3 min" or "3 Min."
+    pub fn minutes(self, minutes: i64) -> String {
+        match self {
+            Lang::En => format!("{} min", minutes),
+            Lang::De => format!("{} Min.", minutes),
+        }
+    }
+
+    /// How long ago a departure left, e.g. "left 3 min ago" or "vor 3 Min. abgefahren"
+    pub fn departed(self, minutes: i64) -> String {
+        match (self, minutes) {
+            (Lang::En, 0) => "just left".to_string(),
+            (Lang::En, minutes) => format!("left {} ago", self.minutes(minutes)),
+            (Lang::De, 0) => "soeben abgefahren".to_string(),
+            (Lang::De, minutes) => format!("vor {} abgefahren", self.minutes(minutes)),
+        }
+    }
+
+    /// Marks a departure that vanished before its time, e.g. "07:42 cancelled?"
+    pub fn cancelled(self) -> &'static str {
+        match self {
+            Lang::En => "cancelled?",
+            Lang::De => "fällt aus?",
+        }
+    }
+
+    /// Minutes a departure leaves earlier than planned, e.g. "-2 early" or "2 Min. früher"
+    pub fn early(self, minutes: i64) -> String {
+        match self {
+            Lang::En => format!("{} early", minutes),
+            Lang::De => format!("{} früher", self.minutes(minutes.abs())),
+        }
+    }
+
+    /// Time left to leave for a departure, "now" once it is time to go
+    pub fn leave_in(self, minutes: i64) -> String {
+        match (self, minutes) {
+            (Lang::En, ..=0) => "now".to_string(),
+            (Lang::De, ..=0) => "jetzt".to_string(),
+            (lang, minutes) => lang.minutes(minutes),
+        }
+    }
+
+    /// Page of the departures shown, e.g. "page 1/3" or "Seite 1/3"
+    pub fn page(self, page: usize, pages: usize) -> String {
+        match self {
+            Lang::En => format!("page {}/{}", page, pages),
+            Lang::De => format!("Seite {}/{}", page, pages),
+        }
+    }
+}
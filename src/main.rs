@@ -1,24 +1,131 @@
-use anyhow::{Context, Result};
+mod about;
+mod alerts;
+mod animation;
+mod announce;
+mod anomaly;
+mod board_cache;
+mod check;
+mod cli;
+mod clipboard;
+mod clock;
+mod control;
+mod countdown;
+mod daemon;
+mod demo;
+mod departures;
+mod destinations;
+mod disruptions;
+mod doctor;
+mod explain;
+mod expression;
+mod filter;
+mod header;
+mod headway;
+mod http;
+mod ics;
+mod journey;
+mod keys;
+mod line_match;
+mod lines;
+mod locale;
+mod mqtt;
+mod ntfy;
+mod occupancy;
+mod output;
+mod pixel;
+mod power;
+mod profile;
+mod provider;
+mod qr;
+mod quiet;
+mod reload;
+mod request_limit;
+mod route;
+mod scheduler;
+mod script;
+mod settings;
+mod shared_cache;
+mod shutdown;
+mod siblings;
+mod snapshot;
+mod state;
+mod stats;
+mod table_style;
+mod template;
+mod theme;
+mod timeline;
+mod timetable;
+mod update;
+mod view;
+mod walking;
+mod widgets;
+mod wiener_linien;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, Timelike, Utc};
+use clap::Parser;
 use comfy_table::{
-    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, ContentArrangement, Row, Table,
-};
-use crossterm::{
-    cursor::{self, MoveTo},
-    execute, queue,
-    style::Print,
-    terminal::{self, size, ClearType},
+    Attribute, Cell, Color, ColumnConstraint, ContentArrangement, Row, Table, TableComponent, Width,
 };
 use iso8601_timestamp::Timestamp;
-use serde::Deserialize;
-use serde_json::Value;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::{
-    io::{stdout, Write},
-    thread::sleep,
-    time::Duration,
+    io::IsTerminal,
+    sync::Arc,
+    time::{Duration, Instant},
     vec,
 };
 use thiserror::Error;
 
+use crate::{
+    alerts::AlertState,
+    animation::RowState,
+    anomaly::DelayBaseline,
+    board_cache::{BoardCache, BoardKey, CLOCK_PLACEHOLDER},
+    cli::{Args, Command, ExportFormat},
+    control::{ControlCommand, ControlRequest},
+    disruptions::Disruption,
+    header::HeaderSettings,
+    headway::HeadwayBaseline,
+    http::HttpSettings,
+    journey::Connection,
+    keys::{KeyAction, KeyListener},
+    line_match::LineMatcher,
+    lines::LineOverrides,
+    locale::Lang,
+    mqtt::MqttPublisher,
+    occupancy::Occupancy,
+    output::{Frame, Output},
+    power::PowerMonitor,
+    profile::{RenderProfile, Stage},
+    provider::Provider,
+    qr::QrCode,
+    reload::ReloadTrigger,
+    request_limit::RequestLimit,
+    scheduler::Scheduler,
+    script::RowScript,
+    settings::{Settings, DEFAULT_CONFIG_FILE},
+    shared_cache::SharedCache,
+    shutdown::Shutdown,
+    state::SavedState,
+    stats::UsageStats,
+    table_style::TableStyle,
+    theme::{ColorSupport, Severity, Theme, DELAY_HIGHLIGHT},
+    timetable::{TimeTravel, Timetable},
+    view::{PageIndicator, ViewKind},
+    walking::WalkingSettings,
+    widgets::{Widget, WidgetFeeds},
+    wiener_linien::{
+        WienerLinienLine, WienerLinienLineDeparture, WienerLinienMessage, WienerLinienMonitor,
+        WienerLinienResponse, WienerLinienTrafficInfo,
+    },
+};
+
 const STATION_IDS: &[i32] = &[
     252,  // Rathaus – 2 (Richtung Friedrich-Engels-Platz)
     269,  // Rathaus – 2 (Richtung Dornbach)
@@ -40,6 +147,9 @@ const STATION_IDS: &[i32] = &[
     5691, // Auerspergstraße – N46 (stadtauswärts)
 ];
 
+/// Milliseconds redraws wait past the full second, so an early timer doesn't show the previous second
+const CLOCK_MARGIN_MS: u64 = 5;
+
 const API_URL: &str = "http://www.wienerlinien.at/ogd_realtime/monitor/";
 
 #[derive(Error, Debug)]
@@ -52,9 +162,42 @@ enum ApiRequestError {
 
     #[error("Missing response field: {0}")]
     MissingField(String),
+
+    #[error("API responded with HTTP {0}")]
+    HttpStatus(reqwest::StatusCode),
+
+    #[error("API error {code}: {message}")]
+    ApiError { code: i64, message: String },
+
+    #[error("API rate limit exceeded")]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("Daemon request failed: {0}")]
+    Daemon(String),
+
+    #[error("API request timed out after {} s", .0.as_secs())]
+    TimedOut(Duration),
+
+    #[error("API request task failed: {0}")]
+    TaskFailed(#[from] tokio::task::JoinError),
+}
+
+impl ApiRequestError {
+    /// Whether the API answered but refused the stops asked for, so other stops may still work
+    fn is_stop_specific(&self) -> bool {
+        match self {
+            ApiRequestError::ApiError { .. }
+            | ApiRequestError::MissingField(_)
+            | ApiRequestError::JsonParsingFailed(_) => true,
+            ApiRequestError::HttpStatus(status) => status.is_client_error(),
+            _ => false,
+        }
+    }
 }
 
 struct WienerLinienAPIRequest {
+    /// Address of the API, [`API_URL`] or a mirror
+    url: String,
     traffic_info: String,
     stop_id: Vec<i32>,
 }
@@ -63,137 +206,206 @@ impl WienerLinienAPIRequest {
     fn to_req_url(&self) -> String {
         format!(
             "{}?activateTrafficInfo={}{}",
-            API_URL,
+            self.url,
             self.traffic_info,
             self.stop_id
                 .iter()
-                .map(|x| "&stopId=".to_string() + &x.to_string())
+                .map(|x| format!("&stopId={}", x))
                 .collect::<String>()
         )
     }
 }
 
-#[allow(non_snake_case)]
-#[derive(Debug, Clone)]
-struct WienerLinienMonitor {
-    locationStop: WienerLinienLocationStop,
-    lines: Vec<WienerLinienLine>,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct WienerLinienLocationStop {
-    //    geometry: StopGeometry,
-    #[serde(rename = "properties")]
-    properties: StopProperties,
-}
-//#[derive(Debug, Clone, Deserialize)]
-//struct StopGeometry {
-//coordinates: [f32; 2],
-//}
-
-#[derive(Debug, Clone, Deserialize)]
-struct StopProperties {
-    title: String,
-}
+impl WienerLinienMessage {
+    /// Code of a successful response
+    const OK: i64 = 1;
+    /// Code of a response refused because of too many requests
+    const RATE_LIMITED: i64 = 316;
 
-#[derive(Debug, Clone, Deserialize)]
-struct WienerLinienLine {
-    name: String,
-    #[serde(rename = "towards")]
-    destination: String,
-    #[serde(rename = "type")]
-    vehicle_type: String,
-    departures: WienerLinienLineDepartures,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct WienerLinienLineDepartures {
-    departure: Vec<WienerLinienLineDeparture>,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct WienerLinienLineDeparture {
-    #[serde(rename = "departureTime")]
-    departure_time: WienerLinienLineDepartureTime,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct WienerLinienLineDepartureTime {
-    #[serde(rename = "timePlanned")]
-    time_planned: Timestamp,
-    #[serde(rename = "timeReal")]
-    time_real: Option<Timestamp>,
-    countdown: i64,
+    fn to_error(&self) -> ApiRequestError {
+        match self.message_code {
+            WienerLinienMessage::RATE_LIMITED => ApiRequestError::RateLimited { retry_after: None },
+            code => ApiRequestError::ApiError {
+                code,
+                message: self.value.clone(),
+            },
+        }
+    }
 }
 
-#[derive(Clone, Deserialize)]
-struct WienerLinienTrafficInfo {
-    //    priority: String,
-    title: String,
-    description: String,
+/// Error for a response missing `field`, the error from its message envelope if there is one
+fn missing_field(message: &Option<WienerLinienMessage>, field: &str) -> ApiRequestError {
+    match message {
+        Some(message) if message.message_code != WienerLinienMessage::OK => message.to_error(),
+        _ => ApiRequestError::MissingField(field.to_string()),
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 enum WienerLinienVehicleType {
     Tram,
     Metro,
     CityBus,
     NightBus,
+    /// Regional and long distance trains, from providers outside Vienna
+    Train,
 }
 
-#[derive(Clone, Eq)]
+#[derive(Debug, Clone, Eq, Deserialize, Serialize)]
 struct Line {
     vehicle_type: WienerLinienVehicleType,
     name: String,
+    /// Shown instead of the name, from the line's overrides
+    #[serde(skip)]
+    label: Option<String>,
+    #[serde(skip)]
+    color: Option<[u8; 3]>,
+    #[serde(skip)]
+    pinned: bool,
 }
 
-#[derive(Clone, Eq)]
+#[derive(Debug, Clone, Eq, Deserialize, Serialize)]
 struct Departure {
     time_planned: Timestamp,
     time_real: Option<Timestamp>,
     countdown: i64,
     station_name: String,
+    /// Stop ID (RBL number) the departure was reported for
+    #[serde(skip)]
+    stop_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    platform: Option<String>,
     destination_name: String,
     line: Line,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    occupancy: Option<Occupancy>,
+    /// Flagged as stuck in a traffic jam by the monitor data
+    traffic_jam: bool,
+    /// Added by the row script, shown in a column of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    /// Countdown the API reported and the one computed from the departure time,
+    /// for the debug column of `countdown.debug`
+    #[serde(skip)]
+    countdowns: Option<(i64, i64)>,
 }
 
 impl Line {
-    fn from_wiener_linien_line(input: &WienerLinienLine) -> Self {
+    fn new(name: &str, vehicle_type: WienerLinienVehicleType, overrides: &LineOverrides) -> Self {
+        let name = clean_text(name);
+        let line_override = overrides.get(&name);
         Self {
-            name: input.name.to_owned(),
-            vehicle_type: match input.vehicle_type.as_str() {
-                "ptTram" => WienerLinienVehicleType::Tram,
-                "ptMetro" => WienerLinienVehicleType::Metro,
-                "ptBusCity" => WienerLinienVehicleType::CityBus,
-                "ptBusNight" => WienerLinienVehicleType::NightBus,
-                _ => panic!("Unknown vehicle type!"),
-            },
+            name,
+            label: line_override.and_then(|o| o.label.clone()),
+            color: line_override.and_then(|o| o.color),
+            pinned: line_override.is_some_and(|o| o.pin),
+            vehicle_type,
         }
     }
+
+    /// The line, `None` if its vehicle type is not one we know
+    fn from_wiener_linien_line(
+        input: &WienerLinienLine,
+        overrides: &LineOverrides,
+    ) -> Option<Self> {
+        let vehicle_type = match input.vehicle_type.as_str() {
+            "ptTram" => WienerLinienVehicleType::Tram,
+            "ptMetro" => WienerLinienVehicleType::Metro,
+            "ptBusCity" => WienerLinienVehicleType::CityBus,
+            "ptBusNight" => WienerLinienVehicleType::NightBus,
+            "ptTrain" | "ptTrainS" => WienerLinienVehicleType::Train,
+            _ => return None,
+        };
+        Some(Self::new(&input.name, vehicle_type, overrides))
+    }
+
+    /// Name shown on the board, the label if one is configured
+    fn display_name(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
 }
 
 impl Departure {
     fn from_wiener_linien_api(
         t_line: &WienerLinienLine,
-        t_time_planned: &Timestamp,
-        t_time_real: &Option<Timestamp>,
-        t_countdown: &i64,
+        t_departure: &WienerLinienLineDeparture,
         t_station_name: &str,
-    ) -> Self {
-        Departure {
-            line: Line::from_wiener_linien_line(t_line),
-            time_planned: *t_time_planned,
-            time_real: *t_time_real,
-            countdown: *t_countdown,
-            destination_name: t_line.destination.clone(),
-            station_name: t_station_name.to_owned(),
-        }
+        t_stop_id: Option<i32>,
+        t_overrides: &LineOverrides,
+    ) -> Option<Self> {
+        Some(Departure {
+            line: Line::from_wiener_linien_line(t_line, t_overrides)?,
+            time_planned: t_departure.departure_time.time_planned,
+            time_real: t_departure.departure_time.time_real,
+            countdown: t_departure.departure_time.countdown,
+            destination_name: clean_text(&t_line.destination),
+            station_name: clean_text(t_station_name),
+            stop_id: t_stop_id,
+            platform: t_line
+                .platform
+                .as_deref()
+                .map(clean_text)
+                .filter(|platform| !platform.is_empty()),
+            occupancy: t_departure.occupancy,
+            traffic_jam: t_line.trafficjam
+                || t_departure
+                    .vehicle
+                    .as_ref()
+                    .is_some_and(|vehicle| vehicle.trafficjam),
+            note: None,
+            countdowns: None,
+        })
+    }
+
+    /// Departure time in local time, preferring the realtime estimate
+    fn local_time(&self) -> DateTime<Local> {
+        to_local(&self.time_real.unwrap_or(self.time_planned))
+    }
+
+    /// Minutes the realtime estimate is behind the schedule, negative if early
+    fn delay_minutes(&self) -> i64 {
+        self.time_real.map_or(0, |real| {
+            real.duration_since(self.time_planned).whole_minutes()
+        })
     }
 }
 
+/// `text` from an API without surrounding, repeated or unusual whitespace and control characters
+///
+/// Names like "Schottentor  " would otherwise be measured wider than they show
+/// and be told apart from "Schottentor" when departures are deduplicated.
+fn clean_text(text: &str) -> String {
+    text.split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn to_local(time: &Timestamp) -> DateTime<Local> {
+    let millis = time
+        .duration_since(Timestamp::UNIX_EPOCH)
+        .whole_milliseconds() as i64;
+    let utc = NaiveDateTime::from_timestamp_millis(millis).unwrap_or_default();
+    DateTime::<Utc>::from_utc(utc, Utc).with_timezone(&Local)
+}
+
 impl Ord for Departure {
+    /// Pinned lines first, then by countdown
+    ///
+    /// Departures with the same countdown are ordered by line, then destination,
+    /// so they keep their rows across refreshes; realtime estimates jittering by
+    /// seconds don't reorder them.
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.countdown.cmp(&other.countdown)
+        other
+            .line
+            .pinned
+            .cmp(&self.line.pinned)
+            .then(self.countdown.cmp(&other.countdown))
+            .then_with(|| self.line.name.cmp(&other.line.name))
+            .then_with(|| self.destination_name.cmp(&other.destination_name))
+            .then_with(|| self.station_name.cmp(&other.station_name))
+            .then_with(|| self.time_planned.cmp(&other.time_planned))
     }
 }
 
@@ -218,85 +430,178 @@ impl PartialEq for Line {
     }
 }
 
-async fn get_data_from_api(req: &WienerLinienAPIRequest) -> Result<String, reqwest::Error> {
-    let res = reqwest::get(req.to_req_url()).await;
+async fn get_data_from_api(req: &WienerLinienAPIRequest) -> Result<String, ApiRequestError> {
+    let res = reqwest::get(req.to_req_url()).await?;
+    let status = res.status();
+    if matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return Err(ApiRequestError::RateLimited {
+            retry_after: retry_after(res.headers()),
+        });
+    }
+    let text = res.text().await?;
+
+    if !status.is_success() {
+        // error responses usually still carry the message envelope
+        let message = serde_json::from_str::<WienerLinienResponse>(&text)
+            .ok()
+            .and_then(|response| response.message);
+        return Err(match message {
+            Some(message) => message.to_error(),
+            None => ApiRequestError::HttpStatus(status),
+        });
+    }
+    Ok(text)
+}
 
-    return res?.text().await;
+/// Delay asked for by a `Retry-After` header, given in seconds or as HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let at = DateTime::parse_from_rfc2822(value).ok()?;
+            (at.with_timezone(&Utc) - Utc::now()).to_std().ok()
+        }
+    }
 }
 
 async fn make_api_request(
+    provider: Provider,
+    stops: &[i32],
+    lines: &LineOverrides,
 ) -> Result<(Vec<Departure>, Option<Vec<WienerLinienTrafficInfo>>), ApiRequestError> {
-    let reqobj = WienerLinienAPIRequest {
-        traffic_info: "stoerunglang".to_string(),
-        stop_id: STATION_IDS.to_vec(),
-    };
-
-    let response_text = get_data_from_api(&reqobj)
-        .await
-        .map_err(ApiRequestError::ApiReqFailed)?;
-
-    let response_json: Value =
-        serde_json::from_str(&response_text).map_err(ApiRequestError::JsonParsingFailed)?;
-
-    let response_trafficinfo_json = response_json["data"]["trafficInfos"].as_array();
+    let responses = provider.fetch(stops).await?;
 
-    let response_monitors_json = response_json["data"]["monitors"]
-        .as_array()
-        .ok_or(ApiRequestError::MissingField("monitors".to_string()))?;
+    provider
+        .parse(&responses, lines)
+        .map(|(data, _skipped)| data)
+}
 
-    let mut wl_monitors: Vec<WienerLinienMonitor> = vec![];
-    response_monitors_json.iter().try_for_each(|monitor| {
-        let stop_json = monitor["locationStop"].clone();
+/// Fields of the Wiener Linien API the monitor deliberately doesn't read, array indices left out
+const UNUSED_FIELDS: &[&str] = &[
+    "message.serverTime",
+    "data.trafficInfoCategories",
+    "data.trafficInfoCategoryGroups",
+    "data.monitors.attributes",
+    "data.monitors.locationStop.type",
+    "data.monitors.locationStop.geometry",
+    "data.monitors.locationStop.properties.name",
+    "data.monitors.locationStop.properties.municipality",
+    "data.monitors.locationStop.properties.municipalityId",
+    "data.monitors.locationStop.properties.type",
+    "data.monitors.locationStop.properties.coordName",
+    "data.monitors.locationStop.properties.gate",
+    "data.monitors.lines.direction",
+    "data.monitors.lines.richtungsId",
+    "data.monitors.lines.barrierFree",
+    "data.monitors.lines.realtimeSupported",
+    "data.monitors.lines.lineId",
+    "data.monitors.lines.departures.departure.vehicle.name",
+    "data.monitors.lines.departures.departure.vehicle.towards",
+    "data.monitors.lines.departures.departure.vehicle.direction",
+    "data.monitors.lines.departures.departure.vehicle.richtungsId",
+    "data.monitors.lines.departures.departure.vehicle.barrierFree",
+    "data.monitors.lines.departures.departure.vehicle.foldingRamp",
+    "data.monitors.lines.departures.departure.vehicle.realtimeSupported",
+    "data.monitors.lines.departures.departure.vehicle.type",
+    "data.monitors.lines.departures.departure.vehicle.linienId",
+    "data.trafficInfos.refTrafficInfoCategoryId",
+    "data.trafficInfos.owner",
+    "data.trafficInfos.time",
+    "data.trafficInfos.relatedStops",
+    "data.trafficInfos.attributes",
+];
 
-        let station =
-            serde_json::from_value(stop_json).map_err(ApiRequestError::JsonParsingFailed)?;
+/// Fields of a response the monitor doesn't know, apart from [`UNUSED_FIELDS`]
+///
+/// A renamed field shows up here under its new name, so changes of the API are
+/// noticed before they turn into silently missing data.
+fn unknown_fields(response_text: &str) -> Result<Vec<String>, serde_json::Error> {
+    let mut unknown = vec![];
+    let mut report = |prefix: &str, path: serde_ignored::Path| {
+        let path = path.to_string();
+        let field = prefix
+            .split('.')
+            .chain(path.split('.'))
+            // leave out array indices and the `?` serde_ignored marks options with
+            .filter(|segment| {
+                !segment.is_empty() && *segment != "?" && segment.parse::<usize>().is_err()
+            })
+            .collect::<Vec<_>>()
+            .join(".");
+        if !UNUSED_FIELDS.contains(&field.as_str()) && !unknown.contains(&field) {
+            unknown.push(field);
+        }
+    };
 
-        let mut v_lines: Vec<WienerLinienLine> = vec![];
+    let response: WienerLinienResponse = serde_ignored::deserialize(
+        &mut serde_json::Deserializer::from_str(response_text),
+        |path| report("", path),
+    )?;
+    // monitors are read on their own, see `parse_api_response`
+    for monitor in response
+        .data
+        .and_then(|data| data.monitors)
+        .unwrap_or_default()
+    {
+        let _: Result<WienerLinienMonitor, _> = serde_ignored::deserialize(
+            &mut serde_json::Deserializer::from_str(monitor.get()),
+            |path| report("data.monitors", path),
+        );
+    }
+    Ok(unknown)
+}
 
-        if let Some(arr_lines) = monitor["lines"].as_array() {
-            arr_lines
-                .iter()
-                .for_each(|line| v_lines.push(serde_json::from_value(line.to_owned()).unwrap()));
-            wl_monitors.push(WienerLinienMonitor {
-                lines: v_lines,
-                locationStop: station,
-            });
-            Ok(())
-        } else {
-            Err(ApiRequestError::MissingField(
-                "lines missing or of wrong type".to_string(),
-            ))
-        }
-    })?;
+/// Departures and disruptions of a response, with the number of monitors that
+/// could not be read and were skipped
+///
+/// A line of a vehicle type we don't know is skipped and counted on its own,
+/// the rest of its monitor is still shown.
+fn parse_api_response(
+    response_text: &str,
+    lines: &LineOverrides,
+) -> Result<(scheduler::ApiResponse, usize), ApiRequestError> {
+    let response: WienerLinienResponse = serde_json::from_str(response_text)?;
+    let data = response
+        .data
+        .ok_or_else(|| missing_field(&response.message, "data"))?;
+    let monitors = data
+        .monitors
+        .ok_or_else(|| missing_field(&response.message, "monitors"))?;
 
+    let mut skipped = 0;
     let mut departures: Vec<Departure> = vec![];
-    wl_monitors.iter().for_each(|monitor| {
-        let t_lines: Vec<WienerLinienLine> = monitor.lines.to_vec();
-
-        for t_line in t_lines.iter() {
-            for dep in t_line.departures.departure.iter() {
-                departures.push(Departure::from_wiener_linien_api(
+    for monitor in monitors {
+        let Ok(monitor) = serde_json::from_str::<WienerLinienMonitor>(monitor.get()) else {
+            skipped += 1;
+            continue;
+        };
+        for t_line in &monitor.lines {
+            if lines.get(&t_line.name).is_some_and(|o| o.hide) {
+                continue;
+            }
+            for dep in &t_line.departures.departure {
+                let Some(departure) = Departure::from_wiener_linien_api(
                     t_line,
-                    &dep.departure_time.time_planned,
-                    &dep.departure_time.time_real,
-                    &dep.departure_time.countdown,
-                    &monitor.locationStop.properties.title,
-                ))
+                    dep,
+                    &monitor.location_stop.properties.title,
+                    monitor.location_stop.properties.attributes.rbl,
+                    lines,
+                ) else {
+                    skipped += 1;
+                    break;
+                };
+                departures.push(departure);
             }
         }
-    });
-
-    let traffic_info = response_trafficinfo_json.and_then(|traffic_info_json| {
-        traffic_info_json
-            .iter()
-            .map(|traffic_info_value| serde_json::from_value(traffic_info_value.to_owned()))
-            .collect::<Result<Vec<WienerLinienTrafficInfo>, _>>()
-            .ok()
-    });
+    }
 
     departures.sort();
 
-    Ok((departures, traffic_info))
+    Ok(((departures, data.traffic_infos), skipped))
 }
 
 #[derive(Error, Debug)]
@@ -305,186 +610,1452 @@ enum DrawError {
     IndexOutOfBoundsError,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn get_departure_board(
-    departures: &[Departure],
-    trafficinfo: &Option<Vec<WienerLinienTrafficInfo>>,
+    departures: &[(&Departure, RowState)],
+    disruptions: &[Disruption],
     traffic_info_index: &Option<usize>,
+    footer: &Footer,
+    headings: &HeaderSettings,
+    style: &TableStyle,
+    walking: &WalkingSettings,
+    selected: Option<usize>,
+    auto_page: Option<usize>,
     width: &u16,
     height: &u16,
+    theme: &Theme,
+    lang: Lang,
 ) -> Result<Table, DrawError> {
-    let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_width(*width)
-        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
-        .set_header(vec!["Departure", "Line", "Closest station", "Destination"]);
+    let mut header = vec!["Departure", "Line", "Closest station", "Destination"];
+    let platforms = departures.iter().any(|(dep, _)| dep.platform.is_some());
+    if platforms {
+        header.insert(3, "Platform");
+    }
+    // notes come from the row script
+    let notes = departures.iter().any(|(dep, _)| dep.note.is_some());
+    if notes {
+        header.push("Note");
+    }
+    let countdowns = departures.iter().any(|(dep, _)| dep.countdowns.is_some());
+    if countdowns {
+        header.push("API / computed");
+    }
+    if walking.is_enabled() {
+        header.push("Leave in");
+    }
+    let columns = header.len();
+    let new_table = || {
+        let mut table = Table::new();
+        style.apply(&mut table);
+        table
+            .set_width(*width)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(headings.columns(&header));
+        style.align(&mut table, &header);
+        table
+    };
+    let mut table = new_table();
 
-    let content_height = height - 5;
+    let max_rows = style.rows(*height);
+    // departures that don't fit are paged through, the selected row's page is kept in view
+    let pages = departures.len().div_ceil(max_rows.max(1)).max(1);
+    let page = match (auto_page, selected) {
+        (_, Some(row)) if auto_page.is_some() => Some(row / max_rows.max(1)),
+        (Some(turned), _) => Some(turned % pages),
+        _ => None,
+    };
+    let first = page.map_or(0, |page| page * max_rows);
+    let footer = Footer {
+        departure_page: page.filter(|_| pages > 1).map(|page| (page, pages)),
+        ..footer.clone()
+    };
+    let footer_row = match traffic_info_index {
+        Some(index) => {
+            let disruption = match disruptions.get(*index) {
+                Some(d) => d,
+                None => return Err(DrawError::IndexOutOfBoundsError),
+            };
+            Row::from(vec![
+                Cell::new(footer.clock()),
+                Cell::new(format!("{}/{}", index + 1, disruptions.len())),
+                disruption_title(disruption, theme),
+                Cell::new(&disruption.info.description),
+            ])
+        }
+        None => footer.row(columns, theme),
+    };
+    if pages > 1 {
+        // every page gets the columns all departures would, so turning pages
+        // leaves the header and footer lines as they are and only the rows are redrawn
+        table.set_constraints(
+            column_widths(new_table(), departures, &footer_row, |dep, state| {
+                departure_cells(dep, *state, platforms, notes, countdowns, walking, lang)
+            })
+            .into_iter()
+            .map(|width| ColumnConstraint::Absolute(Width::Fixed(width))),
+        );
+    }
 
-    let mut depiter = departures.iter();
-    for _ in 0..(content_height / 3) {
-        let dep = match depiter.next() {
+    let mut depiter = departures.iter().skip(first);
+    let mut shown = 0;
+    for row in first..first + max_rows {
+        let (dep, state) = match depiter.next() {
             Some(d) => d,
             None => break,
         };
-        table.add_row(Row::from(vec![
-            format!(
-                "{:02}:{:02} (+{})",
-                if let Some(time) = dep.time_real {
-                    time.hour()
-                } else {
-                    dep.time_planned.hour()
-                },
-                if let Some(time) = dep.time_real {
-                    time.minute()
-                } else {
-                    dep.time_planned.minute()
-                },
-                dep.countdown
-            ),
-            dep.line.name.clone(),
-            dep.station_name.clone(),
-            dep.destination_name.clone(),
-        ]));
+        shown += 1;
+        let cells = departure_cells(dep, *state, platforms, notes, countdowns, walking, lang);
+        let delay_color = match dep.delay_minutes() {
+            _ if matches!(state, RowState::Departed(_)) => None,
+            _ if matches!(state, RowState::Cancelled) => theme.disruption(Severity::Major),
+            delay if delay >= DELAY_HIGHLIGHT => theme.delay(),
+            delay if delay < 0 => theme.early(),
+            _ => None,
+        };
+        table.add_row(Row::from(cells.into_iter().enumerate().map(
+            |(column, text)| {
+                let color = match column {
+                    0 => delay_color,
+                    1 => theme.line(&dep.line),
+                    _ => None,
+                };
+                let boxed = column == 1 && theme.line_boxes();
+                let mut cell = match boxed {
+                    // the padding is part of the box
+                    true => Cell::new(format!(" {} ", state.wipe(&text))),
+                    false => Cell::new(state.wipe(&text)),
+                };
+                if let Some(color) = color.or(theme.text()) {
+                    cell = cell.fg(color);
+                }
+                if boxed {
+                    cell = cell.add_attribute(Attribute::Reverse);
+                }
+                if let Some(stripe) = theme.stripe().filter(|_| style.zebra && row % 2 == 1) {
+                    cell = cell.bg(stripe);
+                }
+                if selected == Some(row) {
+                    cell = cell
+                        .add_attribute(Attribute::Bold)
+                        .add_attribute(Attribute::Underlined);
+                }
+                match state {
+                    RowState::Due => cell.add_attribute(Attribute::Reverse),
+                    RowState::Departed(_) => cell.add_attribute(Attribute::Dim),
+                    // the marker in the first column stays readable
+                    RowState::Cancelled if column > 0 => cell
+                        .add_attribute(Attribute::Dim)
+                        .add_attribute(Attribute::CrossedOut),
+                    _ => cell,
+                }
+            },
+        )));
     }
     // if there is empty space left, add empty rows to fill up the screen
-    if departures.len() < (height - 5).into() {
-        let number_of_blanks = departures.len() - ((height - 5) as usize);
-        for _ in 0..number_of_blanks {
-            table.add_row(Row::new());
-        }
+    for _ in shown..max_rows {
+        table.add_row(Row::new());
     }
 
     // add footer
-    let date = chrono::Local::now();
-    if let Some(index) = traffic_info_index {
-        let infovec = match trafficinfo {
-            Some(i) => i,
-            None => return Err(DrawError::IndexOutOfBoundsError),
-        };
-        let info = match infovec.get(*index) {
-            Some(i) => i,
-            None => return Err(DrawError::IndexOutOfBoundsError),
-        };
+    table.add_row(footer_row);
+    Ok(table)
+}
+
+/// Texts of the cells of a departure's row on the departure board
+fn departure_cells(
+    dep: &Departure,
+    state: RowState,
+    platforms: bool,
+    notes: bool,
+    countdowns: bool,
+    walking: &WalkingSettings,
+    lang: Lang,
+) -> Vec<String> {
+    let mut cells = vec![
+        match state {
+            RowState::Departed(minutes) => lang.departed(minutes),
+            RowState::Cancelled => {
+                format!("{} {}", dep.local_time().format("%H:%M"), lang.cancelled())
+            }
+            _ => time_label(dep, lang),
+        },
+        line_label(dep),
+        dep.station_name.clone(),
+        dep.destination_name.clone(),
+    ];
+    if platforms {
+        cells.insert(3, dep.platform.clone().unwrap_or_default());
+    }
+    if notes {
+        cells.push(dep.note.clone().unwrap_or_default());
+    }
+    if countdowns {
+        cells.push(
+            dep.countdowns
+                .map(|(api, computed)| format!("{} / {}", api, computed))
+                .unwrap_or_default(),
+        );
+    }
+    if walking.is_enabled() {
+        cells.push(match walking.leave_in(dep) {
+            _ if matches!(state, RowState::Departed(_) | RowState::Cancelled) => "-".to_string(),
+            Some(minutes) => lang.leave_in(minutes),
+            None => "-".to_string(),
+        });
+    }
+    cells
+}
+
+/// Widths the empty table `measured` gives its columns with a row for every departure and the footer,
+/// padding included
+fn column_widths(
+    mut measured: Table,
+    departures: &[(&Departure, RowState)],
+    footer: &Row,
+    cells: impl Fn(&Departure, &RowState) -> Vec<String>,
+) -> Vec<u16> {
+    for (dep, state) in departures {
+        measured.add_row(cells(dep, state));
+    }
+    measured.add_row(footer.clone());
+    // with a junction between all columns in the top border, whatever the style draws there
+    measured
+        .set_style(TableComponent::TopBorder, '─')
+        .set_style(TableComponent::TopBorderIntersections, '┬');
+    measured
+        .to_string()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split(|c| c != '─')
+        .filter(|border| !border.is_empty())
+        .map(|border| border.chars().count() as u16)
+        .collect()
+}
+
+/// Board listing all current disruptions, used by disruption views in carousel mode
+fn get_disruption_board(
+    disruptions: &[Disruption],
+    footer: &Footer,
+    headings: &HeaderSettings,
+    style: &TableStyle,
+    width: &u16,
+    height: &u16,
+    theme: &Theme,
+) -> Table {
+    let mut table = Table::new();
+    style.apply(&mut table);
+    table
+        .set_width(*width)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(headings.columns(&["Disruption", "Description"]));
+    style.align(&mut table, &["Disruption", "Description"]);
+
+    let max_rows = style.rows(*height);
+    for disruption in disruptions.iter().take(max_rows) {
+        table.add_row(Row::from(vec![
+            disruption_title(disruption, theme),
+            Cell::new(&disruption.info.description),
+        ]));
+    }
+    for _ in disruptions.len()..max_rows {
+        table.add_row(Row::new());
+    }
+
+    table.add_row(footer.row(2, theme));
+    table
+}
+
+/// Default names of the columns of the journey board
+const JOURNEY_COLUMNS: [&str; 5] = ["Departure", "Line", "Connection", "Line", "Spare"];
+
+/// Board listing connections of the configured journey, used by journey views in carousel mode
+#[allow(clippy::too_many_arguments)]
+fn get_journey_board(
+    connections: &[Connection],
+    footer: &Footer,
+    headings: &HeaderSettings,
+    style: &TableStyle,
+    width: &u16,
+    height: &u16,
+    theme: &Theme,
+    lang: Lang,
+) -> Table {
+    let mut table = Table::new();
+    style.apply(&mut table);
+    table
+        .set_width(*width)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(headings.columns(&JOURNEY_COLUMNS));
+    style.align(&mut table, &JOURNEY_COLUMNS);
+
+    let max_rows = style.rows(*height);
+    for connection in connections.iter().take(max_rows) {
         table.add_row(Row::from(vec![
-            format!("{}", date.format("%H:%M:%S")),
-            format!("{}/{}", index + 1, infovec.len()),
-            info.title.to_string(),
-            info.description.to_string(),
+            Cell::new(time_label(connection.first, lang)),
+            colored(
+                Cell::new(connection.first.line.display_name()),
+                theme.line(&connection.first.line),
+            ),
+            Cell::new(time_label(connection.second, lang)),
+            colored(
+                Cell::new(connection.second.line.display_name()),
+                theme.line(&connection.second.line),
+            ),
+            Cell::new(lang.minutes(connection.spare)),
         ]));
+    }
+    for _ in connections.len()..max_rows {
+        table.add_row(Row::new());
+    }
+
+    table.add_row(footer.row(5, theme));
+    table
+}
+
+/// Line name with a badge if it is stuck in a traffic jam and the occupancy gauge
+fn line_label(dep: &Departure) -> String {
+    let mut label = dep.line.display_name().to_string();
+    if dep.traffic_jam {
+        label.push_str(" ⚠");
+    }
+    if let Some(gauge) = dep.occupancy.and_then(Occupancy::gauge) {
+        label.push(' ');
+        label.push(gauge);
+    }
+    label
+}
+
+/// Departure time as "HH:MM (+countdown)", preferring the realtime estimate
+/// E.g. "43 → Neuwaldegg, 07:42 (+3)", copied with `y`
+fn summary(dep: &Departure, lang: Lang) -> String {
+    format!(
+        "{} → {}, {} ({})",
+        dep.line.display_name(),
+        dep.destination_name,
+        dep.local_time().format("%H:%M"),
+        lang.countdown(dep.countdown)
+    )
+}
+
+/// E.g. "07:42 (+3)", "~07:42 (+3)" if the time is only the schedule's, without a realtime estimate,
+/// and "07:40 (+1) -2 early" if the departure leaves earlier than planned
+fn time_label(dep: &Departure, lang: Lang) -> String {
+    let time = dep.time_real.unwrap_or(dep.time_planned);
+    let mut label = format!(
+        "{}{:02}:{:02} ({})",
+        if dep.time_real.is_some() { "" } else { "~" },
+        time.hour(),
+        time.minute(),
+        lang.countdown(dep.countdown)
+    );
+    // an early departure is easier to miss than a late one
+    if dep.delay_minutes() < 0 {
+        label.push_str(&format!(" {}", lang.early(dep.delay_minutes())));
+    }
+    label
+}
+
+/// Title of a disruption in its severity's color, with a badge while it is new
+fn disruption_title(disruption: &Disruption, theme: &Theme) -> Cell {
+    let info = disruption.info;
+    let cell = colored(
+        Cell::new(if disruption.is_new {
+            format!("NEW {}", info.title)
+        } else {
+            info.title.clone()
+        }),
+        theme.disruption(Severity::of(info)),
+    );
+    if disruption.is_new {
+        cell.add_attribute(Attribute::Bold)
     } else {
-        table.add_row(Row::from(vec![format!("{}", date.format("%H:%M:%S"))]));
+        cell
     }
-    Ok(table)
 }
 
-/// Buffer of two tables, the previous and the current one
-/// Used so only the differences need to be redrawn (to avoid flickering)
-struct Buffer {
-    width: u16,
-    height: u16,
-    content: String,
+/// `cell` in `color`, if the theme has one for it
+fn colored(cell: Cell, color: Option<Color>) -> Cell {
+    match color {
+        Some(color) => cell.fg(color),
+        None => cell,
+    }
 }
 
-impl Buffer {
-    fn new(width: u16, height: u16, content: String) -> Buffer {
-        return Buffer {
-            width,
-            height,
-            content,
-        };
+/// What fills the terminal, switching to another one redraws it from scratch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScreenMode {
+    Board,
+    /// The dim clock of the quiet hours
+    Quiet,
+    /// The timeline of upcoming departures per line
+    Graph,
+    /// The keys and about overlay
+    Help,
+}
+
+/// What the footer row of a board shows
+#[derive(Clone)]
+struct Footer<'a> {
+    page: &'a Option<PageIndicator<'a>>,
+    /// Page of departures shown and the number of pages, while auto paging
+    departure_page: Option<(usize, usize)>,
+    /// Problem to point out, like a failed request
+    notice: Option<&'a str>,
+    /// How many stop batches are stale, if any are
+    stale: Option<&'a str>,
+    /// Replaces the default footer, see `footer` in the config
+    template: Option<&'a str>,
+    disruption_count: usize,
+    /// Weekday, date and week shown before the time, see `clock` in the config
+    date: Option<String>,
+    lang: Lang,
+}
+
+impl Footer<'_> {
+    /// Row with the clock and the notice in the last of `columns`, or the filled in template
+    fn row(&self, columns: usize, theme: &Theme) -> Row {
+        if let Some(template) = self.template {
+            return Row::from(vec![Cell::new(template::render(
+                template,
+                |name| match name {
+                    "clock" => Some(self.time()),
+                    "page" => Some(
+                        self.page
+                            .as_ref()
+                            .map_or_else(String::new, |p| p.to_string()),
+                    ),
+                    "notice" => Some(self.notice.unwrap_or_default().to_string()),
+                    "stale" => Some(self.stale.unwrap_or_default().to_string()),
+                    "disruption_count" => Some(self.disruption_count.to_string()),
+                    _ => None,
+                },
+            ))]);
+        }
+        let mut cells = vec![colored(Cell::new(self.clock()), theme.text())];
+        if let Some(notice) = self.notice {
+            cells.resize(columns - 1, Cell::new(""));
+            cells.push(colored(
+                Cell::new(notice),
+                theme.disruption(Severity::Major),
+            ));
+        }
+        Row::from(cells)
     }
-    /// Get difference between cur and prev tables
-    /// Arguments:
-    fn get_diff(&self, other: &Buffer) -> Vec<(u16, u16, char)> {
-        let mut updates: Vec<(u16, u16, char)> = vec![];
 
-        for (i, (current, previous)) in self.content.chars().zip(other.content.chars()).enumerate()
-        {
-            if current != previous {
-                let x = i as u16 % self.width;
-                let y = i as u16 / self.width;
-                updates.push((x, y, current))
-            }
+    /// Clock, followed by the carousel position and the page of departures if there are any
+    fn clock(&self) -> String {
+        let mut clock = self.time();
+        if let Some(page) = self.page {
+            clock = format!("{} · {}", clock, page);
         }
-        updates
+        if let Some((page, pages)) = self.departure_page {
+            clock = format!("{} · {}", clock, self.lang.page(page + 1, pages));
+        }
+        clock
     }
 
-    fn has_resized(&self, other: &Buffer) -> bool {
-        self.width != other.width || self.height != other.height
+    /// Time with the date in front if configured
+    ///
+    /// The time is filled in after rendering, see [`CLOCK_PLACEHOLDER`].
+    fn time(&self) -> String {
+        match &self.date {
+            Some(date) => format!("{} {}", date, CLOCK_PLACEHOLDER),
+            None => CLOCK_PLACEHOLDER.to_string(),
+        }
     }
 }
 
-fn reset() -> Result<(), std::io::Error> {
-    execute!(
-        stdout(),
-        terminal::Clear(ClearType::All),
-        cursor::MoveTo(0, 0),
-        cursor::Hide
-    )
+/// Wall clock time at `instant`
+fn wall_clock(instant: Instant) -> DateTime<Local> {
+    let wait = instant.saturating_duration_since(Instant::now());
+    Local::now() + chrono::Duration::from_std(wait).unwrap_or_else(|_| chrono::Duration::zero())
+}
+
+/// Stops and request interval of every scheduler job, with the job each carousel view shows
+///
+/// Views showing the same stops share a job, requested at the shortest of
+/// their intervals, so no stop is requested once per view. Without carousel
+/// there is a single job.
+fn scheduler_jobs(settings: &Settings) -> (Vec<(&[i32], Duration)>, Vec<usize>) {
+    let refresh = Duration::from_secs(settings.refresh);
+    if settings.carousel.views.is_empty() {
+        return (vec![(settings.stops.as_slice(), refresh)], vec![]);
+    }
+    let mut jobs: Vec<(&[i32], Duration)> = vec![];
+    let mut view_jobs = vec![];
+    for view in &settings.carousel.views {
+        let stops = match view.stops.is_empty() {
+            true => settings.stops.as_slice(),
+            false => view.stops.as_slice(),
+        };
+        let interval = view.refresh.map_or(refresh, Duration::from_secs);
+        match jobs.iter().position(|(job_stops, _)| *job_stops == stops) {
+            Some(job) => {
+                jobs[job].1 = jobs[job].1.min(interval);
+                view_jobs.push(job);
+            }
+            None => {
+                view_jobs.push(jobs.len());
+                jobs.push((stops, interval));
+            }
+        }
+    }
+    (jobs, view_jobs)
+}
+
+/// Jobs to set up the scheduler with, see [`scheduler_jobs`]
+fn scheduler_sources(settings: &Settings) -> Vec<(Vec<i32>, Duration)> {
+    scheduler_jobs(settings)
+        .0
+        .into_iter()
+        .map(|(stops, interval)| (stops.to_vec(), interval))
+        .collect()
+}
+
+/// Index of the scheduler job whose data the carousel view at `view` shows, 0 without carousel
+fn view_job(settings: &Settings, view: Option<usize>) -> usize {
+    view.and_then(|view| scheduler_jobs(settings).1.get(view).copied())
+        .unwrap_or(0)
+}
+
+/// Time until just after the next full multiple of `seconds`, so the clock ticks in step
+/// with the wall clock however long a frame took
+fn until_next_tick(seconds: u64) -> Duration {
+    let now = Local::now();
+    let seconds = seconds.max(1);
+    let millis = now.timestamp().rem_euclid(seconds as i64) as u64 * 1000
+        + u64::from(now.timestamp_subsec_millis().min(999));
+    Duration::from_millis(seconds * 1000 - millis + CLOCK_MARGIN_MS)
+}
+
+/// Load the config again and continue with it, keeping the current data on screen
+fn reload_settings(
+    args: &Args,
+    profile: Option<&str>,
+    settings: &mut Settings,
+    scheduler: &mut Scheduler,
+    alerts: &mut AlertState,
+    feeds: &mut WidgetFeeds,
+) -> Result<(), config::ConfigError> {
+    // the timetable is only read at startup, so a preview keeps its time
+    let time_travel = settings.time_travel.take();
+    *settings = Settings::load(args.config.as_deref(), profile)?;
+    settings.apply_args(args);
+    settings.time_travel = time_travel;
+    scheduler.reconfigure(
+        settings.provider(),
+        settings.lines.clone(),
+        &settings.batching,
+        Duration::from_secs(settings.dedup_tolerance),
+        Duration::from_secs(settings.keep_departed * 60),
+        settings.refresh_phase.map(Duration::from_secs),
+        settings.adaptive_refresh.clone(),
+        scheduler_sources(settings),
+    );
+    scheduler.set_countdown(settings.countdown);
+    scheduler.set_destinations(settings.destinations.clone().map(Arc::new));
+    scheduler.set_script(
+        load_script(settings).map_err(|e| config::ConfigError::Message(e.to_string()))?,
+    );
+    scheduler.set_shared_cache(settings.shared_cache.clone().map(SharedCache::new));
+    scheduler.set_daemon(settings.daemon_socket.clone());
+    scheduler.set_request_limit(load_request_limit(settings));
+    scheduler.set_failover(
+        load_failover(settings).map_err(|e| config::ConfigError::Message(e.to_string()))?,
+    );
+    alerts.set_rules(settings.alerts.clone());
+    *feeds = WidgetFeeds::new(settings.layout.as_ref());
+    Ok(())
+}
+
+/// The configured row script, compiled
+fn load_script(settings: &Settings) -> Result<Option<Arc<RowScript>>> {
+    settings
+        .script
+        .as_deref()
+        .map(|path| RowScript::load(path).map(Arc::new))
+        .transpose()
+}
+
+/// Provider to fail over to and after how many failed requests, the static
+/// timetable loaded as of now unless a mirror is configured
+fn load_failover(settings: &Settings) -> Result<Option<(Provider, u32)>> {
+    // made up and previewed departures don't fail
+    let Some(failover) = settings
+        .failover
+        .as_ref()
+        .filter(|_| !settings.demo && settings.time_travel.is_none())
+    else {
+        return Ok(None);
+    };
+    let provider = match (&failover.mirror, &settings.timetable) {
+        (Some(url), _) => Provider::Mirror {
+            url: url.as_str().into(),
+        },
+        (None, Some(dir)) => {
+            let timetable = Timetable::load(dir, &settings.stops)?;
+            Provider::Timetable(Arc::new(TimeTravel::new(
+                timetable,
+                Local::now().naive_local(),
+            )))
+        }
+        (None, None) => bail!("Failing over needs a `mirror` or a `timetable`"),
+    };
+    Ok(Some((provider, failover.after)))
+}
+
+/// Stops per request the API accepts as learned before, `None` if batches stay as configured
+fn load_request_limit(settings: &Settings) -> Option<RequestLimit> {
+    let source = shared_cache::source_name(&settings.provider())?;
+    settings
+        .batching
+        .learn_limit
+        .then(|| RequestLimit::load(settings.shared_cache.clone(), &source))
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut stdout = stdout();
-
-    loop {
-        let (departures, traffic_info) = make_api_request()
-            .await
-            .context("Failed to make API request!")?;
+    let args = Args::parse();
+    let mut profile = args.profile.clone();
+    if args.version {
+        match args.verbose {
+            true => print!(
+                "{}",
+                about::text(&about::entries(
+                    settings::config_file(args.config.as_deref()).as_deref(),
+                    Settings::load(args.config.as_deref(), profile.as_deref())
+                        .ok()
+                        .as_ref(),
+                    args.output,
+                ))
+            ),
+            false => println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        }
+        return Ok(());
+    }
+    // before loading, which would stop at the first problem
+    if args.check_config {
+        let errors = check::run(args.config.as_deref(), profile.as_deref());
+        if errors > 0 {
+            return Err(anyhow!("The configuration has {} error(s)!", errors).into());
+        }
+        return Ok(());
+    }
+    let mut settings = Settings::load(args.config.as_deref(), profile.as_deref())
+        .context("Failed to load configuration!")?;
+    settings.apply_args(&args);
 
-        let mut prev_buf = Buffer::new(0, 0, "".to_string());
+    if args.explain_config {
+        print!(
+            "{}",
+            explain::explain(&settings).context("Failed to print configuration!")?
+        );
+        return Ok(());
+    }
 
-        for i in 1..11 {
-            let (mut width, mut height) = size().context("Could not determine terminal size!")?;
-            // For some reason, the above size params are 1-indexed. Drop them back down to 0.
-            width -= 1;
-            height -= 1;
+    if let Some(at) = args.at {
+        let dir = settings
+            .timetable
+            .as_deref()
+            .context("Previewing a time with --at needs a `timetable` in the configuration!")?;
+        let timetable =
+            Timetable::load(dir, &settings.stops).context("Failed to load the timetable!")?;
+        settings.time_travel = Some(Arc::new(TimeTravel::new(timetable, at)));
+    }
 
-            let traffic_info_index = if let Some(traffic) = &traffic_info {
-                Some(i % traffic.len())
+    match &args.command {
+        Some(Command::Export(format)) => {
+            let (departures, _) =
+                make_api_request(settings.provider(), &settings.stops, &settings.lines)
+                    .await
+                    .context("Failed to make API request!")?;
+            match format {
+                ExportFormat::Ics { count, lines } => {
+                    let lines = LineMatcher::new(lines);
+                    let departures: Vec<Departure> = departures::dedup(
+                        departures,
+                        Duration::from_secs(settings.dedup_tolerance),
+                    )
+                    .into_iter()
+                    .filter(|dep| settings.shows(dep))
+                    .filter(|dep| lines.matches(&dep.line.name))
+                    .take(*count)
+                    .collect();
+                    print!("{}", ics::to_ics(&departures));
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Raw { stop_ids, pointer }) => {
+            let stop_id = if stop_ids.is_empty() {
+                settings.stops.clone()
             } else {
-                None
+                stop_ids.clone()
             };
-
-            let board = get_departure_board(
-                &departures,
-                &traffic_info,
-                &None,
-//                &traffic_info_index,
-                &width,
-                &height,
+            let provider = settings.provider();
+            let responses = provider
+                .fetch(&stop_id)
+                .await
+                .context("Failed to make API request!")?;
+            // providers outside Vienna answer once per stop
+            for response_text in responses {
+                let response: serde_json::Value =
+                    serde_json::from_str(provider.json(&response_text))
+                        .context("API response is not JSON!")?;
+                let value = match pointer {
+                    Some(pointer) => response
+                        .pointer(pointer)
+                        .with_context(|| format!("Nothing at {} in the response!", pointer))?,
+                    None => &response,
+                };
+                println!("{}", serde_json::to_string_pretty(value)?);
+            }
+            return Ok(());
+        }
+        Some(Command::Route { from, to, count }) => {
+            let trips = route::plan(from, to, *count)
+                .await
+                .context("Failed to request itineraries!")?;
+            if trips.is_empty() {
+                return Err(anyhow::anyhow!("No itineraries from {} to {}", from, to).into());
+            }
+            let (width, _) = crossterm::terminal::size().unwrap_or((100, 0));
+            println!("{}", route::itinerary_table(&trips, &settings.table, width));
+            return Ok(());
+        }
+        Some(Command::Daemon) => {
+            let socket = settings
+                .daemon_socket
+                .as_deref()
+                .context("The daemon needs a `daemon_socket` in the configuration!")?;
+            daemon::run(
+                socket,
+                settings.provider(),
+                Duration::from_secs(settings.refresh),
             )
-            .context("Failed to create departure board!")?;
+            .await?;
+            return Ok(());
+        }
+        Some(Command::Siblings { stop_id, dataset }) => {
+            if settings.city != provider::City::Vienna {
+                return Err(anyhow!("Sibling stops are only known for Vienna").into());
+            }
+            siblings::run(
+                *stop_id,
+                dataset.as_deref(),
+                args.config.as_deref(),
+                &settings,
+            )
+            .await?;
+            return Ok(());
+        }
+        Some(Command::SelfUpdate { check }) => {
+            update::self_update(*check).await?;
+            return Ok(());
+        }
+        Some(Command::Doctor) => {
+            let failures = doctor::run(&settings).await;
+            if failures > 0 {
+                return Err(anyhow::anyhow!("{} checks failed", failures).into());
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let output = if args.a11y {
+        Output::A11y
+    } else if args.container {
+        Output::Plain
+    } else {
+        args.output
+    };
+    // keys are only read on the full screen board, other outputs may run without a terminal
+    let mut keys = match output == Output::Crossterm
+        && args.snapshot.is_none()
+        && std::io::stdin().is_terminal()
+    {
+        true => Some(KeyListener::new(settings.keys.clone()).context("Failed to read keys!")?),
+        false => None,
+    };
+    let mut output = output
+        .backend(&settings)
+        .context("Failed to open output!")?;
+    let shutdown = Shutdown::new().context("Failed to set up signal handling!")?;
+    // changing the address needs a restart, reloading the config keeps the server running
+    let server = settings
+        .http
+        .as_ref()
+        .map(http::serve)
+        .transpose()
+        .context("Failed to start HTTP server!")?;
+    // for passers-by to open the board on their phone
+    let qr = settings
+        .http
+        .as_ref()
+        .filter(|http| http.qr && server.is_some() && output.uses_board())
+        .and_then(HttpSettings::public_url)
+        .and_then(|url| QrCode::encode(&url));
+    let qr_lines = qr.as_ref().map(QrCode::lines).unwrap_or_default();
+    let started = Instant::now();
+    let mut scheduler = Scheduler::new(
+        settings.provider(),
+        settings.lines.clone(),
+        &settings.batching,
+        Duration::from_secs(settings.dedup_tolerance),
+        Duration::from_secs(settings.keep_departed * 60),
+        settings.refresh_phase.map(Duration::from_secs),
+        settings.adaptive_refresh.clone(),
+        scheduler_sources(&settings),
+    );
+    scheduler.set_countdown(settings.countdown);
+    scheduler.set_destinations(settings.destinations.clone().map(Arc::new));
+    scheduler.set_script(load_script(&settings)?);
+    scheduler.set_shared_cache(settings.shared_cache.clone().map(SharedCache::new));
+    scheduler.set_daemon(settings.daemon_socket.clone());
+    scheduler.set_request_limit(load_request_limit(&settings));
+    scheduler.set_failover(load_failover(&settings).context("Failed to set up failing over!")?);
+    // a stops file set later is only watched after a restart
+    let reload = ReloadTrigger::new(
+        args.config.as_deref(),
+        DEFAULT_CONFIG_FILE,
+        settings.stops_file.as_deref(),
+    )
+    .context("Failed to set up config reloading!")?;
+    let mut control = match &settings.control_socket {
+        Some(path) => Some(control::listen(path).context("Failed to open control socket!")?),
+        None => None,
+    };
+    let mut paused = false;
+    let mut render_profile = args.profile_render.map(RenderProfile::new);
+    let mut usage_stats = settings.usage_stats.clone().map(UsageStats::load);
+
+    let mut alerts = AlertState::new(settings.alerts.clone());
+    // the board shows the data from before a restart until the first requests finish
+    if let Some(path) = &settings.state_file {
+        let mut state = SavedState::load(path).context("Failed to load state!")?;
+        for (index, data) in state.take_views(&settings.lines).into_iter().enumerate() {
+            if let Some(data) = data {
+                scheduler.restore(index, data);
+            }
+        }
+        alerts.restore(state.reported);
+    }
+    let mut baseline = DelayBaseline::default();
+    let mut headways = HeadwayBaseline::default();
+    let mut mqtt = settings.mqtt.clone().map(MqttPublisher::connect);
 
-            let cur_buf = Buffer::new(
-                width,
-                height,
-                format!("{}", board),
+    let mut board_cache = BoardCache::default();
+    let mut power = PowerMonitor::new(args.power);
+    // row picked with the arrow keys
+    let mut selected: Option<usize> = None;
+    // the help and the timeline replace the board while they are toggled on
+    let mut show_help = false;
+    let mut show_graph = false;
+    let mut screen_mode = ScreenMode::Board;
+    // opened on the first copy, so a monitor nobody copies from never connects to the display server
+    let mut clipboard: Option<clipboard::Clipboard> = None;
+    // while keys are read, the size only changes with a resize event instead of being polled
+    let mut terminal_size: Option<(u16, u16)> = None;
+    let mut feeds = WidgetFeeds::new(settings.layout.as_ref());
+    let color_support = ColorSupport::detect();
+
+    for i in 1usize.. {
+        let mut take_snapshot = None;
+        let mut copy = false;
+        while let Some(action) = keys.as_mut().and_then(KeyListener::next) {
+            match action {
+                KeyAction::Quit => shutdown.request(),
+                KeyAction::Refresh => scheduler.refresh_now(),
+                KeyAction::Snapshot { ansi } => take_snapshot = Some(ansi),
+                KeyAction::Select(delta) => {
+                    selected = Some(selected.map_or(0, |row| row.saturating_add_signed(delta)))
+                }
+                KeyAction::Deselect => selected = None,
+                KeyAction::Copy => copy = true,
+                KeyAction::Graph => show_graph = !show_graph,
+                KeyAction::Help => show_help = !show_help,
+                KeyAction::Resize(width, height) => terminal_size = Some((width, height)),
+            }
+        }
+        if shutdown.is_requested() {
+            // requests still running would only hold up exiting
+            scheduler.abort();
+            output.close()?;
+            if let Some(path) = &settings.state_file {
+                let state = SavedState {
+                    views: scheduler.saved_data(),
+                    reported: alerts.reported().clone(),
+                };
+                state.save(path).context("Failed to save state!")?;
+            }
+            if let Some(usage_stats) = &mut usage_stats {
+                // losing the last minutes of counts is no reason to fail exiting
+                let _ = usage_stats.save();
+            }
+            if let Some(mqtt) = &mqtt {
+                mqtt.disconnect().await;
+            }
+            return Ok(());
+        }
+        if let Some(server) = &server {
+            server.tick();
+        }
+
+        while let Some(ControlRequest { command, reply }) =
+            control.as_mut().and_then(|rx| rx.try_recv().ok())
+        {
+            let response = match command {
+                ControlCommand::ReloadConfig | ControlCommand::SetProfile { .. } => {
+                    if let ControlCommand::SetProfile { profile: name } = command {
+                        profile = Some(name);
+                    }
+                    match reload_settings(
+                        &args,
+                        profile.as_deref(),
+                        &mut settings,
+                        &mut scheduler,
+                        &mut alerts,
+                        &mut feeds,
+                    ) {
+                        Ok(()) => control::ok(),
+                        Err(e) => control::error(&e.to_string()),
+                    }
+                }
+                ControlCommand::Pause => {
+                    paused = true;
+                    control::ok()
+                }
+                ControlCommand::Resume => {
+                    paused = false;
+                    control::ok()
+                }
+                ControlCommand::DumpDepartures => {
+                    let page = settings.carousel.current(started.elapsed());
+                    let departures: Vec<&Departure> = scheduler
+                        .departures(view_job(&settings, page.map(|p| p.index)))
+                        .map(|set| {
+                            set.current()
+                                .into_iter()
+                                .filter(|dep| settings.shows(dep))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    json!({ "ok": true, "departures": departures })
+                }
+            };
+            let _ = reply.send(response);
+        }
+
+        // a broken config file keeps the previous settings running, it's likely being edited
+        if reload.take() {
+            let _ = reload_settings(
+                &args,
+                profile.as_deref(),
+                &mut settings,
+                &mut scheduler,
+                &mut alerts,
+                &mut feeds,
             );
-            // it the window got resized, do not try to draw the differences, but redraw everything
-            if cur_buf.has_resized(&prev_buf) {
-                reset().context("Failed to reset terminal after resize")?;
-                queue!(stdout, Print(&cur_buf.content)).context("Failed to queue redraw")?;
-            } else {
-                // get differences between previous and current tables
-                let diff = cur_buf.get_diff(&prev_buf);
-                // queue the differences
-                for (x, y, char) in diff {
-                    queue!(stdout, MoveTo(x, y)).context("Failed to queue move")?;
-                    queue!(stdout, Print(char)).context("Failed to queue print")?;
+        }
+
+        scheduler.throttle(match power.on_battery() {
+            true => settings.battery.refresh_factor,
+            false => 1,
+        });
+        // requests resume by themselves once the quiet hours are over
+        let quiet = quiet::is_quiet(
+            &settings.quiet_hours,
+            &settings
+                .time_travel
+                .as_ref()
+                .map_or_else(Local::now, |travel| travel.now()),
+        );
+        let updated = if paused || quiet {
+            vec![]
+        } else {
+            scheduler.poll().await
+        };
+
+        if let Some(usage_stats) = &mut usage_stats {
+            let (succeeded, failed) = scheduler.take_request_counts();
+            usage_stats.record_requests(succeeded, failed);
+        }
+
+        if let Some(render_profile) = &mut render_profile {
+            for timings in updated.iter().filter_map(|&index| scheduler.timings(index)) {
+                render_profile.record(Stage::Fetch, timings.fetch);
+                render_profile.record(Stage::Parse, timings.parse);
+            }
+        }
+
+        if let Some(mqtt) = &mut mqtt {
+            for (departures, _) in updated.iter().filter_map(|&index| scheduler.data(index)) {
+                mqtt.publish(departures);
+            }
+        }
+
+        for (departures, _) in updated.iter().filter_map(|&index| scheduler.data(index)) {
+            baseline.record(departures, &settings.anomalies);
+            headways.record(departures, &settings.headway);
+        }
+
+        if settings.ntfy.is_some() || settings.announcements.is_some() {
+            for (departures, traffic_info) in scheduler.all_data() {
+                for dep in alerts.check_departures(departures) {
+                    if let Some(ntfy) = &settings.ntfy {
+                        ntfy.send("Departure alert", &alerts::message(dep));
+                    }
+                    if let Some(announcements) = &settings.announcements {
+                        announcements.announce(dep);
+                    }
+                }
+                if let Some(ntfy) = &settings.ntfy {
+                    for info in
+                        alerts.check_disruptions(traffic_info.as_deref().unwrap_or_default())
+                    {
+                        ntfy.send(
+                            &format!("Disruption on {}", info.related_lines.join(", ")),
+                            &format!("{}\n{}", info.title, info.description),
+                        );
+                    }
+                }
+            }
+            if !updated.is_empty() {
+                alerts.prune(scheduler.all_data());
+            }
+        }
+        if let Some(ntfy) = settings.ntfy.as_ref().filter(|_| settings.headway.notify) {
+            for gap in headways.unreported(&settings.headway) {
+                ntfy.send(&format!("Gap on {}", gap.line), &headway::message(&gap));
+            }
+        }
+
+        let (mut width, mut height) = match terminal_size {
+            Some(size) => size,
+            None => output
+                .size()
+                .context("Could not determine terminal size!")?,
+        };
+        if keys.is_some() {
+            terminal_size = Some((width, height));
+        }
+        // For some reason, the above size params are 1-indexed. Drop them back down to 0.
+        width -= 1;
+        height -= 1;
+
+        let page = settings.carousel.current(started.elapsed());
+        let job = view_job(&settings, page.as_ref().map(|p| p.index));
+        let mut departures: Vec<(&Departure, RowState)> = scheduler
+            .departures(job)
+            .map(|set| {
+                set.board(
+                    Instant::now(),
+                    Duration::from_secs(settings.keep_departed * 60),
+                )
+                .into_iter()
+                .filter(|(dep, _)| settings.shows(dep))
+                .filter(|(dep, _)| page.as_ref().is_none_or(|p| p.view.shows(dep)))
+                .collect()
+            })
+            .unwrap_or_default();
+        if settings.walking.sort {
+            departures.sort_by_key(|(dep, _)| {
+                let leave_in = settings.walking.leave_in(dep).unwrap_or(dep.countdown);
+                (!dep.line.pinned, leave_in)
+            });
+        }
+        // the selection stays on the last row when departures leave
+        selected = selected.map(|row| row.min(departures.len().saturating_sub(1)));
+        if let Some((dep, _)) = selected
+            .filter(|_| copy)
+            .and_then(|row| departures.get(row))
+        {
+            // a terminal without clipboard support just ignores it
+            let _ = clipboard
+                .get_or_insert_with(clipboard::Clipboard::new)
+                .copy(&summary(dep, settings.lang));
+        }
+        let disruptions = scheduler
+            .disruptions(job)
+            .map(|set| set.current(Instant::now()))
+            .unwrap_or_default();
+
+        let stale = match scheduler.stale(job) {
+            (0, _) => None,
+            (stale, total) => Some(format!("{} of {} stop batches stale", stale, total)),
+        };
+        let notice = scheduler
+            .error(job)
+            .map(|e| match (e, scheduler.retry_at(job)) {
+                (ApiRequestError::RateLimited { .. }, Some(retry_at)) => format!(
+                    "rate limited, retrying at {}",
+                    wall_clock(retry_at).format("%H:%M")
+                ),
+                (e, _) => e.to_string(),
+            })
+            .or_else(|| stale.clone())
+            .or_else(|| {
+                let isolated = scheduler.isolated(job);
+                let retry_at = isolated.iter().map(|(_, retry_at)| *retry_at).min()?;
+                let stops: Vec<String> =
+                    isolated.iter().map(|(stop, _)| stop.to_string()).collect();
+                Some(format!(
+                    "stop {} failing, retrying at {}",
+                    stops.join(", "),
+                    wall_clock(retry_at).format("%H:%M")
+                ))
+            })
+            .or_else(|| match scheduler.skipped(job) {
+                0 => None,
+                1 => Some("1 monitor could not be read".to_string()),
+                skipped => Some(format!("{} monitors could not be read", skipped)),
+            });
+
+        let attention = scheduler
+            .degraded(job)
+            .map(|e| {
+                format!(
+                    "Degraded: showing failover departures after {} failed requests ({})",
+                    scheduler.failures(job),
+                    e
+                )
+            })
+            .or_else(|| anomaly::banner(&baseline.anomalies(&settings.anomalies)))
+            .or_else(|| headway::banner(&headways.gaps(&settings.headway)));
+
+        let auto_page = settings
+            .auto_page
+            .map(|interval| (started.elapsed().as_secs() / interval.max(1)) as usize);
+
+        let build_started = Instant::now();
+        let now = settings
+            .time_travel
+            .as_ref()
+            .map_or_else(Local::now, |travel| travel.now());
+        let theme = Theme::new(settings.palette, color_support);
+        let feed_texts = feeds.texts();
+        let key = BoardKey {
+            departures: &departures,
+            disruptions: &disruptions,
+            notice: notice.as_deref(),
+            stale: stale.as_deref(),
+            attention: attention.as_deref(),
+            selected,
+            width,
+            height,
+            page: page.as_ref().map(|p| p.index),
+            auto_page,
+            minute: now.minute(),
+            palette: settings.palette,
+            lang: settings.lang,
+            walking: &settings.walking,
+            journey: settings.journey.as_ref(),
+            footer: settings.footer.as_deref(),
+            header: &settings.header,
+            table: &settings.table,
+            clock: &settings.clock,
+            layout: settings.layout.as_ref(),
+            feeds: &feed_texts,
+        };
+        // the banners take a line off the board each
+        let board_height = height
+            .saturating_sub(settings.header.title.is_some() as u16)
+            .saturating_sub(attention.is_some() as u16);
+        // the QR code goes next to the board where it leaves enough room
+        let qr_width = qr
+            .as_ref()
+            .map(QrCode::width)
+            .filter(|qr_width| {
+                *qr_width * 2 <= width && qr_lines.len() * 2 <= board_height as usize
+            })
+            .unwrap_or(0);
+        let board_width = width - qr_width;
+        let footer = Footer {
+            page: &page,
+            departure_page: None,
+            notice: notice.as_deref(),
+            stale: stale.as_deref(),
+            template: settings.footer.as_deref(),
+            disruption_count: disruptions.len(),
+            date: settings.clock.date_label(&now, settings.lang),
+            lang: settings.lang,
+        };
+        let mut format_started = build_started;
+        let board = if output.uses_board() || args.snapshot.is_some() || server.is_some() {
+            let content = board_cache.get_or_render(&key, || -> Result<String> {
+                let render_page = |width: u16, height: u16| -> Result<String> {
+                    let rows = settings.table.rows(height);
+                    let columns = settings.table.columns(width, departures.len(), rows);
+                    let departure_page = page.as_ref().is_none_or(|p| {
+                        !matches!(p.view.kind, ViewKind::Disruptions | ViewKind::Journey)
+                    });
+                    if departure_page && columns > 1 {
+                        // filled top to bottom, the last list pages through what is left
+                        return widgets::side_by_side(width, columns, |column, width| {
+                            let first = column * rows;
+                            let last = column + 1 == columns;
+                            let end = if last { departures.len() } else { first + rows };
+                            // the clock and notices are only shown below the last list
+                            let footer = match last {
+                                true => footer.clone(),
+                                false => Footer {
+                                    template: Some(""),
+                                    ..footer.clone()
+                                },
+                            };
+                            Ok(get_departure_board(
+                                &departures[first..end],
+                                &disruptions,
+                                &None,
+                                &footer,
+                                &settings.header,
+                                &settings.table,
+                                &settings.walking,
+                                selected
+                                    .and_then(|row| row.checked_sub(first))
+                                    .filter(|row| first + row < end),
+                                auto_page.filter(|_| last),
+                                &width,
+                                &height,
+                                &theme,
+                                settings.lang,
+                            )
+                            .context("Failed to create departure board!")?
+                            .to_string())
+                        });
+                    }
+                    let board = match &page {
+                        Some(p) if p.view.kind == ViewKind::Disruptions => get_disruption_board(
+                            &disruptions,
+                            &footer,
+                            &settings.header,
+                            &settings.table,
+                            &width,
+                            &height,
+                            &theme,
+                        ),
+                        Some(p) if p.view.kind == ViewKind::Journey => {
+                            let departures: Vec<&Departure> =
+                                departures.iter().map(|(dep, _)| *dep).collect();
+                            let connections = settings
+                                .journey
+                                .as_ref()
+                                .map(|journey| journey.connections(&departures))
+                                .unwrap_or_default();
+                            get_journey_board(
+                                &connections,
+                                &footer,
+                                &settings.header,
+                                &settings.table,
+                                &width,
+                                &height,
+                                &theme,
+                                settings.lang,
+                            )
+                        }
+                        _ => get_departure_board(
+                            &departures,
+                            &disruptions,
+                            &None,
+                            &footer,
+                            &settings.header,
+                            &settings.table,
+                            &settings.walking,
+                            selected,
+                            auto_page,
+                            &width,
+                            &height,
+                            &theme,
+                            settings.lang,
+                        )
+                        .context("Failed to create departure board!")?,
+                    };
+                    Ok(board.to_string())
+                };
+                let board = match &settings.layout {
+                    Some(layout) => layout.render(
+                        board_width,
+                        board_height,
+                        |index, widget, width, height| -> Result<String> {
+                            Ok(match widget {
+                                Widget::Departures => render_page(width, height)?,
+                                Widget::Disruptions => get_disruption_board(
+                                    &disruptions,
+                                    &footer,
+                                    &settings.header,
+                                    &settings.table,
+                                    &width,
+                                    &height,
+                                    &theme,
+                                )
+                                .to_string(),
+                                Widget::Clock => footer.time(),
+                                Widget::BigClock => widgets::big_clock(&now, width, height),
+                                Widget::Weather { .. } => {
+                                    feed_texts.get(index).cloned().flatten().unwrap_or_default()
+                                }
+                                Widget::Text { text, .. } => feed_texts
+                                    .get(index)
+                                    .cloned()
+                                    .flatten()
+                                    .unwrap_or_else(|| text.clone()),
+                            })
+                        },
+                    )?,
+                    None => render_page(board_width, board_height)?,
+                };
+                format_started = Instant::now();
+                let banners =
+                    settings
+                        .header
+                        .banner(width)
+                        .into_iter()
+                        .chain(attention.as_ref().map(|attention| {
+                            format!("⚠ {}", attention)
+                                .chars()
+                                .take(width as usize)
+                                .collect()
+                        }));
+                Ok(banners.chain([board]).collect::<Vec<String>>().join("\n"))
+            })?;
+            let content = match (show_help, quiet) {
+                (true, _) => about::screen(
+                    &settings.keys.help(),
+                    &about::entries(
+                        settings::config_file(args.config.as_deref()).as_deref(),
+                        Some(&settings),
+                        // `?` is only read on the full screen board
+                        Output::Crossterm,
+                    ),
+                    &settings.table,
+                    width,
+                ),
+                (false, true) => quiet::screen(&now, width, height, &theme),
+                (false, false) if show_graph => {
+                    let upcoming: Vec<&Departure> = departures
+                        .iter()
+                        .filter(|(_, state)| !state.has_left())
+                        .map(|(dep, _)| *dep)
+                        .collect();
+                    timeline::render(&upcoming, &now, width, height, &theme)
+                }
+                (false, false) => {
+                    content.replace(CLOCK_PLACEHOLDER, &now.format("%H:%M:%S").to_string())
+                }
+            };
+            if let Some(server) = &server {
+                server.publish(&content);
+                let (_, view_jobs) = scheduler_jobs(&settings);
+                for index in &updated {
+                    let departures: Vec<&Departure> = scheduler
+                        .departures(*index)
+                        .map(|set| {
+                            set.current()
+                                .into_iter()
+                                .filter(|dep| settings.shows(dep))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    // clients are told about views, several of which may show one job
+                    let views = (0..view_jobs.len().max(1))
+                        .filter(|view| view_jobs.get(*view).copied().unwrap_or(0) == *index);
+                    for view in views {
+                        server.push(view, json!(departures));
+                    }
                 }
-                // for debugging: print both!
-                //                queue!(stdout, Print(&cur_buf.content)).context("bla")?;
-                //               queue!(stdout, Print(&prev_buf.content)).context("bla")?;
             }
-            stdout.flush().context("Failed to write table to stdout")?;
-            // reset cursor to (0,0) just in case
-            execute!(stdout, MoveTo(0, 0)).context("Failed to reset cursor")?;
+            Some(match qr_width {
+                0 => content,
+                _ => widgets::corner(&content, &qr_lines, board_width),
+            })
+        } else {
+            None
+        };
+        if let (Some(ansi), Some(board)) = (take_snapshot, &board) {
+            // a snapshot that can't be written is no reason to stop the board
+            let _ = snapshot::write(&snapshot::file_name(&now), board, ansi);
+        }
+        if let Some(path) = &args.snapshot {
+            // the board is written instead of drawn, once there is something to show
+            if scheduler.data(job).is_some() || scheduler.error(job).is_some() {
+                snapshot::write(
+                    path,
+                    board.as_deref().unwrap_or_default(),
+                    args.snapshot_ansi,
+                )
+                .context("Failed to write snapshot!")?;
+                return Ok(());
+            }
+            shutdown.sleep(animation::FRAME).await;
+            continue;
+        }
+
+        // what the last screen left behind must not show through the new one
+        let mode = match (show_help, quiet) {
+            (true, _) => ScreenMode::Help,
+            (false, true) => ScreenMode::Quiet,
+            (false, false) if show_graph => ScreenMode::Graph,
+            (false, false) => ScreenMode::Board,
+        };
+        let redraw = mode != screen_mode;
+        screen_mode = mode;
+
+        let diff_started = Instant::now();
+        output.draw(&Frame {
+            board: board.as_deref(),
+            departures: &departures,
+            disruptions: &disruptions,
+            notice: notice.as_deref(),
+            attention: attention.as_deref(),
+            now,
+            width,
+            height,
+            redraw,
+        })?;
+        let flush_started = Instant::now();
+        output.flush()?;
+        let flushed = Instant::now();
+        if let Some(usage_stats) = &mut usage_stats {
+            usage_stats.record_frame(build_started.elapsed(), &now);
+        }
+
+        if let Some(render_profile) = &mut render_profile {
+            if format_started != build_started {
+                render_profile.record(Stage::Build, format_started - build_started);
+                render_profile.record(Stage::Format, diff_started - format_started);
+            }
+            render_profile.record(Stage::Diff, flush_started - diff_started);
+            render_profile.record(Stage::Flush, flush_started.elapsed());
+            if render_profile.is_done(i) {
+                output.close()?;
+                print!("{}", render_profile.report());
+                return Ok(());
+            }
+        }
 
-            prev_buf = cur_buf;
-            sleep(Duration::from_secs(1));
+        // leaving rows are animated, everything else only changes once a second
+        let pause = if departures.iter().any(|(_, state)| state.is_leaving()) {
+            animation::FRAME
+        } else {
+            // on battery, the clock may as well skip a few seconds
+            until_next_tick(match power.on_battery() {
+                true => settings.battery.redraw,
+                false => 1,
+            })
+        };
+        // frames over the cap wait for their turn before they are built, so none is shown stale
+        let pause = match settings.max_fps.filter(|fps| *fps > 0) {
+            Some(fps) => {
+                pause.max((Duration::from_secs(1) / fps).saturating_sub(flushed.elapsed()))
+            }
+            None => pause,
+        };
+        tokio::select! {
+            _ = shutdown.sleep(pause) => {}
+            _ = KeyListener::pressed(&keys) => {}
         }
     }
+    Ok(())
 }
@@ -0,0 +1,186 @@
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
+
+use crate::Departure;
+
+/// Publish the next departures of every line to an MQTT broker
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MqttSettings {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Prefix of the state and attribute topics
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    /// Prefix Home Assistant listens on for discovery messages
+    #[serde(default = "default_discovery_prefix")]
+    pub discovery_prefix: String,
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_topic() -> String {
+    "oeffimonitor".to_string()
+}
+
+fn default_discovery_prefix() -> String {
+    "homeassistant".to_string()
+}
+
+/// Number of departures listed in the attributes of a line sensor
+const ATTRIBUTE_DEPARTURES: usize = 3;
+
+/// Payloads of the availability topic
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+/// How long queued messages get to reach the broker when disconnecting
+const DISCONNECT_GRACE: Duration = Duration::from_millis(500);
+
+pub struct MqttPublisher {
+    client: AsyncClient,
+    settings: MqttSettings,
+    /// Lines that already got a Home Assistant discovery message
+    announced: HashSet<String>,
+}
+
+impl MqttPublisher {
+    pub fn connect(settings: MqttSettings) -> Self {
+        let availability_topic = availability_topic(&settings);
+        let mut options = MqttOptions::new("oeffimonitor-cli", &settings.host, settings.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        // the broker marks the sensors unavailable if the monitor goes away without saying so
+        options.set_last_will(LastWill::new(
+            &availability_topic,
+            OFFLINE,
+            QoS::AtLeastOnce,
+            true,
+        ));
+        if let Some(username) = &settings.username {
+            options.set_credentials(username, settings.password.clone().unwrap_or_default());
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(options, 64);
+        let online = client.clone();
+        tokio::spawn(async move {
+            loop {
+                match eventloop.poll().await {
+                    // after every (re)connect, as the last will may have been sent in between
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        let _ =
+                            online.try_publish(&availability_topic, QoS::AtLeastOnce, true, ONLINE);
+                    }
+                    Ok(_) => {}
+                    // the event loop reconnects on the next poll, don't spin while the broker is gone
+                    Err(_) => tokio::time::sleep(Duration::from_secs(5)).await,
+                }
+            }
+        });
+
+        MqttPublisher {
+            client,
+            settings,
+            announced: HashSet::new(),
+        }
+    }
+
+    /// Disconnect from the broker, after what was published before got sent
+    ///
+    /// The sensors are marked unavailable first, a clean disconnect doesn't
+    /// send the last will.
+    pub async fn disconnect(&self) {
+        self.send(availability_topic(&self.settings), OFFLINE.to_string());
+        if self.client.disconnect().await.is_ok() {
+            // the event loop sends the queued messages in the background
+            tokio::time::sleep(DISCONNECT_GRACE).await;
+        }
+    }
+
+    /// Publish one sensor per line, announcing lines seen for the first time to Home Assistant
+    pub fn publish(&mut self, departures: &[Departure]) {
+        let mut lines: BTreeMap<&str, Vec<&Departure>> = BTreeMap::new();
+        for dep in departures {
+            lines.entry(&dep.line.name).or_default().push(dep);
+        }
+
+        for (line, deps) in lines {
+            let id = object_id(line);
+            let state_topic = format!("{}/{}/state", self.settings.topic, id);
+            let attributes_topic = format!("{}/{}/attributes", self.settings.topic, id);
+
+            if self.announced.insert(id.clone()) {
+                let config = json!({
+                    "name": format!("Line {}", line),
+                    "unique_id": format!("oeffimonitor_{}", id),
+                    "state_topic": state_topic,
+                    "json_attributes_topic": attributes_topic,
+                    "availability_topic": availability_topic(&self.settings),
+                    "payload_available": ONLINE,
+                    "payload_not_available": OFFLINE,
+                    "unit_of_measurement": "min",
+                    "icon": "mdi:bus-clock",
+                    "device": {
+                        "identifiers": ["oeffimonitor-cli"],
+                        "name": "Öffimonitor",
+                    },
+                });
+                self.send(
+                    format!(
+                        "{}/sensor/oeffimonitor_{}/config",
+                        self.settings.discovery_prefix, id
+                    ),
+                    config.to_string(),
+                );
+            }
+
+            let attributes = json!({
+                "departures": deps
+                    .iter()
+                    .take(ATTRIBUTE_DEPARTURES)
+                    .map(|dep| json!({
+                        "countdown": dep.countdown,
+                        "time": dep.local_time().to_rfc3339(),
+                        "station": dep.station_name,
+                        "destination": dep.destination_name,
+                    }))
+                    .collect::<Vec<_>>(),
+            });
+            self.send(state_topic, deps[0].countdown.to_string());
+            self.send(attributes_topic, attributes.to_string());
+        }
+    }
+
+    fn send(&self, topic: String, payload: String) {
+        // a full request queue only loses this update, the next refresh sends a new one
+        let _ = self
+            .client
+            .try_publish(topic, QoS::AtLeastOnce, true, payload);
+    }
+}
+
+/// Topic telling Home Assistant whether the monitor is running, [`ONLINE`] or [`OFFLINE`]
+fn availability_topic(settings: &MqttSettings) -> String {
+    format!("{}/availability", settings.topic)
+}
+
+/// Topic- and ID-safe version of a line name
+fn object_id(line: &str) -> String {
+    line.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
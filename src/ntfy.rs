@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// Push notifications through an ntfy server, see <https://ntfy.sh>
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NtfySettings {
+    #[serde(default = "default_server")]
+    pub server: String,
+    pub topic: String,
+    /// Access token, sent as bearer authentication
+    pub token: Option<String>,
+    /// Basic authentication, used if no token is set
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+fn default_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+impl NtfySettings {
+    /// Publish a message in the background, failures are dropped
+    pub fn send(&self, title: &str, message: &str) {
+        let mut request = reqwest::Client::new()
+            .post(format!(
+                "{}/{}",
+                self.server.trim_end_matches('/'),
+                self.topic
+            ))
+            .header("Title", title)
+            .header("Tags", "tram")
+            .body(message.to_string());
+
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        } else if let Some(username) = &self.username {
+            request = request.basic_auth(username, self.password.as_ref());
+        }
+
+        tokio::spawn(async move {
+            let _ = request.send().await;
+        });
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// How crowded a vehicle is, for providers that report it (GTFS-RT `OccupancyStatus` names)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Occupancy {
+    #[serde(alias = "MANY_SEATS_AVAILABLE")]
+    Empty,
+    FewSeatsAvailable,
+    #[serde(alias = "CRUSHED_STANDING_ROOM_ONLY")]
+    StandingRoomOnly,
+    #[serde(alias = "NOT_ACCEPTING_PASSENGERS")]
+    Full,
+    /// Reported, but not a level we know, e.g. `NO_DATA_AVAILABLE`
+    #[serde(other)]
+    Unknown,
+}
+
+impl Occupancy {
+    /// Gauge shown next to the line, `None` if the level is unknown
+    pub fn gauge(self) -> Option<char> {
+        match self {
+            Occupancy::Empty => Some('▁'),
+            Occupancy::FewSeatsAvailable => Some('▃'),
+            Occupancy::StandingRoomOnly => Some('▅'),
+            Occupancy::Full => Some('█'),
+            Occupancy::Unknown => None,
+        }
+    }
+}
@@ -0,0 +1,288 @@
+mod a11y;
+mod braille;
+mod fbdev;
+mod framebuffer;
+mod led_matrix;
+mod plain;
+
+pub use fbdev::FbdevSettings;
+pub use led_matrix::LedMatrixSettings;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use crossterm::{
+    cursor::{self, MoveTo},
+    execute, queue,
+    style::Print,
+    terminal::{self, size, ClearType},
+};
+use std::io::{self, stdout, Stdout, Write};
+
+use crate::{
+    animation::RowState,
+    disruptions::Disruption,
+    pixel::{text_width, Canvas, GLYPH_WIDTH, LINE_HEIGHT},
+    settings::Settings,
+    Departure,
+};
+
+/// Everything a backend may put on screen for one frame
+pub struct Frame<'a> {
+    /// The rendered departure board, only set if the backend uses it
+    pub board: Option<&'a str>,
+    pub departures: &'a [(&'a Departure, RowState)],
+    pub disruptions: &'a [Disruption<'a>],
+    /// Problem to point out, e.g. why the latest request failed
+    pub notice: Option<&'a str>,
+    /// Lines running much later than usual
+    pub attention: Option<&'a str>,
+    pub now: DateTime<Local>,
+    pub width: u16,
+    pub height: u16,
+    /// The screen switched to something else, e.g. quiet hours, so nothing of
+    /// the last frame can be kept
+    pub redraw: bool,
+}
+
+/// Where and how the board is drawn
+pub trait OutputBackend {
+    /// Size of the area to draw on, in characters
+    fn size(&self) -> io::Result<(u16, u16)>;
+
+    /// Whether frames need the rendered departure board
+    fn uses_board(&self) -> bool {
+        false
+    }
+
+    /// Prepare a frame, nothing is shown before [`OutputBackend::flush`]
+    fn draw(&mut self, frame: &Frame) -> Result<()>;
+
+    /// Show the drawn frame
+    fn flush(&mut self) -> Result<()>;
+
+    /// Give the terminal back, e.g. before printing something else
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Output backends selectable with `--output`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// Full screen table, only redrawing what changed
+    #[default]
+    Crossterm,
+    /// Print the departures line by line whenever they change, for dumb terminals and logs
+    Plain,
+    /// One sentence per departure whenever they change, for screen readers (`--a11y`)
+    A11y,
+    /// Next departures in a tiny pixel font drawn with braille characters, for small displays
+    Braille,
+    /// Next departures on an LED matrix panel, configured in `[led_matrix]`
+    LedMatrix,
+    /// Clock and next departures on a Linux framebuffer, configured in `[fbdev]`
+    Fbdev,
+}
+
+impl Output {
+    pub fn backend(self, settings: &Settings) -> io::Result<Box<dyn OutputBackend>> {
+        Ok(match self {
+            Output::Crossterm => Box::new(CrosstermBackend::default()),
+            Output::Plain => Box::<plain::PlainBackend>::default(),
+            Output::A11y => Box::<a11y::A11yBackend>::default(),
+            Output::Braille => Box::<braille::BrailleBackend>::default(),
+            Output::LedMatrix => Box::new(led_matrix::LedMatrixBackend::open(
+                settings.led_matrix.clone(),
+            )?),
+            Output::Fbdev => Box::new(fbdev::FbdevBackend::open(&settings.fbdev)?),
+        })
+    }
+}
+
+/// Draw one "line destination countdown" row per departure, starting at text row `first_row`
+///
+/// Destinations are cut off where the right aligned countdown starts.
+fn draw_departures(canvas: &mut Canvas, frame: &Frame, first_row: usize) {
+    let departures = frame
+        .departures
+        .iter()
+        .filter(|(_, state)| !state.has_left());
+    for (row, (dep, _)) in (first_row..canvas.height() / LINE_HEIGHT).zip(departures) {
+        let y = row * LINE_HEIGHT;
+        let countdown = dep.countdown.to_string();
+        let countdown_x = canvas.width().saturating_sub(text_width(&countdown));
+        let destination_x = canvas.text(0, y, dep.line.display_name()) + 1;
+
+        let columns = countdown_x.saturating_sub(destination_x) / (GLYPH_WIDTH + 1);
+        let destination: String = dep.destination_name.chars().take(columns).collect();
+        canvas.text(destination_x, y, &destination);
+        canvas.text(countdown_x, y, &countdown);
+    }
+}
+
+/// Full screen terminal drawing, only redrawing the lines that changed
+///
+/// The commands of a frame are collected and written at once when it is
+/// flushed, so slow terminals like serial consoles never show half of one.
+pub struct Screen {
+    stdout: Stdout,
+    /// Commands of the frame being drawn
+    queued: Vec<u8>,
+    prev_buf: Buffer,
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen {
+            stdout: stdout(),
+            queued: vec![],
+            prev_buf: Buffer::new(0, 0, "".to_string()),
+        }
+    }
+}
+
+impl Screen {
+    pub fn draw(&mut self, width: u16, height: u16, content: String, redraw: bool) -> Result<()> {
+        let cur_buf = Buffer::new(width, height, content);
+        // it the window got resized, do not try to draw the differences, but redraw everything
+        if redraw || cur_buf.has_resized(&self.prev_buf) {
+            queue!(
+                self.queued,
+                terminal::Clear(ClearType::All),
+                MoveTo(0, 0),
+                cursor::Hide
+            )
+            .context("Failed to queue reset after resize")?;
+            // line by line, a newline doesn't return the cursor while keys are read in raw mode
+            for (y, line) in cur_buf.content.lines().enumerate() {
+                queue!(self.queued, MoveTo(0, y as u16), Print(line))
+                    .context("Failed to queue redraw")?;
+            }
+        } else {
+            // get differences between previous and current tables
+            let diff = cur_buf.get_diff(&self.prev_buf);
+            // queue the differences
+            // a line that got shorter would leave the end of the old one behind
+            for (y, line) in diff {
+                queue!(
+                    self.queued,
+                    MoveTo(0, y),
+                    Print(line),
+                    terminal::Clear(ClearType::UntilNewLine)
+                )
+                .context("Failed to queue line")?;
+            }
+            // as would a frame with fewer lines than the last one
+            let rows = cur_buf.content.lines().count();
+            if rows < self.prev_buf.content.lines().count() {
+                queue!(
+                    self.queued,
+                    MoveTo(0, rows as u16),
+                    terminal::Clear(ClearType::FromCursorDown)
+                )
+                .context("Failed to queue clearing removed lines")?;
+            }
+        }
+        self.prev_buf = cur_buf;
+        Ok(())
+    }
+
+    /// Write the frame in one go
+    pub fn flush(&mut self) -> Result<()> {
+        if self.queued.is_empty() {
+            return Ok(());
+        }
+        // reset cursor to (0,0) just in case
+        queue!(self.queued, MoveTo(0, 0)).context("Failed to queue cursor reset")?;
+        let mut stdout = self.stdout.lock();
+        stdout
+            .write_all(&self.queued)
+            .and_then(|_| stdout.flush())
+            .context("Failed to write table to stdout")?;
+        self.queued.clear();
+        Ok(())
+    }
+
+    pub fn close(&mut self) -> Result<()> {
+        reset().context("Failed to reset terminal")?;
+        execute!(self.stdout, cursor::Show).context("Failed to show cursor")?;
+        Ok(())
+    }
+}
+
+/// The departure board as a table, drawn with crossterm
+#[derive(Default)]
+pub struct CrosstermBackend {
+    screen: Screen,
+}
+
+impl OutputBackend for CrosstermBackend {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        size()
+    }
+
+    fn uses_board(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, frame: &Frame) -> Result<()> {
+        self.screen.draw(
+            frame.width,
+            frame.height,
+            frame.board.unwrap_or_default().to_string(),
+            frame.redraw,
+        )
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.screen.flush()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.screen.close()
+    }
+}
+
+/// Buffer of two tables, the previous and the current one
+/// Used so only the differences need to be redrawn (to avoid flickering)
+struct Buffer {
+    width: u16,
+    height: u16,
+    content: String,
+}
+
+impl Buffer {
+    fn new(width: u16, height: u16, content: String) -> Buffer {
+        Buffer {
+            width,
+            height,
+            content,
+        }
+    }
+    /// Get the lines of cur that differ from prev, with their row
+    ///
+    /// Whole lines are compared, so styled text is never redrawn halfway.
+    fn get_diff<'a>(&'a self, other: &Buffer) -> Vec<(u16, &'a str)> {
+        let mut previous = other.content.lines();
+        self.content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| previous.next() != Some(*line))
+            .map(|(y, line)| (y as u16, line))
+            .collect()
+    }
+
+    fn has_resized(&self, other: &Buffer) -> bool {
+        self.width != other.width || self.height != other.height
+    }
+}
+
+fn reset() -> Result<(), std::io::Error> {
+    execute!(
+        stdout(),
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0),
+        cursor::Hide
+    )
+}
@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use std::{
+    fmt::Write as _,
+    io::{self, stdout, Write},
+};
+
+use super::{Frame, OutputBackend};
+use crate::Departure;
+
+/// Number of departures read out per refresh
+const MAX_DEPARTURES: usize = 10;
+
+/// Prints one plain sentence per departure whenever they change, for screen readers
+///
+/// There is no box drawing and nothing is redrawn in place, so every refresh
+/// is read out once and in order.
+#[derive(Default)]
+pub struct A11yBackend {
+    last: String,
+    pending: Option<String>,
+}
+
+impl OutputBackend for A11yBackend {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok((80, 24))
+    }
+
+    fn draw(&mut self, frame: &Frame) -> Result<()> {
+        let mut text = String::new();
+        for (dep, _) in frame
+            .departures
+            .iter()
+            .filter(|(_, state)| !state.has_left())
+            .take(MAX_DEPARTURES)
+        {
+            let _ = writeln!(text, "{}.", sentence(dep));
+        }
+        for disruption in frame.disruptions {
+            let _ = writeln!(
+                text,
+                "{}: {}.",
+                if disruption.is_new {
+                    "New disruption"
+                } else {
+                    "Disruption"
+                },
+                disruption.info.title.trim_end_matches('.')
+            );
+        }
+        if let Some(attention) = frame.attention {
+            let _ = writeln!(text, "Attention: {}.", attention);
+        }
+        if let Some(notice) = frame.notice {
+            let _ = writeln!(text, "Warning: {}.", notice);
+        }
+
+        if text != self.last {
+            self.pending = Some(format!(
+                "Departures at {}:\n{}",
+                frame.now.format("%H:%M"),
+                text
+            ));
+            self.last = text;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some(text) = self.pending.take() {
+            let mut stdout = stdout();
+            writeln!(stdout, "{}", text).context("Failed to write departures to stdout")?;
+            stdout
+                .flush()
+                .context("Failed to write departures to stdout")?;
+        }
+        Ok(())
+    }
+}
+
+/// E.g. "43 to Neuwaldegg from Schottentor in 4 minutes, delayed 2 minutes"
+fn sentence(dep: &Departure) -> String {
+    let mut sentence = format!(
+        "{} to {} from {}",
+        dep.line.name, dep.destination_name, dep.station_name
+    );
+    if let Some(platform) = &dep.platform {
+        let _ = write!(sentence, " platform {}", platform);
+    }
+    let _ = write!(
+        sentence,
+        " {}",
+        match dep.countdown {
+            ..=0 => "now".to_string(),
+            minutes => format!("in {}", minutes_text(minutes)),
+        }
+    );
+    let delay = dep.time_real.map_or(0, |real| {
+        real.duration_since(dep.time_planned).whole_minutes()
+    });
+    if delay > 0 {
+        let _ = write!(sentence, ", delayed {}", minutes_text(delay));
+    } else if delay < 0 {
+        let _ = write!(sentence, ", {} early", minutes_text(-delay));
+    }
+    if dep.time_real.is_none() {
+        sentence.push_str(", as scheduled without live data");
+    }
+    if dep.traffic_jam {
+        sentence.push_str(", stuck in a traffic jam");
+    }
+    sentence
+}
+
+fn minutes_text(minutes: i64) -> String {
+    match minutes {
+        1 => "1 minute".to_string(),
+        minutes => format!("{} minutes", minutes),
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use crossterm::terminal::size;
+use std::io;
+
+use super::{Frame, OutputBackend, Screen};
+use crate::pixel::{Canvas, GLYPH_WIDTH, LINE_HEIGHT};
+
+/// Clock and next departures in a 3x5 pixel font, drawn with braille characters
+///
+/// Every character cell holds 2x4 pixels, which fits a readable board onto
+/// displays too small for the table.
+#[derive(Default)]
+pub struct BrailleBackend {
+    screen: Screen,
+}
+
+impl OutputBackend for BrailleBackend {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        size()
+    }
+
+    fn draw(&mut self, frame: &Frame) -> Result<()> {
+        let mut canvas = Canvas::new(frame.width as usize * 2, frame.height as usize * 4);
+        canvas.text(0, 0, &frame.now.format("%H:%M").to_string());
+
+        let columns = canvas.width() / (GLYPH_WIDTH + 1);
+        let departures = frame
+            .departures
+            .iter()
+            .filter(|(_, state)| !state.has_left());
+        for (row, (dep, _)) in (1..canvas.height() / LINE_HEIGHT).zip(departures) {
+            let line = format!(
+                "{:>2} {:<3} {}",
+                dep.countdown,
+                dep.line.display_name(),
+                dep.destination_name
+            );
+            let line: String = line.chars().take(columns).collect();
+            canvas.text(0, row * LINE_HEIGHT, &line);
+        }
+
+        self.screen
+            .draw(frame.width, frame.height, canvas.to_braille(), frame.redraw)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.screen.flush()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.screen.close()
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    draw_departures,
+    framebuffer::{Framebuffer, PixelFormat},
+    Frame, OutputBackend,
+};
+use crate::pixel::{Canvas, GLYPH_WIDTH, LINE_HEIGHT};
+
+/// Linux framebuffer drawn on by `--output fbdev`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FbdevSettings {
+    pub device: PathBuf,
+    /// Size of a font pixel in screen pixels
+    pub scale: usize,
+    /// Color of the text, as red, green and blue
+    pub color: [u8; 3],
+}
+
+impl Default for FbdevSettings {
+    fn default() -> Self {
+        FbdevSettings {
+            device: PathBuf::from("/dev/fb0"),
+            scale: 4,
+            color: [255, 160, 0],
+        }
+    }
+}
+
+/// Clock and next departures drawn straight onto a Linux framebuffer, no terminal needed
+pub struct FbdevBackend {
+    framebuffer: Framebuffer,
+    /// Canvas size in font pixels
+    width: usize,
+    height: usize,
+}
+
+impl FbdevBackend {
+    /// Open the device, its geometry is read from sysfs
+    pub fn open(settings: &FbdevSettings) -> io::Result<Self> {
+        let name = settings
+            .device
+            .file_name()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "not a framebuffer device"))?;
+        let sysfs = Path::new("/sys/class/graphics").join(name);
+
+        let size = read_sysfs(&sysfs, "virtual_size")?;
+        let (width, height) = size
+            .split_once(',')
+            .and_then(|(w, h)| Some((w.parse::<usize>().ok()?, h.parse::<usize>().ok()?)))
+            .ok_or_else(|| invalid_data(format!("unexpected virtual_size {}", size)))?;
+        let bits = read_sysfs(&sysfs, "bits_per_pixel")?;
+        let format = bits
+            .parse()
+            .ok()
+            .and_then(PixelFormat::from_bits_per_pixel)
+            .ok_or_else(|| invalid_data(format!("unsupported color depth of {} bits", bits)))?;
+        let stride = read_sysfs(&sysfs, "stride")?
+            .parse()
+            .map_err(|_| invalid_data("unexpected stride".to_string()))?;
+
+        let scale = settings.scale.max(1);
+        Ok(FbdevBackend {
+            framebuffer: Framebuffer::open(
+                &settings.device,
+                format,
+                settings.color,
+                scale,
+                Some(stride),
+            )?,
+            width: width / scale,
+            height: height / scale,
+        })
+    }
+}
+
+impl OutputBackend for FbdevBackend {
+    /// Rows and columns of text that fit on the screen
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok((
+            (self.width / (GLYPH_WIDTH + 1)) as u16,
+            (self.height / LINE_HEIGHT) as u16,
+        ))
+    }
+
+    fn draw(&mut self, frame: &Frame) -> Result<()> {
+        let mut canvas = Canvas::new(self.width, self.height);
+        canvas.text(0, 0, &frame.now.format("%H:%M:%S").to_string());
+        draw_departures(&mut canvas, frame, 2);
+        self.framebuffer.draw(&canvas);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.framebuffer.flush()
+    }
+}
+
+fn read_sysfs(dir: &Path, attribute: &str) -> io::Result<String> {
+    Ok(fs::read_to_string(dir.join(attribute))?.trim().to_string())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
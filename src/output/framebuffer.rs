@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::pixel::Canvas;
+
+/// How a pixel is laid out in the framebuffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    /// 16 bits per pixel, little endian, what most panel drivers expose
+    Rgb565,
+    /// 24 bits per pixel in red, green, blue order
+    Rgb24,
+    /// 32 bits per pixel, little endian, the usual format of display framebuffers
+    Xrgb8888,
+}
+
+impl PixelFormat {
+    /// Format of a Linux framebuffer with this color depth
+    pub fn from_bits_per_pixel(bits: u32) -> Option<Self> {
+        match bits {
+            16 => Some(PixelFormat::Rgb565),
+            32 => Some(PixelFormat::Xrgb8888),
+            _ => None,
+        }
+    }
+
+    fn encode(self, [r, g, b]: [u8; 3]) -> Vec<u8> {
+        match self {
+            PixelFormat::Rgb565 => ((r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3)
+                .to_le_bytes()
+                .to_vec(),
+            PixelFormat::Rgb24 => vec![r, g, b],
+            PixelFormat::Xrgb8888 => vec![b, g, r, 0],
+        }
+    }
+}
+
+/// A framebuffer device or pipe that canvases are written to, scaled up and in color
+///
+/// Unchanged frames are not written again.
+pub struct Framebuffer {
+    path: PathBuf,
+    device: File,
+    format: PixelFormat,
+    color: [u8; 3],
+    scale: usize,
+    /// Bytes per line, may be more than the visible pixels take
+    stride: Option<usize>,
+    last: Vec<u8>,
+    pending: Option<Vec<u8>>,
+}
+
+impl Framebuffer {
+    pub fn open(
+        path: &Path,
+        format: PixelFormat,
+        color: [u8; 3],
+        scale: usize,
+        stride: Option<usize>,
+    ) -> io::Result<Self> {
+        Ok(Framebuffer {
+            path: path.to_path_buf(),
+            device: OpenOptions::new().write(true).open(path)?,
+            format,
+            color,
+            scale: scale.max(1),
+            stride,
+            last: vec![],
+            pending: None,
+        })
+    }
+
+    pub fn draw(&mut self, canvas: &Canvas) {
+        let lit = self.format.encode(self.color);
+        let dark = vec![0; lit.len()];
+        let line_length = canvas.width() * self.scale * lit.len();
+        let padding = vec![
+            0;
+            self.stride
+                .unwrap_or(line_length)
+                .saturating_sub(line_length)
+        ];
+
+        let mut bytes =
+            Vec::with_capacity(canvas.height() * self.scale * (line_length + padding.len()));
+        for y in 0..canvas.height() {
+            let start = bytes.len();
+            for x in 0..canvas.width() {
+                let pixel = if canvas.get(x, y) { &lit } else { &dark };
+                for _ in 0..self.scale {
+                    bytes.extend_from_slice(pixel);
+                }
+            }
+            bytes.extend_from_slice(&padding);
+            for _ in 1..self.scale {
+                bytes.extend_from_within(start..start + line_length + padding.len());
+            }
+        }
+
+        if bytes != self.last {
+            self.pending = Some(bytes.clone());
+            self.last = bytes;
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(bytes) = self.pending.take() {
+            // framebuffers are rewritten from the start, pipes can't seek and just get the next frame
+            let _ = self.device.seek(SeekFrom::Start(0));
+            self.device
+                .write_all(&bytes)
+                .and_then(|_| self.device.flush())
+                .with_context(|| format!("Failed to write to {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+}
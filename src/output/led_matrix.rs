@@ -0,0 +1,75 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{io, path::PathBuf};
+
+use super::{
+    draw_departures,
+    framebuffer::{Framebuffer, PixelFormat},
+    Frame, OutputBackend,
+};
+use crate::pixel::{Canvas, GLYPH_WIDTH, LINE_HEIGHT};
+
+/// LED matrix panel, e.g. HUB75, driven through a framebuffer device
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LedMatrixSettings {
+    /// Framebuffer device or pipe the frames are written to
+    pub path: PathBuf,
+    /// Panel width in pixels
+    pub width: usize,
+    /// Panel height in pixels
+    pub height: usize,
+    pub format: PixelFormat,
+    /// Color of lit pixels, as red, green and blue
+    pub color: [u8; 3],
+}
+
+impl Default for LedMatrixSettings {
+    fn default() -> Self {
+        LedMatrixSettings {
+            path: PathBuf::from("/dev/fb1"),
+            width: 64,
+            height: 32,
+            format: PixelFormat::Rgb565,
+            color: [255, 160, 0],
+        }
+    }
+}
+
+/// Next departures as "line destination countdown" rows on an LED matrix
+pub struct LedMatrixBackend {
+    settings: LedMatrixSettings,
+    framebuffer: Framebuffer,
+}
+
+impl LedMatrixBackend {
+    pub fn open(settings: LedMatrixSettings) -> io::Result<Self> {
+        let framebuffer =
+            Framebuffer::open(&settings.path, settings.format, settings.color, 1, None)?;
+        Ok(LedMatrixBackend {
+            settings,
+            framebuffer,
+        })
+    }
+}
+
+impl OutputBackend for LedMatrixBackend {
+    /// Rows and columns of text that fit on the panel
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok((
+            (self.settings.width / (GLYPH_WIDTH + 1)) as u16,
+            (self.settings.height / LINE_HEIGHT) as u16,
+        ))
+    }
+
+    fn draw(&mut self, frame: &Frame) -> Result<()> {
+        let mut canvas = Canvas::new(self.settings.width, self.settings.height);
+        draw_departures(&mut canvas, frame, 0);
+        self.framebuffer.draw(&canvas);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.framebuffer.flush()
+    }
+}
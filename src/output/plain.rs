@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use crossterm::terminal::size;
+use std::{
+    fmt::Write as _,
+    io::{self, stdout, Write},
+};
+
+use super::{Frame, OutputBackend};
+
+/// Size assumed when stdout is no terminal, e.g. when piped into a log
+const FALLBACK_SIZE: (u16, u16) = (80, 24);
+
+/// Prints the departures as plain lines, a new block whenever they change
+#[derive(Default)]
+pub struct PlainBackend {
+    last: String,
+    pending: Option<String>,
+}
+
+impl OutputBackend for PlainBackend {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok(size().unwrap_or(FALLBACK_SIZE))
+    }
+
+    fn draw(&mut self, frame: &Frame) -> Result<()> {
+        let mut block = String::new();
+        for (dep, _) in frame
+            .departures
+            .iter()
+            .filter(|(_, state)| !state.has_left())
+            .take(frame.height.saturating_sub(2) as usize)
+        {
+            // only scheduled, there is no realtime estimate
+            let marker = if dep.time_real.is_some() { "" } else { "~" };
+            let _ = write!(
+                block,
+                "{:>3} min  {:<5} {} ({})",
+                format!("{}{}", marker, dep.countdown),
+                dep.line.display_name(),
+                dep.destination_name,
+                dep.station_name
+            );
+            let _ = match dep.delay_minutes() {
+                delay if delay < 0 => writeln!(block, " {} early", delay),
+                _ => writeln!(block),
+            };
+        }
+        for disruption in frame.disruptions {
+            let badge = if disruption.is_new { "NEW " } else { "" };
+            let _ = writeln!(block, "! {}{}", badge, disruption.info.title);
+        }
+        if let Some(attention) = frame.attention {
+            let _ = writeln!(block, "⚠ {}", attention);
+        }
+        if let Some(notice) = frame.notice {
+            let _ = writeln!(block, "!! {}", notice);
+        }
+
+        if block != self.last {
+            self.pending = Some(format!("{}\n{}", frame.now.format("%H:%M:%S"), block));
+            self.last = block;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some(block) = self.pending.take() {
+            let mut stdout = stdout();
+            writeln!(stdout, "{}", block).context("Failed to write departures to stdout")?;
+            stdout
+                .flush()
+                .context("Failed to write departures to stdout")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+/// Width of a glyph of the bitmap font, in pixels
+pub const GLYPH_WIDTH: usize = 3;
+/// Height of a glyph of the bitmap font, in pixels
+pub const GLYPH_HEIGHT: usize = 5;
+/// Pixel rows per line of text, including the gap below it
+pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+/// Monochrome pixel buffer for displays that can't show text directly
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![false; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[y * self.width + x]
+    }
+
+    /// Set a pixel, pixels outside the canvas are ignored
+    pub fn set(&mut self, x: usize, y: usize) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = true;
+        }
+    }
+
+    /// Draw `text` with its top left corner at `x`, `y`, clipped at the edges
+    ///
+    /// Returns the x coordinate after the last glyph.
+    pub fn text(&mut self, x: usize, y: usize, text: &str) -> usize {
+        self.text_scaled(x, y, text, 1)
+    }
+
+    /// Draw `text` like [`Canvas::text`], every pixel of the font `scale` pixels wide and high
+    pub fn text_scaled(&mut self, x: usize, y: usize, text: &str, scale: usize) -> usize {
+        let mut x = x;
+        for c in text.chars() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (0b100 >> col) == 0 {
+                        continue;
+                    }
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            self.set(x + col * scale + dx, y + row * scale + dy);
+                        }
+                    }
+                }
+            }
+            x += (GLYPH_WIDTH + 1) * scale;
+        }
+        x
+    }
+
+    /// Render as braille characters, each covering 2x4 pixels, one line per 4 pixel rows
+    pub fn to_braille(&self) -> String {
+        // dot bits of a braille cell, indexed by [row][column]
+        const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+        let mut output = String::new();
+        for cell_y in (0..self.height).step_by(4) {
+            if cell_y > 0 {
+                output.push('\n');
+            }
+            for cell_x in (0..self.width).step_by(2) {
+                let mut bits = 0;
+                for (dy, row) in DOTS.iter().enumerate() {
+                    for (dx, dot) in row.iter().enumerate() {
+                        if self.get(cell_x + dx, cell_y + dy) {
+                            bits |= dot;
+                        }
+                    }
+                }
+                output.push(char::from_u32(0x2800 + bits).unwrap_or(' '));
+            }
+        }
+        output
+    }
+}
+
+/// Width of `text` in pixels when drawn with [`Canvas::text`], without trailing gap
+pub fn text_width(text: &str) -> usize {
+    (text.chars().count() * (GLYPH_WIDTH + 1)).saturating_sub(1)
+}
+
+/// Rows of a glyph of the 3x5 bitmap font, most significant bit on the left
+///
+/// Lowercase letters are drawn as uppercase, characters without a glyph as blanks.
+pub fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' | 'ä' | 'Ä' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' | 'ö' | 'Ö' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' | 'ß' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' | 'ü' | 'Ü' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '-' | '–' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
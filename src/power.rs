@@ -0,0 +1,89 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Where the kernel lists batteries and chargers
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// How often the power source is checked again
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Power source to assume, selected with `--power`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PowerSource {
+    /// Detect it from the batteries in sysfs
+    #[default]
+    Auto,
+    /// Never throttle
+    Ac,
+    /// Always throttle
+    Battery,
+}
+
+/// How much less often to refresh and redraw while running on battery
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BatterySettings {
+    /// Refresh intervals are this many times as long, not throttled if 1
+    pub refresh_factor: u32,
+    /// Seconds between redraws, the clock then skips seconds
+    pub redraw: u64,
+}
+
+impl Default for BatterySettings {
+    fn default() -> Self {
+        BatterySettings {
+            refresh_factor: 3,
+            redraw: 5,
+        }
+    }
+}
+
+/// Whether the machine runs on battery, checked again now and then
+pub struct PowerMonitor {
+    source: PowerSource,
+    on_battery: bool,
+    checked: Option<Instant>,
+}
+
+impl PowerMonitor {
+    pub fn new(source: PowerSource) -> Self {
+        PowerMonitor {
+            source,
+            on_battery: false,
+            checked: None,
+        }
+    }
+
+    pub fn on_battery(&mut self) -> bool {
+        match self.source {
+            PowerSource::Ac => false,
+            PowerSource::Battery => true,
+            PowerSource::Auto => {
+                if self
+                    .checked
+                    .is_none_or(|checked| checked.elapsed() >= CHECK_INTERVAL)
+                {
+                    self.on_battery = discharging(Path::new(POWER_SUPPLY_DIR));
+                    self.checked = Some(Instant::now());
+                }
+                self.on_battery
+            }
+        }
+    }
+}
+
+/// Whether any battery in `dir` is discharging, machines without one never are
+fn discharging(dir: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let read = |name: &str| fs::read_to_string(entry.path().join(name)).unwrap_or_default();
+        read("type").trim() == "Battery" && read("status").trim() == "Discharging"
+    })
+}
@@ -0,0 +1,65 @@
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+/// Steps of getting data onto the screen that are timed with `--profile-render`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Fetch,
+    Parse,
+    Build,
+    Format,
+    Diff,
+    Flush,
+}
+
+/// Collects timings of every stage over a fixed number of frames
+pub struct RenderProfile {
+    frames: usize,
+    samples: BTreeMap<Stage, Vec<Duration>>,
+}
+
+impl RenderProfile {
+    pub fn new(frames: usize) -> Self {
+        RenderProfile {
+            frames,
+            samples: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, stage: Stage, duration: Duration) {
+        self.samples.entry(stage).or_default().push(duration);
+    }
+
+    /// Whether enough frames were drawn, `frame` counts from 1
+    pub fn is_done(&self, frame: usize) -> bool {
+        frame >= self.frames
+    }
+
+    /// Table of count, mean, median, 95th percentile and maximum per stage
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{:<8} {:>6} {:>10} {:>10} {:>10} {:>10}\n",
+            "stage", "count", "mean ms", "p50 ms", "p95 ms", "max ms"
+        );
+        for (stage, samples) in &self.samples {
+            let mut sorted = samples.clone();
+            sorted.sort();
+            let total: Duration = sorted.iter().sum();
+            let percentile = |p: usize| sorted[(sorted.len() - 1) * p / 100];
+            let _ = writeln!(
+                report,
+                "{:<8} {:>6} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+                format!("{:?}", stage).to_lowercase(),
+                sorted.len(),
+                millis(total / sorted.len() as u32),
+                millis(percentile(50)),
+                millis(percentile(95)),
+                millis(sorted[sorted.len() - 1]),
+            );
+        }
+        report
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,386 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use clap::ValueEnum;
+use iso8601_timestamp::{Duration, Timestamp};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::{
+    clean_text, demo, get_data_from_api, lines::LineOverrides, parse_api_response, retry_after,
+    scheduler::ApiResponse, timetable::TimeTravel, ApiRequestError, Departure, Line,
+    WienerLinienAPIRequest, WienerLinienVehicleType, API_URL,
+};
+
+/// Departures requested per stop from EFA and HAFAS servers
+const DEPARTURES_PER_STOP: usize = 20;
+
+/// Transit network the monitored stops belong to, selected with `--city`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum City {
+    /// Wiener Linien, stops are RBL numbers
+    #[default]
+    Vienna,
+    /// Linz AG, stops are EFA stop IDs
+    Linz,
+    /// Graz and Styria (Verbund Linie), stops are EFA stop IDs
+    Graz,
+    /// ÖBB trains and regional buses, stops are HAFAS station IDs
+    Oebb,
+}
+
+impl City {
+    pub fn provider(self) -> Provider {
+        match self {
+            City::Vienna => Provider::WienerLinien,
+            City::Linz => Provider::Efa {
+                url: "https://www.linzag.at/linz2/XML_DM_REQUEST",
+            },
+            City::Graz => Provider::Efa {
+                url: "https://verkehrsauskunft.verbundlinie.at/stv/XML_DM_REQUEST",
+            },
+            City::Oebb => Provider::Hafas {
+                url: "https://fahrplan.oebb.at/bin/stboard.exe/dn",
+            },
+        }
+    }
+}
+
+/// Realtime API departures are requested from
+///
+/// Only the Wiener Linien API reports disruptions, EFA and HAFAS servers are
+/// requested once per stop since they answer for a single stop only.
+#[derive(Debug, Clone)]
+pub enum Provider {
+    WienerLinien,
+    /// Wiener Linien compatible API at another address, e.g. a mirror to fail over to
+    Mirror {
+        url: Arc<str>,
+    },
+    /// Departure monitor (`XML_DM_REQUEST`) of an EFA server
+    Efa {
+        url: &'static str,
+    },
+    /// Station board (`stboard.exe`) of a HAFAS server
+    Hafas {
+        url: &'static str,
+    },
+    /// Made up Wiener Linien responses, selected with `--demo`
+    Demo,
+    /// Wiener Linien responses from a static timetable, as of the time set with `--at`
+    Timetable(Arc<TimeTravel>),
+}
+
+impl Provider {
+    /// Raw responses for `stops`, one per request
+    pub async fn fetch(&self, stops: &[i32]) -> Result<Vec<String>, ApiRequestError> {
+        match self {
+            Provider::WienerLinien => Ok(vec![
+                get_data_from_api(&WienerLinienAPIRequest {
+                    url: API_URL.to_string(),
+                    traffic_info: "stoerunglang".to_string(),
+                    stop_id: stops.to_vec(),
+                })
+                .await?,
+            ]),
+            Provider::Mirror { url } => Ok(vec![
+                get_data_from_api(&WienerLinienAPIRequest {
+                    url: url.to_string(),
+                    traffic_info: "stoerunglang".to_string(),
+                    stop_id: stops.to_vec(),
+                })
+                .await?,
+            ]),
+            Provider::Efa { url } => {
+                let mut responses = vec![];
+                for stop in stops {
+                    responses.push(get_text(&efa_url(url, *stop)).await?);
+                }
+                Ok(responses)
+            }
+            Provider::Hafas { url } => {
+                let mut responses = vec![];
+                for stop in stops {
+                    responses.push(get_text(&hafas_url(url, *stop)).await?);
+                }
+                Ok(responses)
+            }
+            Provider::Demo => Ok(vec![demo::response(stops)]),
+            Provider::Timetable(travel) => Ok(vec![travel
+                .timetable
+                .response(stops, travel.now().naive_local())]),
+        }
+    }
+
+    /// JSON part of a response, HAFAS wraps it in JavaScript
+    pub fn json<'a>(&self, response: &'a str) -> &'a str {
+        match self {
+            Provider::Hafas { .. } => response
+                .trim()
+                .strip_prefix(HAFAS_PREFIX)
+                .unwrap_or(response),
+            _ => response,
+        }
+    }
+
+    /// Departures and disruptions from the responses of [`Provider::fetch`],
+    /// with the number of malformed monitors that were skipped
+    pub fn parse(
+        &self,
+        responses: &[String],
+        lines: &LineOverrides,
+    ) -> Result<(ApiResponse, usize), ApiRequestError> {
+        let mut departures = vec![];
+        let mut traffic_infos = None;
+        let mut skipped = 0;
+        for response in responses {
+            match self {
+                Provider::WienerLinien
+                | Provider::Mirror { .. }
+                | Provider::Demo
+                | Provider::Timetable(_) => {
+                    let ((response_departures, response_infos), response_skipped) =
+                        parse_api_response(response, lines)?;
+                    departures.extend(response_departures);
+                    traffic_infos = response_infos;
+                    skipped += response_skipped;
+                }
+                Provider::Efa { .. } => departures.extend(parse_efa(response, lines)?),
+                Provider::Hafas { .. } => departures.extend(parse_hafas(response, lines)?),
+            }
+        }
+        departures.retain(|dep| !lines.get(&dep.line.name).is_some_and(|o| o.hide));
+        departures.sort();
+        Ok(((departures, traffic_infos), skipped))
+    }
+}
+
+async fn get_text(url: &str) -> Result<String, ApiRequestError> {
+    let res = reqwest::get(url).await?;
+    let status = res.status();
+    if matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return Err(ApiRequestError::RateLimited {
+            retry_after: retry_after(res.headers()),
+        });
+    }
+    if !status.is_success() {
+        return Err(ApiRequestError::HttpStatus(status));
+    }
+    Ok(res.text().await?)
+}
+
+fn efa_url(url: &str, stop: i32) -> String {
+    format!(
+        "{}?outputFormat=JSON&type_dm=stop&name_dm={}&mode=direct&useRealtime=1&depType=stopEvents&limit={}",
+        url, stop, DEPARTURES_PER_STOP
+    )
+}
+
+fn hafas_url(url: &str, stop: i32) -> String {
+    format!(
+        "{}?L=vs_scotty.vs_liveticker&evaId={}&boardType=dep&productsFilter=1111111111111111&start=yes&showJourneys={}&outputMode=tickerDataOnly",
+        url, stop, DEPARTURES_PER_STOP
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct EfaResponse {
+    /// Missing or null if there are no departures
+    #[serde(rename = "departureList", default)]
+    departure_list: Option<Vec<EfaDeparture>>,
+}
+
+/// EFA servers send numbers as strings
+#[derive(Debug, Deserialize)]
+struct EfaDeparture {
+    #[serde(rename = "stopID")]
+    stop_id: String,
+    #[serde(rename = "stopName")]
+    stop_name: String,
+    #[serde(default)]
+    platform: Option<String>,
+    #[serde(rename = "dateTime")]
+    date_time: EfaDateTime,
+    #[serde(rename = "realDateTime", default)]
+    real_date_time: Option<EfaDateTime>,
+    #[serde(rename = "servingLine")]
+    serving_line: EfaServingLine,
+}
+
+#[derive(Debug, Deserialize)]
+struct EfaDateTime {
+    year: String,
+    month: String,
+    day: String,
+    hour: String,
+    minute: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EfaServingLine {
+    number: String,
+    #[serde(rename = "motType")]
+    mot_type: String,
+    direction: String,
+}
+
+impl EfaDateTime {
+    fn to_local(&self) -> Option<DateTime<Local>> {
+        let date = NaiveDate::from_ymd_opt(
+            self.year.parse().ok()?,
+            self.month.parse().ok()?,
+            self.day.parse().ok()?,
+        )?;
+        let time = NaiveTime::from_hms_opt(self.hour.parse().ok()?, self.minute.parse().ok()?, 0)?;
+        Local
+            .from_local_datetime(&NaiveDateTime::new(date, time))
+            .earliest()
+    }
+}
+
+fn parse_efa(response: &str, lines: &LineOverrides) -> Result<Vec<Departure>, ApiRequestError> {
+    let response: EfaResponse = serde_json::from_str(response)?;
+    let mut departures = vec![];
+    for dep in response.departure_list.unwrap_or_default() {
+        let planned = dep
+            .date_time
+            .to_local()
+            .ok_or_else(|| ApiRequestError::MissingField("dateTime".to_string()))?;
+        let real = dep.real_date_time.as_ref().and_then(EfaDateTime::to_local);
+        // EFA means of transport: trains, subway, light rail and tram, then buses and others
+        let vehicle_type = match dep.serving_line.mot_type.as_str() {
+            "0" | "1" | "13" | "14" | "15" | "16" | "17" | "18" => WienerLinienVehicleType::Train,
+            "2" => WienerLinienVehicleType::Metro,
+            "3" | "4" => WienerLinienVehicleType::Tram,
+            _ => WienerLinienVehicleType::CityBus,
+        };
+        departures.push(departure(
+            Line::new(&dep.serving_line.number, vehicle_type, lines),
+            planned,
+            real,
+            &dep.stop_name,
+            dep.stop_id.parse().ok(),
+            dep.platform,
+            &dep.serving_line.direction,
+        ));
+    }
+    Ok(departures)
+}
+
+/// Assigned to a variable, the liveticker responds with JavaScript rather than JSON
+const HAFAS_PREFIX: &str = "journeysObj = ";
+
+#[derive(Debug, Deserialize)]
+struct HafasResponse {
+    #[serde(rename = "stationName")]
+    station_name: String,
+    #[serde(rename = "stationEvaId", default)]
+    station_id: Option<String>,
+    #[serde(default)]
+    journey: Vec<HafasJourney>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HafasJourney {
+    /// Product, e.g. "REX 7" or "Bus 200"
+    pr: String,
+    /// Destination
+    st: String,
+    /// Planned departure, "HH:MM"
+    ti: String,
+    /// Date of the planned departure, "DD.MM.YYYY"
+    da: String,
+    /// Track
+    #[serde(default)]
+    tr: Option<String>,
+    /// Realtime data, `false` if there is none
+    #[serde(default)]
+    rt: Value,
+}
+
+fn parse_hafas(response: &str, lines: &LineOverrides) -> Result<Vec<Departure>, ApiRequestError> {
+    let json = response
+        .trim()
+        .strip_prefix(HAFAS_PREFIX)
+        .ok_or_else(|| ApiRequestError::MissingField("journeysObj".to_string()))?;
+    let response: HafasResponse = serde_json::from_str(json)?;
+    let mut departures = vec![];
+    for journey in &response.journey {
+        let planned = NaiveDate::parse_from_str(&journey.da, "%d.%m.%Y")
+            .ok()
+            .zip(NaiveTime::parse_from_str(&journey.ti, "%H:%M").ok())
+            .and_then(|(date, time)| {
+                Local
+                    .from_local_datetime(&NaiveDateTime::new(date, time))
+                    .earliest()
+            })
+            .ok_or_else(|| ApiRequestError::MissingField("ti".to_string()))?;
+        let real = journey.rt["dlt"]
+            .as_str()
+            .and_then(|time| NaiveTime::parse_from_str(time, "%H:%M").ok())
+            .and_then(|time| {
+                let mut real = planned.date_naive().and_time(time);
+                // delayed past midnight
+                if real < planned.naive_local() - chrono::Duration::hours(12) {
+                    real += chrono::Duration::days(1);
+                }
+                Local.from_local_datetime(&real).earliest()
+            });
+        let vehicle_type = match journey.pr.split_whitespace().next() {
+            Some("Bus" | "AST") => WienerLinienVehicleType::CityBus,
+            Some("Tram" | "STR") => WienerLinienVehicleType::Tram,
+            Some("U") => WienerLinienVehicleType::Metro,
+            _ => WienerLinienVehicleType::Train,
+        };
+        departures.push(departure(
+            Line::new(&journey.pr, vehicle_type, lines),
+            planned,
+            real,
+            &response.station_name,
+            response
+                .station_id
+                .as_deref()
+                .and_then(|id| id.parse().ok()),
+            journey.tr.clone(),
+            &journey.st,
+        ));
+    }
+    Ok(departures)
+}
+
+fn departure(
+    line: Line,
+    planned: DateTime<Local>,
+    real: Option<DateTime<Local>>,
+    station: &str,
+    stop_id: Option<i32>,
+    platform: Option<String>,
+    destination: &str,
+) -> Departure {
+    let timestamp = |time: DateTime<Local>| {
+        Timestamp::UNIX_EPOCH.saturating_add(Duration::milliseconds(time.timestamp_millis()))
+    };
+    Departure {
+        line,
+        time_planned: timestamp(planned),
+        time_real: real.map(timestamp),
+        countdown: (real.unwrap_or(planned) - Local::now())
+            .num_minutes()
+            .max(0),
+        destination_name: clean_text(destination),
+        station_name: clean_text(station),
+        stop_id,
+        platform: platform
+            .as_deref()
+            .map(clean_text)
+            .filter(|platform| !platform.is_empty()),
+        occupancy: None,
+        traffic_jam: false,
+        note: None,
+        countdowns: None,
+    }
+}
@@ -0,0 +1,58 @@
+use qrcode::{types::Version, Color, EcLevel};
+
+/// Light modules around the code, less than the standard four but enough for phone cameras
+const QUIET_ZONE: i32 = 2;
+
+/// Largest version drawn, 57 modules across, anything larger wouldn't fit next to the board
+const MAX_VERSION: i16 = 10;
+
+/// QR code of a short text with low error correction
+pub struct QrCode(qrcode::QrCode);
+
+impl QrCode {
+    /// The smallest code holding `text`, `None` if it needs more than version 10
+    pub fn encode(text: &str) -> Option<Self> {
+        qrcode::QrCode::with_error_correction_level(text, EcLevel::L)
+            .ok()
+            .filter(
+                |code| matches!(code.version(), Version::Normal(version) if version <= MAX_VERSION),
+            )
+            .map(QrCode)
+    }
+
+    /// Drawn with half blocks, two rows of modules per line, in black on white
+    /// so it scans whatever the colors of the terminal are
+    pub fn lines(&self) -> Vec<String> {
+        let end = self.size() + QUIET_ZONE;
+        (-QUIET_ZONE..end)
+            .step_by(2)
+            .map(|y| {
+                let row: String = (-QUIET_ZONE..end)
+                    .map(|x| match (self.is_dark(x, y), self.is_dark(x, y + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    })
+                    .collect();
+                format!("\x1b[30;107m{}\x1b[0m", row)
+            })
+            .collect()
+    }
+
+    /// Width of the drawn code in characters
+    pub fn width(&self) -> u16 {
+        (self.size() + 2 * QUIET_ZONE) as u16
+    }
+
+    fn size(&self) -> i32 {
+        self.0.width() as i32
+    }
+
+    fn is_dark(&self, x: i32, y: i32) -> bool {
+        let size = self.size();
+        (0..size).contains(&x)
+            && (0..size).contains(&y)
+            && self.0[(x as usize, y as usize)] == Color::Dark
+    }
+}
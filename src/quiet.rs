@@ -0,0 +1,57 @@
+use chrono::{DateTime, Datelike, Local, NaiveTime, Weekday};
+use crossterm::style::{Attribute, Stylize};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    filter::{time_between, time_of_day},
+    theme::Theme,
+};
+
+/// Hours the monitor stops requesting departures and only shows a dim clock,
+/// e.g. 01:00 to 04:30 on weekdays
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QuietHours {
+    /// Start as "HH:MM"
+    #[serde(with = "time_of_day")]
+    pub from: Option<NaiveTime>,
+    /// End as "HH:MM", before `from` if the quiet hours span midnight
+    #[serde(with = "time_of_day")]
+    pub until: Option<NaiveTime>,
+    /// Days the quiet hours start on, e.g. "Mon", every day if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+}
+
+impl QuietHours {
+    /// Whether `now` falls into these quiet hours
+    pub fn contains(&self, now: &DateTime<Local>) -> bool {
+        let time = now.time();
+        if !time_between(time, self.from, self.until) {
+            return false;
+        }
+        // after midnight, the quiet hours started the day before
+        let started = match (self.from, self.until) {
+            (Some(from), Some(until)) if from > until && time < until => now.weekday().pred(),
+            _ => now.weekday(),
+        };
+        self.days.is_empty() || self.days.contains(&started)
+    }
+}
+
+/// Whether `now` falls into any of the quiet hours
+pub fn is_quiet(quiet_hours: &[QuietHours], now: &DateTime<Local>) -> bool {
+    quiet_hours.iter().any(|quiet| quiet.contains(now))
+}
+
+/// The screen shown during quiet hours, a dim clock in the middle
+pub fn screen(now: &DateTime<Local>, width: u16, height: u16, theme: &Theme) -> String {
+    let time = now.format("%H:%M").to_string();
+    let padding = (width as usize).saturating_sub(time.len()) / 2;
+    let mut clock = time.attribute(Attribute::Dim);
+    if let Some(color) = theme.quiet() {
+        clock = clock.with(color);
+    }
+    let mut lines = vec![String::new(); height as usize / 2];
+    lines.push(format!("{}{}", " ".repeat(padding), clock));
+    lines.join("\n")
+}
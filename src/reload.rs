@@ -0,0 +1,96 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    ffi::OsString,
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::signal::unix::{signal, SignalKind};
+
+/// Notices when the config should be reloaded, on SIGHUP or when the config file
+/// or the stops file changes
+pub struct ReloadTrigger {
+    requested: Arc<AtomicBool>,
+    _watchers: Vec<RecommendedWatcher>,
+}
+
+impl ReloadTrigger {
+    /// `config_path` is the file given with `--config`; without one, any
+    /// `<default_name>.*` file in the working directory is watched
+    pub fn new(
+        config_path: Option<&Path>,
+        default_name: &str,
+        stops_file: Option<&Path>,
+    ) -> io::Result<Self> {
+        let requested = Arc::new(AtomicBool::new(false));
+
+        let mut hangup = signal(SignalKind::hangup())?;
+        let flag = requested.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                flag.store(true, Ordering::Relaxed);
+            }
+        });
+
+        // watching is best effort, SIGHUP keeps working without it
+        let default_name = OsString::from(default_name);
+        let config = match config_path {
+            Some(path) => watch(path, requested.clone()),
+            None => watch_directory(Path::new("."), requested.clone(), move |path| {
+                path.file_stem() == Some(&default_name)
+            }),
+        };
+        let stops = stops_file.map(|path| watch(path, requested.clone()));
+        let watchers = [Some(config), stops]
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(ReloadTrigger {
+            requested,
+            _watchers: watchers,
+        })
+    }
+
+    /// Whether a reload was requested since the last call
+    pub fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Watch the directory of `path`, since editors often replace the file instead of writing it
+fn watch(path: &Path, requested: Arc<AtomicBool>) -> notify::Result<RecommendedWatcher> {
+    let directory = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = path.file_name().map(OsString::from);
+    watch_directory(directory, requested, move |changed| {
+        changed.file_name() == file_name.as_deref()
+    })
+}
+
+/// Request a reload when a file in `directory` matching `is_watched` changes
+fn watch_directory(
+    directory: &Path,
+    requested: Arc<AtomicBool>,
+    is_watched: impl Fn(&Path) -> bool + Send + 'static,
+) -> notify::Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        if event.paths.iter().any(|path| is_watched(path)) {
+            requested.store(true, Ordering::Relaxed);
+        }
+    })?;
+    watcher.watch(directory, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
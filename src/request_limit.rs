@@ -0,0 +1,67 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+/// How long a learned limit is trusted, the API may accept more stops again afterwards
+const RELEARN: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Most stops the API accepted in one request, learned from refused requests
+///
+/// A request of several stops is refused, but each of its stops on its own is
+/// answered: the request had too many stops, so half as many are requested at
+/// once from then on. The limit is kept in a file in the cache directory, so
+/// restarts don't have to learn it again, and forgotten after a day, as the
+/// API's limit changes every now and then.
+#[derive(Debug)]
+pub struct RequestLimit {
+    /// File the limit is kept in, only in memory without a cache directory
+    path: Option<PathBuf>,
+    learned: Option<(usize, SystemTime)>,
+}
+
+impl RequestLimit {
+    /// The limit kept for the provider called `source`, in `dir` or the user's cache directory
+    pub fn load(dir: Option<PathBuf>, source: &str) -> Self {
+        let path = dir
+            .or_else(cache_dir)
+            .map(|dir| dir.join(format!("{}.limit", source)));
+        let learned = path.as_ref().and_then(|path| {
+            let written = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+            let limit = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+            Some((limit, written))
+        });
+        RequestLimit { path, learned }
+    }
+
+    /// Stops per request, `None` unless a limit was learned within the last day
+    pub fn get(&self) -> Option<usize> {
+        self.learned
+            .filter(|(_, learned)| learned.elapsed().is_ok_and(|age| age < RELEARN))
+            .map(|(limit, _)| limit)
+    }
+
+    /// Remember that a request of `refused` stops was too many, returning the new limit
+    pub fn learn(&mut self, refused: usize) -> usize {
+        let limit = (refused / 2).max(1);
+        self.learned = Some((limit, SystemTime::now()));
+        if let Some(path) = &self.path {
+            // learned again after a restart if it can't be kept
+            let _ = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(path, limit.to_string()));
+        }
+        limit
+    }
+}
+
+/// `$XDG_CACHE_HOME/oeffimonitor-cli`, or `~/.cache/oeffimonitor-cli`
+fn cache_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+}
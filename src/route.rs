@@ -0,0 +1,134 @@
+use chrono::NaiveTime;
+use comfy_table::{ContentArrangement, Row, Table};
+use serde::Deserialize;
+
+use crate::{table_style::TableStyle, ApiRequestError};
+
+/// Trip planner (EFA) of Wiener Linien, answering in JSON with `outputFormat=JSON`
+const ROUTING_URL: &str = "http://www.wienerlinien.at/ogd_routing/XML_TRIP_REQUEST2";
+
+#[derive(Debug, Deserialize)]
+struct TripResponse {
+    #[serde(default)]
+    trips: Vec<Trip>,
+}
+
+/// One itinerary from origin to destination
+#[derive(Debug, Deserialize)]
+pub struct Trip {
+    legs: Vec<TripLeg>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TripLeg {
+    points: Vec<TripPoint>,
+    mode: TripMode,
+}
+
+#[derive(Debug, Deserialize)]
+struct TripPoint {
+    name: String,
+    #[serde(rename = "dateTime")]
+    date_time: TripTime,
+}
+
+#[derive(Debug, Deserialize)]
+struct TripTime {
+    time: String,
+    /// Realtime estimate, only set if realtime data is available
+    #[serde(rename = "rtTime")]
+    rt_time: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TripMode {
+    /// Line name like "U2", empty for walking
+    #[serde(default)]
+    number: String,
+    /// Long name like "U-Bahn U2" or "Fussweg"
+    name: String,
+}
+
+/// Request the next `count` itineraries from `from` to `to`, both stop names or addresses
+pub async fn plan(from: &str, to: &str, count: usize) -> Result<Vec<Trip>, ApiRequestError> {
+    let res = reqwest::Client::new()
+        .get(ROUTING_URL)
+        .query(&[
+            ("outputFormat", "JSON"),
+            ("type_origin", "any"),
+            ("name_origin", from),
+            ("type_destination", "any"),
+            ("name_destination", to),
+            ("itdTripDateTimeDepArr", "dep"),
+            ("calcNumberOfTrips", &count.to_string()),
+        ])
+        .send()
+        .await?;
+    let status = res.status();
+    if !status.is_success() {
+        return Err(ApiRequestError::HttpStatus(status));
+    }
+    let response: TripResponse = serde_json::from_str(&res.text().await?)?;
+    Ok(response.trips.into_iter().take(count).collect())
+}
+
+impl TripPoint {
+    /// "HH:MM", followed by the delay if the realtime estimate differs
+    fn time_label(&self) -> String {
+        let time = &self.date_time;
+        let parse = |time: &str| NaiveTime::parse_from_str(time, "%H:%M").ok();
+        let delay = time
+            .rt_time
+            .as_deref()
+            .and_then(parse)
+            .zip(parse(&time.time))
+            .map_or(0, |(real, planned)| (real - planned).num_minutes());
+        match delay {
+            0 => time.time.clone(),
+            delay => format!(
+                "{} ({:+})",
+                time.rt_time.as_deref().unwrap_or_default(),
+                delay
+            ),
+        }
+    }
+}
+
+/// Names of the columns of the itinerary table
+const COLUMNS: [&str; 5] = ["Departure", "From", "Arrival", "To", "Route"];
+
+/// Itineraries as a table like the departure board, one row per trip
+pub fn itinerary_table(trips: &[Trip], style: &TableStyle, width: u16) -> Table {
+    let mut table = Table::new();
+    style.apply(&mut table);
+    table
+        .set_width(width)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(COLUMNS);
+    style.align(&mut table, &COLUMNS);
+
+    for trip in trips {
+        let (Some(first), Some(last)) = (
+            trip.legs.first().and_then(|leg| leg.points.first()),
+            trip.legs.last().and_then(|leg| leg.points.last()),
+        ) else {
+            continue;
+        };
+        let route: Vec<&str> = trip
+            .legs
+            .iter()
+            .map(|leg| match leg.mode.number.as_str() {
+                "" => leg.mode.name.as_str(),
+                number => number,
+            })
+            .collect();
+        table.add_row(Row::from(vec![
+            first.time_label(),
+            first.name.clone(),
+            last.time_label(),
+            last.name.clone(),
+            route.join(" → "),
+        ]));
+    }
+    table
+}
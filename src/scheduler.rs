@@ -0,0 +1,1098 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::task::JoinHandle;
+
+use crate::{
+    countdown::CountdownSettings,
+    daemon,
+    departures::{self, DepartureSet},
+    destinations::DestinationNames,
+    disruptions::DisruptionSet,
+    lines::LineOverrides,
+    provider::Provider,
+    request_limit::RequestLimit,
+    script::RowScript,
+    shared_cache::SharedCache,
+    ApiRequestError, Departure, WienerLinienTrafficInfo,
+};
+
+pub type ApiResponse = (Vec<Departure>, Option<Vec<WienerLinienTrafficInfo>>);
+
+type RequestResult = Result<Fetched, ApiRequestError>;
+
+/// Longest wait between failed requests
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// First wait before a stop failing on its own is requested again, doubling up to [`MAX_BACKOFF`]
+const ISOLATED_RETRY: Duration = Duration::from_secs(60);
+
+/// How long departures that vanished from the API data are kept around at least
+const REMOVED_RETENTION: Duration = Duration::from_secs(120);
+
+/// Longest a request may take before it counts as failed, so a hung one can't
+/// keep its batch loading for good
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time until the wall clock is `phase` into the next `interval`, counted from the Unix epoch
+///
+/// Intervals dividing a minute or an hour thereby start on the full minute or hour.
+fn until_phase(interval: Duration, phase: Duration) -> Duration {
+    let interval = interval.as_millis().max(1);
+    let since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let into = (since_epoch + interval - phase.as_millis() % interval) % interval;
+    Duration::from_millis((interval - into) as u64)
+}
+
+/// Requests more often while the next departure is close, and less often while it is far out
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdaptiveRefresh {
+    /// Minutes within which the next departure counts as close
+    pub near: i64,
+    /// Seconds between requests while the next departure is close
+    pub near_refresh: u64,
+    /// Minutes beyond which the next departure counts as far out
+    pub far: i64,
+    /// Seconds between requests while the next departure is far out, or there is none
+    pub far_refresh: u64,
+}
+
+impl Default for AdaptiveRefresh {
+    fn default() -> Self {
+        AdaptiveRefresh {
+            near: 5,
+            near_refresh: 5,
+            far: 30,
+            far_refresh: 180,
+        }
+    }
+}
+
+impl AdaptiveRefresh {
+    /// Interval until the next request, given the countdown of the next departure
+    fn interval(&self, next_departure: Option<i64>, interval: Duration) -> Duration {
+        match next_departure {
+            Some(countdown) if countdown < self.near => {
+                interval.min(Duration::from_secs(self.near_refresh))
+            }
+            Some(countdown) if countdown <= self.far => interval,
+            _ => interval.max(Duration::from_secs(self.far_refresh)),
+        }
+    }
+}
+
+/// Splits the stops into several requests that run concurrently
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Batching {
+    /// Stops per request, all stops in one request if 0
+    pub size: usize,
+    /// Seconds after which a batch that is still loading counts as stale
+    pub deadline: u64,
+    /// Learn how many stops the API accepts per request and make batches no larger
+    pub learn_limit: bool,
+}
+
+impl Default for Batching {
+    fn default() -> Self {
+        Batching {
+            size: 0,
+            deadline: 5,
+            learn_limit: true,
+        }
+    }
+}
+
+/// Switches to a secondary provider while the city's keeps failing, and back once it answers again
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Failover {
+    /// Failed requests in a row after which the secondary provider is used
+    pub after: u32,
+    /// Wiener Linien compatible API to use instead, the static `timetable` if unset
+    pub mirror: Option<String>,
+}
+
+impl Default for Failover {
+    fn default() -> Self {
+        Failover {
+            after: 3,
+            mirror: None,
+        }
+    }
+}
+
+/// Data of a finished request
+struct Fetched {
+    data: ApiResponse,
+    /// Monitors that could not be read
+    skipped: usize,
+    timings: RequestTimings,
+    /// Why the provider failed, if the data is from the failover provider instead
+    degraded: Option<ApiRequestError>,
+}
+
+/// How long the last request of a job spent on the network and on parsing
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimings {
+    pub fetch: Duration,
+    pub parse: Duration,
+}
+
+/// Refreshes the data of every job, a set of stops, on its own interval
+///
+/// Requests run as background tasks, so a slow refresh of one job never
+/// holds up drawing or refreshing the others. With batching, the stops of a
+/// job are requested in several concurrent batches and the board shows
+/// whatever arrived, a slow batch keeps its previous data and is marked stale.
+///
+/// A batch the API refuses is split into one batch per stop, so a single
+/// broken stop can't take the others down with it. A stop that keeps failing
+/// on its own is isolated: it is retried on a slower schedule of its own
+/// while the rest of its job refreshes as usual.
+pub struct Scheduler {
+    provider: Provider,
+    /// Stops per batch as configured, all stops of a job in one batch if `None`
+    batch_size: Option<usize>,
+    /// Stops per request the API accepted, batches are made no larger
+    request_limit: Option<RequestLimit>,
+    lines: Arc<LineOverrides>,
+    deadline: Duration,
+    dedup_tolerance: Duration,
+    /// How long departures that vanished from the API data are kept around
+    retention: Duration,
+    /// Requests are made at this offset into the interval, counted from the full minute
+    /// or hour, instead of an interval after the previous one
+    phase: Option<Duration>,
+    adaptive: Option<AdaptiveRefresh>,
+    /// Intervals are this many times as long, e.g. on battery
+    throttle: u32,
+    /// Countdown shown, the API's or one computed from the departure time
+    countdown: CountdownSettings,
+    /// Cleans up destination names right after they were parsed
+    destinations: Option<Arc<DestinationNames>>,
+    /// Changes departures right after they were parsed
+    script: Option<Arc<RowScript>>,
+    /// Responses shared with other monitors on this machine
+    shared_cache: Option<SharedCache>,
+    /// Provider requested once this many requests in a row failed
+    failover: Option<(Provider, u32)>,
+    /// Socket of the daemon requests go through
+    daemon: Option<PathBuf>,
+    /// Requests that succeeded and failed since [`Scheduler::take_request_counts`]
+    request_counts: (u64, u64),
+    jobs: Vec<Job>,
+}
+
+struct Job {
+    stops: Vec<i32>,
+    interval: Duration,
+    next_run: Instant,
+    batches: Vec<Batch>,
+    /// Data of all batches merged
+    data: Option<ApiResponse>,
+    departures: DepartureSet,
+    disruptions: DisruptionSet,
+    timings: Option<RequestTimings>,
+    /// Requests that failed in a row
+    failures: u32,
+    /// Size and stops of refused batches split into one batch per stop, until
+    /// it is clear whether a stop or the size was the problem
+    splits: Vec<(usize, Vec<i32>)>,
+    /// Refreshes started so far, numbering the batches requested together
+    sequence: u64,
+}
+
+struct Batch {
+    stops: Vec<i32>,
+    task: Option<JoinHandle<RequestResult>>,
+    /// Refresh the running or last request belongs to
+    sequence: u64,
+    /// Refresh the data is from
+    shown: u64,
+    /// Result of a request held back until the other batches of its refresh answered
+    held: Option<RequestResult>,
+    started: Instant,
+    data: Option<ApiResponse>,
+    /// Monitors of the latest response that could not be read
+    skipped: usize,
+    error: Option<ApiRequestError>,
+    /// Why the provider failed, while the data is from the failover provider
+    degraded: Option<ApiRequestError>,
+    /// Requested at this time instead of with its job, while the batch was just
+    /// split off or is isolated
+    next_run: Option<Instant>,
+    /// Requests that failed in a row while the batch is isolated
+    failures: u32,
+}
+
+impl Batch {
+    fn new(stops: Vec<i32>, now: Instant) -> Self {
+        Batch {
+            stops,
+            task: None,
+            sequence: 0,
+            shown: 0,
+            held: None,
+            started: now,
+            data: None,
+            skipped: 0,
+            error: None,
+            degraded: None,
+            next_run: None,
+            failures: 0,
+        }
+    }
+
+    /// Whether the batch is a single stop failing on its own, retried on its own schedule
+    fn is_isolated(&self) -> bool {
+        self.next_run.is_some() && self.error.is_some()
+    }
+}
+
+impl Scheduler {
+    /// A scheduler with one job per entry of `jobs`, its stops and the interval they are requested at
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        provider: Provider,
+        lines: LineOverrides,
+        batching: &Batching,
+        dedup_tolerance: Duration,
+        keep_departed: Duration,
+        phase: Option<Duration>,
+        adaptive: Option<AdaptiveRefresh>,
+        jobs: impl IntoIterator<Item = (Vec<i32>, Duration)>,
+    ) -> Self {
+        let now = Instant::now();
+        let batch_size = (batching.size > 0).then_some(batching.size);
+        Scheduler {
+            provider,
+            batch_size,
+            request_limit: None,
+            lines: Arc::new(lines),
+            deadline: Duration::from_secs(batching.deadline),
+            dedup_tolerance,
+            retention: REMOVED_RETENTION.max(keep_departed),
+            phase,
+            adaptive,
+            throttle: 1,
+            countdown: CountdownSettings::default(),
+            destinations: None,
+            script: None,
+            shared_cache: None,
+            failover: None,
+            daemon: None,
+            request_counts: (0, 0),
+            jobs: jobs
+                .into_iter()
+                .map(|(stops, interval)| Job {
+                    interval,
+                    next_run: now,
+                    batches: stops
+                        .chunks(batch_size.unwrap_or(stops.len()).max(1))
+                        .map(|stops| Batch::new(stops.to_vec(), now))
+                        .collect(),
+                    stops,
+                    data: None,
+                    departures: DepartureSet::default(),
+                    disruptions: DisruptionSet::default(),
+                    timings: None,
+                    failures: 0,
+                    splits: vec![],
+                    sequence: 0,
+                })
+                .collect(),
+        }
+    }
+
+    /// Switch to a new provider, line overrides and jobs, requesting all jobs again right away
+    ///
+    /// Data of a previous job with the same stops is kept until the new one
+    /// arrived, so the board stays filled in the meantime.
+    #[allow(clippy::too_many_arguments)]
+    pub fn reconfigure(
+        &mut self,
+        provider: Provider,
+        lines: LineOverrides,
+        batching: &Batching,
+        dedup_tolerance: Duration,
+        keep_departed: Duration,
+        phase: Option<Duration>,
+        adaptive: Option<AdaptiveRefresh>,
+        jobs: impl IntoIterator<Item = (Vec<i32>, Duration)>,
+    ) {
+        let mut old_jobs = std::mem::take(&mut self.jobs);
+        let request_counts = self.request_counts;
+        *self = Scheduler::new(
+            provider,
+            lines,
+            batching,
+            dedup_tolerance,
+            keep_departed,
+            phase,
+            adaptive,
+            jobs,
+        );
+        self.request_counts = request_counts;
+        for job in self.jobs.iter_mut() {
+            if let Some(position) = old_jobs.iter().position(|old| old.stops == job.stops) {
+                let old_job = old_jobs.swap_remove(position);
+                old_job.abort();
+                job.data = old_job.data;
+                job.departures = old_job.departures;
+                job.disruptions = old_job.disruptions;
+            }
+        }
+        old_jobs.iter().for_each(Job::abort);
+    }
+
+    /// Cancel all requests still running, e.g. before exiting
+    pub fn abort(&mut self) {
+        for job in &mut self.jobs {
+            job.abort();
+        }
+    }
+
+    /// Request all jobs right away, e.g. when asked to with a key
+    pub fn refresh_now(&mut self) {
+        let now = Instant::now();
+        for job in &mut self.jobs {
+            job.next_run = now;
+        }
+    }
+
+    /// Requests that succeeded and failed since the last call
+    pub fn take_request_counts(&mut self) -> (u64, u64) {
+        std::mem::take(&mut self.request_counts)
+    }
+
+    /// Make the intervals `factor` times as long from the next request on, 1 for as configured
+    pub fn throttle(&mut self, factor: u32) {
+        self.throttle = factor.max(1);
+    }
+
+    /// Count down departures as `countdown` says from the next request on
+    pub fn set_countdown(&mut self, countdown: CountdownSettings) {
+        self.countdown = countdown;
+    }
+
+    /// Clean up destination names with `names` from the next request on, or show them as reported
+    pub fn set_destinations(&mut self, names: Option<Arc<DestinationNames>>) {
+        self.destinations = names;
+    }
+
+    /// Run `script` on the departures of every request from the next one on
+    pub fn set_script(&mut self, script: Option<Arc<RowScript>>) {
+        self.script = script;
+    }
+
+    /// Share responses through `cache` from the next request on, or stop sharing them
+    pub fn set_shared_cache(&mut self, cache: Option<SharedCache>) {
+        self.shared_cache = cache;
+    }
+
+    /// Request `provider` instead once `after` requests in a row failed, from the
+    /// next request on, until the configured provider answers again
+    pub fn set_failover(&mut self, failover: Option<(Provider, u32)>) {
+        self.failover = failover;
+    }
+
+    /// Request through the daemon listening at `socket` from the next request on, or directly
+    pub fn set_daemon(&mut self, socket: Option<PathBuf>) {
+        self.daemon = socket;
+    }
+
+    /// Make batches no larger than the stops per request the API accepts, learning
+    /// the limit from refused requests, or leave batches as configured
+    pub fn set_request_limit(&mut self, limit: Option<RequestLimit>) {
+        self.request_limit = limit;
+        self.rebatch(false);
+    }
+
+    /// Split the stops of every job into batches of the configured size, or of
+    /// the learned limit if it is smaller
+    ///
+    /// Unless `all`, only jobs with a batch larger than that are split anew.
+    /// Jobs split anew are requested right away.
+    fn rebatch(&mut self, all: bool) {
+        let limit = self.request_limit.as_ref().and_then(RequestLimit::get);
+        let now = Instant::now();
+        for job in &mut self.jobs {
+            let size = match (self.batch_size, limit) {
+                (Some(size), Some(limit)) => size.min(limit),
+                (size, limit) => size.or(limit).unwrap_or(job.stops.len()).max(1),
+            };
+            if !all && job.batches.iter().all(|batch| batch.stops.len() <= size) {
+                continue;
+            }
+            job.abort();
+            job.batches = job
+                .stops
+                .chunks(size)
+                .enumerate()
+                .map(|(n, stops)| Batch {
+                    data: job.data.as_ref().map(|data| data_of(data, stops, n == 0)),
+                    ..Batch::new(stops.to_vec(), now)
+                })
+                .collect();
+            job.splits.clear();
+            job.next_run = now;
+        }
+    }
+
+    /// Collect finished requests and start the ones that are due
+    ///
+    /// Returns the indices of the jobs that got new data. A failed request keeps
+    /// the previous data and is reported by [`Scheduler::error`] until one succeeds.
+    ///
+    /// Batches requested together are taken over together, once the last of them
+    /// answered, so the board never shows some stops as of one refresh and
+    /// others as of the next while their requests come back one by one. Once
+    /// the deadline passed, those that answered are taken over without the
+    /// ones still loading, which are marked stale until they answer.
+    pub async fn poll(&mut self) -> Vec<usize> {
+        let now = Instant::now();
+        let mut updated = vec![];
+        let mut relearned = false;
+        for (index, job) in self.jobs.iter_mut().enumerate() {
+            let mut changed = false;
+            let mut failed = None;
+            let mut split = vec![];
+            let alone = job.batches.len() == 1;
+            for batch in job.batches.iter_mut() {
+                if let Some(task) = batch.task.take_if(|task| task.is_finished()) {
+                    // a task that panicked is a failed request like any other
+                    let result = task.await.unwrap_or_else(|e| Err(e.into()));
+                    match result {
+                        Ok(_) => self.request_counts.0 += 1,
+                        Err(_) => self.request_counts.1 += 1,
+                    }
+                    // older than the data shown, which only a refresh started later can be
+                    if batch.sequence > batch.shown {
+                        batch.held = Some(result);
+                    }
+                }
+            }
+            for position in released(&job.batches, now, self.deadline) {
+                let batch = &mut job.batches[position];
+                if let Some(result) = batch.held.take() {
+                    batch.shown = batch.sequence;
+                    match result {
+                        Ok(fetched) => {
+                            batch.data = Some(fetched.data);
+                            batch.skipped = fetched.skipped;
+                            batch.error = None;
+                            batch.degraded = fetched.degraded;
+                            batch.next_run = None;
+                            batch.failures = 0;
+                            job.timings = Some(fetched.timings);
+                            changed = true;
+                        }
+                        Err(e) if e.is_stop_specific() && batch.stops.len() > 1 => {
+                            split.push(position);
+                        }
+                        Err(e) if e.is_stop_specific() && !alone => {
+                            batch.failures += 1;
+                            batch.next_run = Some(
+                                now + ISOLATED_RETRY
+                                    .saturating_mul(2u32.saturating_pow(batch.failures - 1))
+                                    .min(MAX_BACKOFF),
+                            );
+                            batch.error = Some(e);
+                        }
+                        Err(e) => {
+                            // without a duration to wait, the exponential backoff alone applies
+                            failed = match e {
+                                ApiRequestError::RateLimited { retry_after } => {
+                                    retry_after.or(Some(Duration::ZERO))
+                                }
+                                _ => Some(Duration::ZERO),
+                            }
+                            .max(failed);
+                            batch.next_run = None;
+                            batch.error = Some(e);
+                        }
+                    }
+                }
+            }
+            // the stops of a refused batch are requested again right away, one by one
+            for position in split.into_iter().rev() {
+                let batch = job.batches.remove(position);
+                let singles: Vec<Batch> = batch
+                    .stops
+                    .iter()
+                    .enumerate()
+                    .map(|(n, stop)| Batch {
+                        // the previous data stays until the stop's own arrives
+                        data: batch
+                            .data
+                            .as_ref()
+                            .map(|data| data_of(data, &[*stop], n == 0)),
+                        next_run: Some(now),
+                        ..Batch::new(vec![*stop], now)
+                    })
+                    .collect();
+                job.batches.splice(position..position, singles);
+                job.splits.push((batch.stops.len(), batch.stops));
+            }
+            // every stop of a refused batch answering on its own means the batch was too large
+            if let Some(limit) = self.request_limit.as_mut() {
+                let batches = &job.batches;
+                let answered = |stops: &[i32]| {
+                    batches
+                        .iter()
+                        .filter(|batch| batch.stops.len() == 1 && stops.contains(&batch.stops[0]))
+                        .all(|batch| batch.next_run.is_none() && batch.error.is_none())
+                };
+                if let Some((size, _)) = job.splits.iter().find(|(_, stops)| answered(stops)) {
+                    limit.learn(*size);
+                    relearned = true;
+                }
+                job.splits.retain(|(_, stops)| {
+                    !batches
+                        .iter()
+                        .filter(|batch| batch.stops.len() == 1 && batch.is_isolated())
+                        .any(|batch| stops.contains(&batch.stops[0]))
+                });
+            }
+
+            if let Some(retry_after) = failed {
+                // back off exponentially, or as long as a rate limit asks for
+                job.failures += 1;
+                let backoff = job
+                    .interval
+                    .saturating_mul(2u32.saturating_pow(job.failures))
+                    .min(MAX_BACKOFF);
+                job.next_run = now + retry_after.max(backoff);
+            } else if changed
+                && job
+                    .batches
+                    .iter()
+                    .all(|batch| batch.error.is_none() || batch.is_isolated())
+            {
+                // failing over keeps the interval, but the provider still counts as failing
+                job.failures = match job.batches.iter().any(|batch| batch.degraded.is_some()) {
+                    true => job.failures.saturating_add(1),
+                    false => 0,
+                };
+            }
+
+            if changed {
+                let data = job.merged_data(self.dedup_tolerance);
+                job.departures.apply(&data.0);
+                job.disruptions.apply(data.1.as_deref().unwrap_or_default());
+                if let Some(before) = now.checked_sub(self.retention) {
+                    job.departures.prune(before);
+                }
+                job.data = Some(data);
+                updated.push(index);
+            }
+
+            let job_due = job.next_run <= now;
+            let batch_due = |batch: &Batch| {
+                // a batch still loading from the last refresh is not requested twice,
+                // nor one waiting for the rest of its refresh
+                batch.task.is_none()
+                    && batch.held.is_none()
+                    && batch.next_run.map_or(job_due, |next_run| next_run <= now)
+            };
+            if job.batches.iter().any(batch_due) {
+                // without data yet there is nothing to adapt to
+                let interval = match &self.adaptive {
+                    Some(adaptive) if job.data.is_some() => adaptive.interval(
+                        job.departures
+                            .current()
+                            .iter()
+                            .map(|dep| dep.countdown)
+                            .min(),
+                        job.interval,
+                    ),
+                    _ => job.interval,
+                }
+                .saturating_mul(self.throttle);
+                let failover = self
+                    .failover
+                    .as_ref()
+                    .filter(|(_, after)| job.failures >= *after)
+                    .map(|(provider, _)| provider);
+                job.sequence += 1;
+                for batch in job.batches.iter_mut().filter(|batch| batch_due(batch)) {
+                    batch.sequence = job.sequence;
+                    batch.task = Some(tokio::spawn(timed_request(
+                        self.provider.clone(),
+                        batch.stops.clone(),
+                        self.lines.clone(),
+                        self.countdown,
+                        self.destinations.clone(),
+                        self.script.clone(),
+                        self.shared_cache.clone().map(|cache| (cache, interval)),
+                        self.daemon.clone(),
+                        failover.cloned(),
+                    )));
+                    batch.started = now;
+                }
+                if job_due {
+                    job.next_run = now
+                        + match self.phase {
+                            Some(phase) => until_phase(interval, phase),
+                            None => interval,
+                        };
+                }
+            }
+        }
+        if relearned {
+            self.rebatch(true);
+        }
+        updated
+    }
+
+    /// Show `data` for the job at `index` until its first request finishes, e.g. data saved before a restart
+    pub fn restore(&mut self, index: usize, data: ApiResponse) {
+        if let Some(job) = self.jobs.get_mut(index).filter(|job| job.data.is_none()) {
+            job.departures.apply(&data.0);
+            job.disruptions.apply(data.1.as_deref().unwrap_or_default());
+            job.data = Some(data);
+        }
+    }
+
+    /// Latest data of every job, by index
+    pub fn saved_data(&self) -> Vec<Option<ApiResponse>> {
+        self.jobs.iter().map(|job| job.data.clone()).collect()
+    }
+
+    /// Latest data of all jobs that finished a request
+    pub fn all_data(&self) -> impl Iterator<Item = &ApiResponse> {
+        self.jobs.iter().filter_map(|job| job.data.as_ref())
+    }
+
+    /// Latest data of the job at `index`, `None` until its first request finished
+    pub fn data(&self, index: usize) -> Option<&ApiResponse> {
+        self.jobs.get(index).and_then(|job| job.data.as_ref())
+    }
+
+    /// Departures of the job at `index`, tracked across its requests
+    pub fn departures(&self, index: usize) -> Option<&DepartureSet> {
+        self.jobs.get(index).map(|job| &job.departures)
+    }
+
+    /// Disruptions of the job at `index`, tracked across its requests
+    pub fn disruptions(&self, index: usize) -> Option<&DisruptionSet> {
+        self.jobs.get(index).map(|job| &job.disruptions)
+    }
+
+    /// Why the latest request of the job at `index` failed, `None` if it succeeded
+    ///
+    /// Isolated stops don't count, see [`Scheduler::isolated`].
+    pub fn error(&self, index: usize) -> Option<&ApiRequestError> {
+        self.jobs.get(index).and_then(|job| {
+            job.batches
+                .iter()
+                .filter(|batch| !batch.is_isolated())
+                .find_map(|batch| batch.error.as_ref())
+        })
+    }
+
+    /// Stops of the job at `index` failing on their own, and when each is requested again
+    pub fn isolated(&self, index: usize) -> Vec<(i32, Instant)> {
+        self.jobs.get(index).map_or(vec![], |job| {
+            job.batches
+                .iter()
+                .filter(|batch| batch.is_isolated())
+                .filter_map(|batch| Some((*batch.stops.first()?, batch.next_run?)))
+                .collect()
+        })
+    }
+
+    /// When the job at `index` requests again after its latest request failed
+    pub fn retry_at(&self, index: usize) -> Option<Instant> {
+        self.jobs
+            .get(index)
+            .filter(|_| self.error(index).is_some())
+            .map(|job| job.next_run)
+    }
+
+    /// Number of stale batches of the job at `index`, and of all its batches
+    ///
+    /// A batch is stale while it is loading for longer than the deadline.
+    pub fn stale(&self, index: usize) -> (usize, usize) {
+        let now = Instant::now();
+        self.jobs.get(index).map_or((0, 0), |job| {
+            let stale = job
+                .batches
+                .iter()
+                .filter(|batch| {
+                    batch.task.is_some()
+                        && now.saturating_duration_since(batch.started) > self.deadline
+                })
+                .count();
+            (stale, job.batches.len())
+        })
+    }
+
+    /// Why the provider of the job at `index` failed, while it shows data of the failover provider
+    pub fn degraded(&self, index: usize) -> Option<&ApiRequestError> {
+        self.jobs
+            .get(index)
+            .and_then(|job| job.batches.iter().find_map(|batch| batch.degraded.as_ref()))
+    }
+
+    /// Requests of the job at `index` that failed in a row
+    pub fn failures(&self, index: usize) -> u32 {
+        self.jobs.get(index).map_or(0, |job| job.failures)
+    }
+
+    /// Monitors of the job at `index` skipped in the latest responses because they were malformed
+    pub fn skipped(&self, index: usize) -> usize {
+        self.jobs
+            .get(index)
+            .map_or(0, |job| job.batches.iter().map(|batch| batch.skipped).sum())
+    }
+
+    /// Timings of the latest request of the job at `index`
+    pub fn timings(&self, index: usize) -> Option<RequestTimings> {
+        self.jobs.get(index).and_then(|job| job.timings)
+    }
+}
+
+impl Job {
+    /// Departures and disruptions of all batches that have data, as one response
+    ///
+    /// Departures reported by several stops are only kept once.
+    fn merged_data(&self, dedup_tolerance: Duration) -> ApiResponse {
+        let mut departures = vec![];
+        let mut traffic_infos: Option<Vec<WienerLinienTrafficInfo>> = None;
+        for (batch_departures, batch_traffic_infos) in
+            self.batches.iter().filter_map(|batch| batch.data.as_ref())
+        {
+            departures.extend(batch_departures.iter().cloned());
+            if let Some(infos) = batch_traffic_infos {
+                traffic_infos
+                    .get_or_insert_with(Vec::new)
+                    .extend(infos.iter().cloned());
+            }
+        }
+        let mut departures = departures::dedup(departures, dedup_tolerance);
+        departures.sort();
+        (departures, traffic_infos)
+    }
+
+    fn abort(&self) {
+        for task in self.batches.iter().filter_map(|batch| batch.task.as_ref()) {
+            task.abort();
+        }
+    }
+}
+
+/// Positions of the batches whose results can be taken over, those of refreshes
+/// none of whose batches is still loading within the `deadline`
+fn released(batches: &[Batch], now: Instant, deadline: Duration) -> Vec<usize> {
+    let loading = |sequence: u64| {
+        batches.iter().any(|batch| {
+            batch.task.is_some()
+                && batch.sequence == sequence
+                && now.saturating_duration_since(batch.started) <= deadline
+        })
+    };
+    batches
+        .iter()
+        .enumerate()
+        .filter(|(_, batch)| batch.held.is_some() && !loading(batch.sequence))
+        .map(|(position, _)| position)
+        .collect()
+}
+
+/// The departures of `stops` in `data`, and its disruptions if `traffic_infos_too`
+fn data_of(
+    (departures, traffic_infos): &ApiResponse,
+    stops: &[i32],
+    traffic_infos_too: bool,
+) -> ApiResponse {
+    (
+        departures
+            .iter()
+            .filter(|dep| dep.stop_id.is_some_and(|stop| stops.contains(&stop)))
+            .cloned()
+            .collect(),
+        traffic_infos.clone().filter(|_| traffic_infos_too),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn timed_request(
+    provider: Provider,
+    stops: Vec<i32>,
+    lines: Arc<LineOverrides>,
+    countdown: CountdownSettings,
+    destinations: Option<Arc<DestinationNames>>,
+    script: Option<Arc<RowScript>>,
+    shared_cache: Option<(SharedCache, Duration)>,
+    daemon: Option<PathBuf>,
+    failover: Option<Provider>,
+) -> RequestResult {
+    let started = Instant::now();
+    let primary = async {
+        let responses =
+            within_timeout(fetch(&provider, &stops, shared_cache, daemon.as_deref())).await?;
+        let fetched = Instant::now();
+        Ok((provider.parse(&responses, &lines)?, fetched))
+    }
+    .await;
+    let (((mut departures, traffic_infos), skipped), fetched, degraded) = match (primary, failover)
+    {
+        (Ok((parsed, fetched)), _) => (parsed, fetched, None),
+        (Err(e), Some(failover)) => {
+            let responses = within_timeout(failover.fetch(&stops)).await?;
+            let fetched = Instant::now();
+            (failover.parse(&responses, &lines)?, fetched, Some(e))
+        }
+        (Err(e), None) => return Err(e),
+    };
+    countdown.apply(&mut departures);
+    if let Some(destinations) = destinations {
+        destinations.apply(&mut departures);
+    }
+    if let Some(script) = script {
+        script.apply(&mut departures);
+    }
+
+    Ok(Fetched {
+        data: (departures, traffic_infos),
+        skipped,
+        timings: RequestTimings {
+            fetch: fetched - started,
+            parse: fetched.elapsed(),
+        },
+        degraded,
+    })
+}
+
+/// The result of `request`, or an error once it took longer than [`REQUEST_TIMEOUT`]
+async fn within_timeout<T>(
+    request: impl Future<Output = Result<T, ApiRequestError>>,
+) -> Result<T, ApiRequestError> {
+    tokio::time::timeout(REQUEST_TIMEOUT, request)
+        .await
+        .unwrap_or(Err(ApiRequestError::TimedOut(REQUEST_TIMEOUT)))
+}
+
+/// Responses for `stops` through the daemon if it is running, otherwise through
+/// the shared cache or from the provider directly
+async fn fetch(
+    provider: &Provider,
+    stops: &[i32],
+    shared_cache: Option<(SharedCache, Duration)>,
+    daemon: Option<&Path>,
+) -> Result<Vec<String>, ApiRequestError> {
+    if let Some(result) = match daemon {
+        Some(socket) => daemon::fetch(socket, stops).await,
+        None => None,
+    } {
+        return result;
+    }
+    match shared_cache {
+        Some((cache, max_age)) => cache.fetch(provider, stops, max_age).await,
+        None => provider.fetch(stops).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A batch of refresh `sequence` whose request is still on its way
+    fn loading(stop: i32, sequence: u64) -> Batch {
+        Batch {
+            task: Some(tokio::spawn(std::future::pending())),
+            sequence,
+            ..Batch::new(vec![stop], Instant::now())
+        }
+    }
+
+    /// A scheduler of one job with a batch per stop, each loading the first refresh
+    fn scheduler(stops: &[i32], deadline: u64) -> Scheduler {
+        let batching = Batching {
+            size: 1,
+            deadline,
+            ..Batching::default()
+        };
+        let mut scheduler = Scheduler::new(
+            Provider::Demo,
+            LineOverrides::default(),
+            &batching,
+            Duration::ZERO,
+            Duration::ZERO,
+            None,
+            None,
+            [(stops.to_vec(), Duration::from_secs(3600))],
+        );
+        let job = &mut scheduler.jobs[0];
+        // refreshes are started by the tests instead
+        job.next_run = Instant::now() + Duration::from_secs(3600);
+        job.sequence = 1;
+        job.batches = stops.iter().map(|stop| loading(*stop, 1)).collect();
+        scheduler
+    }
+
+    /// The request of `batch` came back with a departure from its stop as of its refresh
+    async fn respond(batch: &mut Batch) {
+        batch.task.take().expect("batch wasn't loading").abort();
+        let departure = Departure {
+            time_planned: iso8601_timestamp::Timestamp::UNIX_EPOCH,
+            time_real: None,
+            countdown: 0,
+            station_name: format!("refresh {}", batch.sequence),
+            stop_id: batch.stops.first().copied(),
+            platform: None,
+            destination_name: format!("{:?}", batch.stops),
+            line: crate::Line::new(
+                "U1",
+                crate::WienerLinienVehicleType::Metro,
+                &LineOverrides::default(),
+            ),
+            occupancy: None,
+            traffic_jam: false,
+            note: None,
+            countdowns: None,
+        };
+        let task = tokio::spawn(std::future::ready(Ok(Fetched {
+            data: (vec![departure], None),
+            skipped: 0,
+            timings: RequestTimings {
+                fetch: Duration::ZERO,
+                parse: Duration::ZERO,
+            },
+            degraded: None,
+        })));
+        while !task.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        batch.task = Some(task);
+    }
+
+    /// Refreshes the departures shown are from, and the number of departures
+    fn shown(scheduler: &Scheduler) -> (Vec<String>, usize) {
+        let departures = scheduler.data(0).map_or(&[][..], |(deps, _)| deps);
+        let mut refreshes: Vec<String> = departures
+            .iter()
+            .map(|dep| dep.station_name.clone())
+            .collect();
+        refreshes.dedup();
+        (refreshes, departures.len())
+    }
+
+    /// The request of `batch` came back
+    fn answer(batch: &mut Batch) {
+        batch.task.take().expect("batch wasn't loading").abort();
+        batch.held = Some(Err(ApiRequestError::Daemon(format!("{:?}", batch.stops))));
+    }
+
+    #[tokio::test]
+    async fn releases_a_refresh_once_all_its_batches_answered() {
+        let mut batches = vec![loading(1, 1), loading(2, 1), loading(3, 1)];
+        answer(&mut batches[2]);
+        answer(&mut batches[0]);
+        assert!(released(&batches, Instant::now(), Duration::from_secs(60)).is_empty());
+        answer(&mut batches[1]);
+        assert_eq!(
+            released(&batches, Instant::now(), Duration::from_secs(60)),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn later_refreshes_answering_first_wait_for_nothing_else() {
+        // stop 3 was retried on its own while the first refresh is still loading
+        let mut batches = vec![loading(1, 1), loading(2, 1), loading(3, 2)];
+        answer(&mut batches[2]);
+        answer(&mut batches[1]);
+        assert_eq!(
+            released(&batches, Instant::now(), Duration::from_secs(60)),
+            vec![2]
+        );
+        batches[2].held = None;
+        answer(&mut batches[0]);
+        assert_eq!(
+            released(&batches, Instant::now(), Duration::from_secs(60)),
+            vec![0, 1]
+        );
+    }
+
+    #[tokio::test]
+    async fn poll_never_mixes_refreshes() {
+        let mut scheduler = scheduler(&[1, 2, 3], 60);
+        for position in [2, 0] {
+            respond(&mut scheduler.jobs[0].batches[position]).await;
+            assert!(scheduler.poll().await.is_empty());
+            assert_eq!(shown(&scheduler), (vec![], 0));
+        }
+        respond(&mut scheduler.jobs[0].batches[1]).await;
+        assert_eq!(scheduler.poll().await, vec![0]);
+        assert_eq!(shown(&scheduler), (vec!["refresh 1".to_string()], 3));
+
+        // the second refresh answers in another order, stop 2 first
+        let job = &mut scheduler.jobs[0];
+        job.sequence = 2;
+        for batch in &mut job.batches {
+            *batch = Batch {
+                data: batch.data.take(),
+                shown: batch.shown,
+                ..loading(batch.stops[0], 2)
+            };
+        }
+        for position in [1, 2] {
+            respond(&mut scheduler.jobs[0].batches[position]).await;
+            assert!(scheduler.poll().await.is_empty());
+            assert_eq!(shown(&scheduler), (vec!["refresh 1".to_string()], 3));
+        }
+        respond(&mut scheduler.jobs[0].batches[0]).await;
+        assert_eq!(scheduler.poll().await, vec![0]);
+        assert_eq!(shown(&scheduler), (vec!["refresh 2".to_string()], 3));
+    }
+
+    #[tokio::test]
+    async fn poll_stops_waiting_for_a_batch_past_the_deadline() {
+        let mut scheduler = scheduler(&[1, 2, 3], 0);
+        respond(&mut scheduler.jobs[0].batches[0]).await;
+        respond(&mut scheduler.jobs[0].batches[2]).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(scheduler.poll().await, vec![0]);
+        assert_eq!(shown(&scheduler), (vec!["refresh 1".to_string()], 2));
+        assert_eq!(scheduler.stale(0), (1, 3));
+
+        respond(&mut scheduler.jobs[0].batches[1]).await;
+        assert_eq!(scheduler.poll().await, vec![0]);
+        assert_eq!(shown(&scheduler), (vec!["refresh 1".to_string()], 3));
+        assert_eq!(scheduler.stale(0), (0, 3));
+    }
+
+    #[tokio::test]
+    async fn reconfigure_keeps_the_data_of_jobs_with_the_same_stops() {
+        let mut scheduler = scheduler(&[1, 2], 60);
+        for position in [0, 1] {
+            respond(&mut scheduler.jobs[0].batches[position]).await;
+        }
+        scheduler.poll().await;
+        let hour = Duration::from_secs(3600);
+        scheduler.reconfigure(
+            Provider::Demo,
+            LineOverrides::default(),
+            &Batching::default(),
+            Duration::ZERO,
+            Duration::ZERO,
+            None,
+            None,
+            [(vec![3], hour), (vec![1, 2], hour)],
+        );
+        scheduler.abort();
+        assert!(scheduler.data(0).is_none());
+        assert_eq!(scheduler.data(1).map(|(deps, _)| deps.len()), Some(2));
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{anyhow, bail, Result};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use std::path::Path;
+
+use crate::{expression, Departure};
+
+/// Function a row script has to define
+const FUNCTION: &str = "transform";
+
+/// Operations a script may take per departure, so a script stuck in a loop can't hold up refreshes
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A Rhai script changing departures before they are shown, see `script` in the config
+///
+/// The script defines `fn transform(dep)`, which gets every departure as a map
+/// and returns it changed, or `false` to drop the departure. Of the map's keys,
+/// `label`, `destination`, `station`, `platform` and `note` are taken over,
+/// a note is shown in a column of its own.
+pub struct RowScript {
+    engine: Engine,
+    ast: AST,
+}
+
+impl RowScript {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| anyhow!("Failed to compile {}: {}", path.display(), e))?;
+        if !ast
+            .iter_functions()
+            .any(|function| function.name == FUNCTION && function.params.len() == 1)
+        {
+            bail!("{} defines no `fn {}(dep)`", path.display(), FUNCTION);
+        }
+        Ok(RowScript { engine, ast })
+    }
+
+    /// Run the script on every departure, dropping those it returns `false` for
+    ///
+    /// A departure the script fails on is kept as it is.
+    pub fn apply(&self, departures: &mut Vec<Departure>) {
+        departures.retain_mut(|dep| self.transform(dep).unwrap_or(true));
+    }
+
+    /// Whether to keep `dep`, `None` if the script failed
+    fn transform(&self, dep: &mut Departure) -> Option<bool> {
+        let result: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, FUNCTION, (to_map(dep),))
+            .ok()?;
+        if let Some(keep) = result.clone().try_cast::<bool>() {
+            return Some(keep);
+        }
+        let map = result.try_cast::<Map>()?;
+        let text = |key: &str| {
+            map.get(key)
+                .filter(|value| !value.is_unit())
+                .map(|value| value.to_string())
+        };
+        if let Some(label) = text("label").filter(|label| *label != dep.line.name) {
+            dep.line.label = Some(label);
+        }
+        if let Some(destination) = text("destination") {
+            dep.destination_name = destination;
+        }
+        if let Some(station) = text("station") {
+            dep.station_name = station;
+        }
+        dep.platform = text("platform").filter(|platform| !platform.is_empty());
+        dep.note = text("note").filter(|note| !note.is_empty());
+        Some(true)
+    }
+}
+
+/// `dep` as the script sees it, with the fields filter expressions know
+fn to_map(dep: &Departure) -> Map {
+    let mut map = Map::new();
+    let mut insert = |key: &str, value: Dynamic| {
+        map.insert(key.into(), value);
+    };
+    insert("line", dep.line.name.clone().into());
+    insert("label", dep.line.display_name().to_string().into());
+    insert("type", expression::vehicle_type(dep).into());
+    insert("destination", dep.destination_name.clone().into());
+    insert("station", dep.station_name.clone().into());
+    insert(
+        "platform",
+        dep.platform.clone().map_or(Dynamic::UNIT, Dynamic::from),
+    );
+    insert(
+        "stop",
+        dep.stop_id
+            .map_or(Dynamic::UNIT, |id| Dynamic::from(id as i64)),
+    );
+    insert("countdown", dep.countdown.into());
+    insert("delay", dep.delay_minutes().into());
+    insert("realtime", dep.time_real.is_some().into());
+    insert("traffic_jam", dep.traffic_jam.into());
+    insert(
+        "note",
+        dep.note.clone().map_or(Dynamic::UNIT, Dynamic::from),
+    );
+    map
+}
@@ -0,0 +1,441 @@
+use config::{Config, ConfigError, Environment, File, FileFormat};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    alerts::AlertRule,
+    announce::AnnouncementSettings,
+    anomaly::AnomalySettings,
+    cli::Args,
+    clock::ClockSettings,
+    countdown::CountdownSettings,
+    destinations::DestinationNames,
+    expression::Filter,
+    filter::TimeWindow,
+    header::HeaderSettings,
+    headway::HeadwaySettings,
+    http::HttpSettings,
+    journey::JourneySettings,
+    keys::KeyBindings,
+    lines::LineOverrides,
+    locale::Lang,
+    mqtt::MqttSettings,
+    ntfy::NtfySettings,
+    output::{FbdevSettings, LedMatrixSettings},
+    power::BatterySettings,
+    provider::{City, Provider},
+    quiet::QuietHours,
+    scheduler::{AdaptiveRefresh, Batching, Failover},
+    table_style::TableStyle,
+    template,
+    theme::{Palette, ThemePreset},
+    timetable::TimeTravel,
+    view::Carousel,
+    walking::WalkingSettings,
+    widgets::Layout,
+    Departure, STATION_IDS,
+};
+
+/// Config file looked up in the working directory when no `--config` is given
+pub const DEFAULT_CONFIG_FILE: &str = "oeffimonitor";
+
+/// Prefix of environment variables overriding settings
+const ENV_PREFIX: &str = "OEFFI";
+
+/// Variables with the prefix that are command line options rather than settings
+const ENV_ARGS: [&str; 2] = ["OEFFI_OUTPUT", "OEFFI_CONTAINER"];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Stop IDs (RBL numbers) to monitor
+    #[serde(deserialize_with = "one_or_many")]
+    pub stops: Vec<i32>,
+    /// File with further stop IDs, one per line, watched so edits change the board right away
+    pub stops_file: Option<PathBuf>,
+    /// Transit network the stops belong to, overridden by `--city`
+    pub city: City,
+    /// Made up departures instead of the city's, set by `--demo`
+    #[serde(skip)]
+    pub demo: bool,
+    /// Directory of an extracted GTFS feed, the static timetable `--at` previews
+    pub timetable: Option<PathBuf>,
+    /// Timetable as of the time set by `--at`
+    #[serde(skip)]
+    pub time_travel: Option<Arc<TimeTravel>>,
+    /// Named sets of stop IDs, e.g. the platforms of one station, that profiles
+    /// include and filters refer to by name
+    pub stop_groups: StopGroups,
+    /// Named alternative stop sets, selected with `--profile`
+    pub profiles: BTreeMap<String, Profile>,
+    /// Seconds between API requests, unless a view sets its own interval
+    pub refresh: u64,
+    /// Seconds into every refresh interval requests are made at, counted from the full minute
+    pub refresh_phase: Option<u64>,
+    /// Refresh more often while the next departure is close and less often while it is far out
+    pub adaptive_refresh: Option<AdaptiveRefresh>,
+    /// Throttling while on battery, see `--power`
+    pub battery: BatterySettings,
+    /// Frames drawn per second at most, e.g. 2 for a slow serial console; not capped if unset
+    pub max_fps: Option<u32>,
+    /// Hours without requests, showing only a dim clock
+    pub quiet_hours: Vec<QuietHours>,
+    pub batching: Batching,
+    /// Provider requested while the city's keeps failing
+    pub failover: Option<Failover>,
+    pub countdown: CountdownSettings,
+    /// Seconds apart departures of a line and destination may be planned and still count as one
+    pub dedup_tolerance: u64,
+    /// Minutes departures stay on the board after they left, dimmed, never if 0
+    pub keep_departed: u64,
+    pub window: TimeWindow,
+    /// Only show departures matching this expression, overridden by `--filter`
+    pub filter: Option<Filter>,
+    /// Colors of the board, overridden by `--palette`
+    pub palette: Palette,
+    /// Language of times and durations on the board, overridden by `--lang`
+    pub lang: Lang,
+    /// Palette and table borders in one, overriding `palette` and `[table]`, see `--theme`
+    pub theme: Option<ThemePreset>,
+    pub header: HeaderSettings,
+    pub table: TableStyle,
+    pub clock: ClockSettings,
+    /// Keys of the full screen board by action
+    pub keys: KeyBindings,
+    /// Template replacing the footer, e.g. "{clock} · {stale} · {disruption_count} disruptions",
+    /// with the placeholders in [`FOOTER_PLACEHOLDERS`]
+    pub footer: Option<String>,
+    /// Per line display overrides, keyed by line name
+    pub lines: LineOverrides,
+    /// Cleanup of the all caps, abbreviated destination names the API reports
+    pub destinations: Option<DestinationNames>,
+    /// Rhai script defining `fn transform(dep)`, run on every departure before it is shown
+    pub script: Option<PathBuf>,
+    pub walking: WalkingSettings,
+    pub anomalies: AnomalySettings,
+    /// Banner pointing out gaps in the service of a line
+    pub headway: HeadwaySettings,
+    /// Two-leg journey shown by views of kind "journey"
+    pub journey: Option<JourneySettings>,
+    pub carousel: Carousel,
+    /// Seconds each page of departures is shown when not all of them fit, not paged if unset
+    pub auto_page: Option<u64>,
+    /// Widgets composing the board, the departure board alone if unset
+    pub layout: Option<Layout>,
+    /// Departure alerts, their lines also select which disruptions are reported
+    pub alerts: Vec<AlertRule>,
+    pub ntfy: Option<NtfySettings>,
+    /// Speak departures that match an alert rule
+    pub announcements: Option<AnnouncementSettings>,
+    pub mqtt: Option<MqttSettings>,
+    /// Unix socket accepting control commands
+    pub control_socket: Option<PathBuf>,
+    /// Unix socket of the daemon departures are requested through, directly while it isn't running
+    pub daemon_socket: Option<PathBuf>,
+    /// File the latest data and reported alerts are saved to on shutdown and restored from
+    pub state_file: Option<PathBuf>,
+    /// Directory a weekly summary of refreshes, failed requests and render times is written to,
+    /// nothing is counted if unset
+    pub usage_stats: Option<PathBuf>,
+    /// Directory the monitors on this machine share API responses in, so each is requested once per interval
+    pub shared_cache: Option<PathBuf>,
+    pub http: Option<HttpSettings>,
+    /// Panel used by `--output led-matrix`
+    pub led_matrix: LedMatrixSettings,
+    /// Framebuffer used by `--output fbdev`
+    pub fbdev: FbdevSettings,
+}
+
+/// Stop IDs by group name
+pub type StopGroups = BTreeMap<String, Vec<i32>>;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Profile {
+    #[serde(default)]
+    pub stops: Vec<i32>,
+    /// Stop groups monitored on top of `stops`, by name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            stops: STATION_IDS.to_vec(),
+            stops_file: None,
+            city: City::default(),
+            demo: false,
+            timetable: None,
+            time_travel: None,
+            stop_groups: StopGroups::new(),
+            profiles: BTreeMap::new(),
+            refresh: 10,
+            refresh_phase: None,
+            adaptive_refresh: None,
+            battery: BatterySettings::default(),
+            max_fps: None,
+            quiet_hours: vec![],
+            batching: Batching::default(),
+            failover: None,
+            countdown: CountdownSettings::default(),
+            dedup_tolerance: 0,
+            keep_departed: 0,
+            window: TimeWindow::default(),
+            filter: None,
+            palette: Palette::default(),
+            lang: Lang::default(),
+            theme: None,
+            header: HeaderSettings::default(),
+            table: TableStyle::default(),
+            clock: ClockSettings::default(),
+            keys: KeyBindings::default(),
+            footer: None,
+            lines: LineOverrides::new(),
+            destinations: None,
+            script: None,
+            walking: WalkingSettings::default(),
+            anomalies: AnomalySettings::default(),
+            headway: HeadwaySettings::default(),
+            journey: None,
+            carousel: Carousel::default(),
+            auto_page: None,
+            layout: None,
+            alerts: vec![],
+            ntfy: None,
+            announcements: None,
+            mqtt: None,
+            control_socket: None,
+            daemon_socket: None,
+            state_file: None,
+            usage_stats: None,
+            shared_cache: None,
+            http: None,
+            led_matrix: LedMatrixSettings::default(),
+            fbdev: FbdevSettings::default(),
+        }
+    }
+}
+
+/// Placeholders the `footer` template may use
+pub const FOOTER_PLACEHOLDERS: &[&str] = &["clock", "page", "notice", "stale", "disruption_count"];
+
+impl Settings {
+    /// Load the settings from `path`, or from the optional default config file,
+    /// and switch to the stops of `profile` if one is given
+    pub fn load(path: Option<&Path>, profile: Option<&str>) -> Result<Self, ConfigError> {
+        let mut settings: Settings = sources(path)?.try_deserialize()?;
+
+        // a typo would otherwise only show up on the board, as the placeholder itself
+        if let Some(unknown) = settings
+            .footer
+            .iter()
+            .flat_map(|footer| template::placeholders(footer))
+            .find(|name| !FOOTER_PLACEHOLDERS.contains(&name.as_str()))
+        {
+            return Err(ConfigError::Message(format!(
+                "footer: unknown placeholder {{{}}}, expected one of {}",
+                unknown,
+                FOOTER_PLACEHOLDERS.join(", ")
+            )));
+        }
+
+        if let Some(name) = profile {
+            let profile = settings
+                .profiles
+                .get(name)
+                .ok_or_else(|| ConfigError::NotFound(format!("profile {}", name)))?;
+            settings.stops = settings.profile_stops(profile)?;
+        }
+        if let Some(path) = &settings.stops_file {
+            for stop in read_stops_file(path)? {
+                if !settings.stops.contains(&stop) {
+                    settings.stops.push(stop);
+                }
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Stops of `profile`, its own ones and then those of its groups
+    pub fn profile_stops(&self, profile: &Profile) -> Result<Vec<i32>, ConfigError> {
+        let mut stops = profile.stops.clone();
+        for name in &profile.groups {
+            let group = self
+                .stop_groups
+                .get(name)
+                .ok_or_else(|| ConfigError::NotFound(format!("stop group {}", name)))?;
+            for stop in group {
+                if !stops.contains(stop) {
+                    stops.push(*stop);
+                }
+            }
+        }
+        Ok(stops)
+    }
+
+    /// Where departures are requested from
+    pub fn provider(&self) -> Provider {
+        match (&self.time_travel, self.demo) {
+            (Some(travel), _) => Provider::Timetable(travel.clone()),
+            (None, true) => Provider::Demo,
+            (None, false) => self.city.provider(),
+        }
+    }
+
+    /// Whether `dep` is inside the time window and matches the filter
+    pub fn shows(&self, dep: &Departure) -> bool {
+        self.window.contains(dep)
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(dep, &self.stop_groups))
+    }
+
+    /// Let command line flags take precedence over the config file
+    pub fn apply_args(&mut self, args: &Args) {
+        self.window.within = args.within.or(self.window.within);
+        self.window.after = args.after.or(self.window.after);
+        self.window.before = args.before.or(self.window.before);
+        if let Some(filter) = &args.filter {
+            self.filter = Some(filter.clone());
+        }
+        if let Some(preset) = args.theme.or(self.theme) {
+            self.theme = Some(preset);
+            self.palette = preset.palette();
+            preset.apply(&mut self.table);
+        }
+        self.palette = args.palette.unwrap_or(self.palette);
+        self.lang = args.lang.unwrap_or(self.lang);
+        if let Some(preset) = args.layout {
+            self.layout = Some(preset.layout());
+        }
+        self.city = args.city.unwrap_or(self.city);
+        self.demo = args.demo;
+    }
+}
+
+/// The config file at `path`, or the optional default one, with the environment on top
+pub fn sources(path: Option<&Path>) -> Result<Config, ConfigError> {
+    let file = match path {
+        Some(path) => File::from(path).format(format_of(path)?).required(true),
+        None => File::with_name(DEFAULT_CONFIG_FILE).required(false),
+    };
+    Config::builder()
+        .add_source(file)
+        .add_source(environment())
+        .build()
+}
+
+/// The config file read from `path`, or the default one if there is one
+pub fn config_file(path: Option<&Path>) -> Option<PathBuf> {
+    match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => ["toml", "yaml", "yml", "json", "json5", "ini", "ron"]
+            .iter()
+            .map(|extension| Path::new(DEFAULT_CONFIG_FILE).with_extension(extension))
+            .find(|path| path.is_file()),
+    }
+}
+
+/// `OEFFI_*` variables overriding the config file, e.g. `OEFFI_STOPS=252,269` or
+/// `OEFFI_CLOCK__DATE=true` for nested settings, so containers can run without one
+fn environment() -> Environment {
+    let variables = std::env::vars()
+        .filter(|(key, _)| !ENV_ARGS.contains(&key.to_uppercase().as_str()))
+        .collect();
+    Environment::with_prefix(ENV_PREFIX)
+        .source(Some(variables))
+        .prefix_separator("_")
+        .separator("__")
+        .try_parsing(true)
+        .list_separator(",")
+        .with_list_parse_key("stops")
+}
+
+/// A list, or a single value, which `OEFFI_STOPS=252` can't be told apart from
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i32>, D::Error> {
+    struct OneOrMany;
+
+    impl<'de> Visitor<'de> for OneOrMany {
+        type Value = Vec<i32>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a stop ID or a list of them")
+        }
+
+        fn visit_i64<E: de::Error>(self, stop: i64) -> Result<Self::Value, E> {
+            i32::try_from(stop)
+                .map(|stop| vec![stop])
+                .map_err(|_| E::invalid_value(de::Unexpected::Signed(stop), &self))
+        }
+
+        fn visit_u64<E: de::Error>(self, stop: u64) -> Result<Self::Value, E> {
+            i32::try_from(stop)
+                .map(|stop| vec![stop])
+                .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(stop), &self))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut stops = vec![];
+            while let Some(stop) = seq.next_element()? {
+                stops.push(stop);
+            }
+            Ok(stops)
+        }
+    }
+
+    deserializer.deserialize_any(OneOrMany)
+}
+
+/// Stop IDs of a stops file, `#` starts a comment; a missing file has none
+fn read_stops_file(path: &Path) -> Result<Vec<i32>, ConfigError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => {
+            return Err(ConfigError::Message(format!(
+                "can't read {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.parse().map_err(|_| {
+                ConfigError::Message(format!("{} in {} is no stop ID", line, path.display()))
+            })
+        })
+        .collect()
+}
+
+/// Format of a config file, from its extension
+fn format_of(path: &Path) -> Result<FileFormat, ConfigError> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "toml" => Ok(FileFormat::Toml),
+        "yaml" | "yml" => Ok(FileFormat::Yaml),
+        "json" => Ok(FileFormat::Json),
+        "json5" => Ok(FileFormat::Json5),
+        "ini" => Ok(FileFormat::Ini),
+        "ron" => Ok(FileFormat::Ron),
+        _ => Err(ConfigError::Message(format!(
+            "can't tell the format of {} from its extension, use .toml, .yaml, .yml or .json",
+            path.display()
+        ))),
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{provider::Provider, ApiRequestError};
+
+/// API responses shared by the monitors running on one machine, see `shared_cache` in the config
+///
+/// Responses are kept per provider and stops, each next to a lock file. The
+/// first monitor to find them older than its interval requests them again while
+/// holding the lock, the others wait for it and read what was written, so
+/// monitors of the same stops query the API once per interval however many of
+/// them run. With `batching.size = 1` every stop is shared on its own, so
+/// monitors of overlapping stops share the stops they have in common.
+#[derive(Debug, Clone)]
+pub struct SharedCache {
+    dir: PathBuf,
+}
+
+impl SharedCache {
+    pub fn new(dir: PathBuf) -> Self {
+        SharedCache { dir }
+    }
+
+    /// Raw responses for `stops` like [`Provider::fetch`], taken from the cache
+    /// if another monitor requested them within `max_age`
+    ///
+    /// Without a usable cache directory the responses are requested directly.
+    pub async fn fetch(
+        &self,
+        provider: &Provider,
+        stops: &[i32],
+        max_age: Duration,
+    ) -> Result<Vec<String>, ApiRequestError> {
+        let Some(name) = cache_name(provider, stops) else {
+            return provider.fetch(stops).await;
+        };
+        let Ok(_lock) = lock(self.dir.clone(), self.dir.join(format!("{}.lock", name))).await
+        else {
+            return provider.fetch(stops).await;
+        };
+
+        let path = self.dir.join(format!("{}.json", name));
+        if let Some(responses) = read_fresh(&path, max_age) {
+            return Ok(responses);
+        }
+        let responses = provider.fetch(stops).await?;
+        // the next request tries again if this one can't be shared
+        let _ = write(&path, &responses);
+        Ok(responses)
+    }
+}
+
+/// File name the responses of `provider` for `stops` are cached under, `None` for
+/// providers answering locally
+fn cache_name(provider: &Provider, stops: &[i32]) -> Option<String> {
+    let stops: Vec<String> = stops.iter().map(|stop| stop.to_string()).collect();
+    Some(format!("{}-{}", source_name(provider)?, stops.join("_")))
+}
+
+/// Name of the API `provider` requests, as a file name, `None` for providers answering locally
+pub fn source_name(provider: &Provider) -> Option<String> {
+    Some(match provider {
+        Provider::WienerLinien => "wienerlinien".to_string(),
+        Provider::Mirror { url } => url_name(url),
+        Provider::Efa { url } | Provider::Hafas { url } => url_name(url),
+        Provider::Demo | Provider::Timetable(_) => return None,
+    })
+}
+
+/// `url` without its scheme, as a file name
+fn url_name(url: &str) -> String {
+    url.split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Wait for the lock file at `path`, released when the returned file is dropped
+async fn lock(dir: PathBuf, path: PathBuf) -> io::Result<File> {
+    tokio::task::spawn_blocking(move || {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        file.lock()?;
+        Ok(file)
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Responses cached at `path`, if they were written within `max_age`
+fn read_fresh(path: &Path, max_age: Duration) -> Option<Vec<String>> {
+    let written = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    if SystemTime::now().duration_since(written).ok()? >= max_age {
+        return None;
+    }
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Write `responses` to `path`, replacing the previous ones at once
+fn write(path: &Path, responses: &[String]) -> io::Result<()> {
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, serde_json::to_string(responses)?)?;
+    fs::rename(&temporary, path)
+}
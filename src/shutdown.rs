@@ -0,0 +1,83 @@
+use crossterm::{cursor, execute, terminal::disable_raw_mode};
+use std::{
+    io::{self, stdout},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::Notify,
+};
+
+/// Cleaning up may take this long before the monitor exits anyway
+const CLEANUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Notices SIGTERM and SIGINT, so the monitor can clean up before exiting
+///
+/// Cleaning up is bounded: a second signal, or cleaning up for longer than
+/// [`CLEANUP_TIMEOUT`], exits right away with the terminal given back.
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl Shutdown {
+    pub fn new() -> io::Result<Self> {
+        let requested = Arc::new(AtomicBool::new(false));
+        let notify = Arc::new(Notify::new());
+
+        for kind in [SignalKind::terminate(), SignalKind::interrupt()] {
+            let mut signal = signal(kind)?;
+            let requested = requested.clone();
+            let notify = notify.clone();
+            tokio::spawn(async move {
+                while signal.recv().await.is_some() {
+                    if requested.swap(true, Ordering::Relaxed) {
+                        force_exit();
+                    }
+                    notify.notify_one();
+                    tokio::spawn(watchdog());
+                }
+            });
+        }
+
+        Ok(Shutdown { requested, notify })
+    }
+
+    /// Whether a signal asked the monitor to exit
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Ask the monitor to exit, as a signal would
+    pub fn request(&self) {
+        if !self.requested.swap(true, Ordering::Relaxed) {
+            tokio::spawn(watchdog());
+        }
+        self.notify.notify_one();
+    }
+
+    /// Sleep for `duration`, waking up early if a shutdown is requested
+    pub async fn sleep(&self, duration: std::time::Duration) {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => {}
+            _ = self.notify.notified() => {}
+        }
+    }
+}
+
+/// Exit once cleaning up took too long
+async fn watchdog() {
+    tokio::time::sleep(CLEANUP_TIMEOUT).await;
+    force_exit();
+}
+
+/// Exit without cleaning up, only giving the terminal back
+fn force_exit() -> ! {
+    let _ = disable_raw_mode();
+    let _ = execute!(stdout(), cursor::Show);
+    std::process::exit(1);
+}
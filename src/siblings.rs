@@ -0,0 +1,150 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+};
+use toml_edit::{Array, DocumentMut, Item, Value};
+
+use crate::settings::{self, Settings};
+
+/// Stops ("Haltepunkte") of the Wiener Linien open data, one per RBL number with the station (DIVA) it belongs to
+const STOPS_URL: &str =
+    "https://www.wienerlinien.at/ogd_realtime/doku/ogd/wienerlinien-ogd-haltepunkte.csv";
+
+/// A row of the stops dataset
+#[derive(Debug)]
+struct Stop {
+    id: i32,
+    station: String,
+    name: String,
+}
+
+/// Print the other stop IDs of the station `stop` belongs to and offer to add
+/// the ones not monitored yet to the config file at `path`
+///
+/// The stops dataset is downloaded unless `dataset` is a copy of it. Each
+/// direction and platform of a station has a stop ID of its own, which this
+/// saves looking up one by one.
+pub async fn run(
+    stop: i32,
+    dataset: Option<&Path>,
+    path: Option<&Path>,
+    settings: &Settings,
+) -> Result<()> {
+    let text = match dataset {
+        Some(dataset) => fs::read_to_string(dataset)
+            .with_context(|| format!("Failed to read {}", dataset.display()))?,
+        None => {
+            reqwest::get(STOPS_URL)
+                .await
+                .and_then(|res| res.error_for_status())
+                .context("Failed to download the stops dataset")?
+                .text()
+                .await?
+        }
+    };
+    let stops = parse(&text)?;
+    let Some(this) = stops.iter().find(|other| other.id == stop) else {
+        bail!("Stop {} isn't in the stops dataset", stop);
+    };
+    let siblings: Vec<&Stop> = stops
+        .iter()
+        .filter(|other| other.station == this.station && other.id != stop)
+        .collect();
+    if siblings.is_empty() {
+        println!("Stop {} is the only one of {}", stop, this.name);
+        return Ok(());
+    }
+
+    println!("Stop {} is at {}, which also has", stop, this.name);
+    for sibling in &siblings {
+        let monitored = match settings.stops.contains(&sibling.id) {
+            true => ", monitored",
+            false => "",
+        };
+        println!("  {:>6}  {}{}", sibling.id, sibling.name, monitored);
+    }
+    let missing: Vec<i32> = [stop]
+        .into_iter()
+        .chain(siblings.iter().map(|sibling| sibling.id))
+        .filter(|id| !settings.stops.contains(id))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let file = settings::config_file(path)
+        .filter(|file| file.extension().and_then(|extension| extension.to_str()) == Some("toml"));
+    let ids: Vec<String> = missing.iter().map(i32::to_string).collect();
+    let Some(file) = file.filter(|_| io::stdin().is_terminal()) else {
+        println!("Add them to `stops` to monitor them: {}", ids.join(", "));
+        return Ok(());
+    };
+    print!("Add {} to {}? [y/N] ", ids.join(", "), file.display());
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        return Ok(());
+    }
+    add_stops(&file, &missing)?;
+    println!("Added {} stops to {}", missing.len(), file.display());
+    Ok(())
+}
+
+/// Rows of the stops dataset, separated by semicolons
+fn parse(text: &str) -> Result<Vec<Stop>> {
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('\u{feff}')
+        .split(';')
+        .map(|column| column.trim().trim_matches('"'))
+        .collect();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| *column == name)
+            .with_context(|| format!("The stops dataset has no {} column", name))
+    };
+    let (id, station, name) = (column("StopID")?, column("DIVA")?, column("StopText")?);
+
+    Ok(lines
+        .filter_map(|line| {
+            let fields: Vec<&str> = line
+                .split(';')
+                .map(|field| field.trim_matches('"'))
+                .collect();
+            Some(Stop {
+                id: fields.get(id)?.trim().parse().ok()?,
+                station: fields.get(station)?.trim().to_string(),
+                name: fields.get(name)?.trim().to_string(),
+            })
+        })
+        // stops without a station can't have siblings
+        .filter(|stop| !stop.station.is_empty())
+        .collect())
+}
+
+/// Append `stops` to the `stops` of the TOML config file at `path`, keeping its comments and layout
+fn add_stops(path: &Path, stops: &[i32]) -> Result<()> {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    let mut document: DocumentMut = text
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    // a single stop may be given without a list
+    let mut list = match document.get("stops").and_then(Item::as_value) {
+        Some(Value::Array(list)) => list.clone(),
+        Some(Value::Integer(stop)) => Array::from_iter([*stop.value()]),
+        Some(_) => bail!("`stops` in {} is no list", path.display()),
+        None => Array::new(),
+    };
+    for stop in stops {
+        list.push(i64::from(*stop));
+    }
+    document["stops"] = toml_edit::value(list);
+    fs::write(path, document.to_string())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
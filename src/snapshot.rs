@@ -0,0 +1,36 @@
+use chrono::{DateTime, Local};
+use std::{fs, io, path::Path, path::PathBuf};
+
+/// File a snapshot taken with a key press is written to, in the working directory
+pub fn file_name(now: &DateTime<Local>) -> PathBuf {
+    PathBuf::from(now.format("oeffimonitor-%Y%m%d-%H%M%S.txt").to_string())
+}
+
+/// Write the rendered board to `path`, with its ANSI codes or as plain text
+pub fn write(path: &Path, board: &str, ansi: bool) -> io::Result<()> {
+    let mut text = match ansi {
+        true => board.to_string(),
+        false => strip_ansi(board),
+    };
+    text.push('\n');
+    fs::write(path, text)
+}
+
+/// `text` without ANSI escape sequences
+pub fn strip_ansi(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip the sequence up to its final letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        output.push(c);
+    }
+    output
+}
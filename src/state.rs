@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use iso8601_timestamp::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{alerts::Reported, lines::LineOverrides, scheduler::ApiResponse, Line};
+
+/// What a restarted monitor picks up again, written to the `state_file` on shutdown
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SavedState {
+    /// Latest data of every scheduler job, by index
+    pub views: Vec<Option<ApiResponse>>,
+    pub reported: Reported,
+}
+
+impl SavedState {
+    /// Read the state saved at `path`, nothing if there is none yet
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(SavedState::default()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        serde_json::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Write the state to `path`, replacing the previous one at once
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = serde_json::to_string(self).context("Failed to serialize state")?;
+        let temporary = path.with_extension("tmp");
+        // on disk before it replaces the previous state, so a crash leaves one of them intact
+        File::create(&temporary)
+            .and_then(|mut file| {
+                file.write_all(text.as_bytes())?;
+                file.sync_all()
+            })
+            .with_context(|| format!("Failed to write {}", temporary.display()))?;
+        fs::rename(&temporary, path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Data of the views as of now: departures that left in the meantime are
+    /// dropped, countdowns counted again and the line overrides applied anew
+    pub fn take_views(&mut self, lines: &LineOverrides) -> Vec<Option<ApiResponse>> {
+        let now = Timestamp::now_utc();
+        let mut views = std::mem::take(&mut self.views);
+        for (departures, _) in views.iter_mut().flatten() {
+            departures.retain_mut(|dep| {
+                let time = dep.time_real.unwrap_or(dep.time_planned);
+                dep.countdown = time.duration_since(now).whole_minutes();
+                dep.line = Line::new(&dep.line.name, dep.line.vehicle_type.clone(), lines);
+                dep.countdown >= 0
+            });
+        }
+        views
+    }
+}
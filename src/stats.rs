@@ -0,0 +1,124 @@
+use chrono::{DateTime, Datelike, IsoWeek, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// How often the summary of the current week is written while the monitor runs
+const WRITE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Counts of what the monitor did in one week, see `usage_stats` in the config
+///
+/// Only local events are counted and the summary is only written to the
+/// configured directory, one file per ISO week; nothing is ever sent anywhere.
+/// Render times are kept as a histogram, so a restart within the week goes on
+/// counting where the summary left off.
+pub struct UsageStats {
+    dir: PathBuf,
+    week: IsoWeek,
+    summary: Summary,
+    written: Instant,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Summary {
+    /// Requests that brought new data
+    refreshes: u64,
+    failed_requests: u64,
+    frames: u64,
+    /// Percentiles of the render times, for reading the file
+    #[serde(default, skip_deserializing)]
+    render_ms: BTreeMap<String, f64>,
+    /// Render times in microseconds, rounded to two significant digits, and how often each occurred
+    render_us: BTreeMap<u64, u64>,
+}
+
+impl UsageStats {
+    /// Go on counting the current week in `dir`, from scratch if it has no summary yet
+    pub fn load(dir: PathBuf) -> Self {
+        let week = Local::now().iso_week();
+        let summary = fs::read_to_string(dir.join(file_name(week)))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        UsageStats {
+            dir,
+            week,
+            summary,
+            written: Instant::now(),
+        }
+    }
+
+    /// Count requests that succeeded and failed since the last call
+    pub fn record_requests(&mut self, succeeded: u64, failed: u64) {
+        self.summary.refreshes += succeeded;
+        self.summary.failed_requests += failed;
+    }
+
+    /// Count a frame that took `duration` from building the board to flushing it
+    pub fn record_frame(&mut self, duration: Duration, now: &DateTime<Local>) {
+        // a new week starts a new summary, the last one is complete
+        if now.iso_week() != self.week {
+            let _ = self.save();
+            self.week = now.iso_week();
+            self.summary = Summary::default();
+        }
+        self.summary.frames += 1;
+        *self
+            .summary
+            .render_us
+            .entry(significant(duration.as_micros() as u64))
+            .or_default() += 1;
+        if self.written.elapsed() >= WRITE_INTERVAL {
+            // the next write tries again
+            let _ = self.save();
+        }
+    }
+
+    /// Write the summary of the current week, replacing the previous one at once
+    pub fn save(&mut self) -> io::Result<()> {
+        self.written = Instant::now();
+        self.summary.render_ms = [("p50", 50), ("p95", 95), ("p99", 99), ("max", 100)]
+            .into_iter()
+            .filter_map(|(name, p)| {
+                Some((
+                    name.to_string(),
+                    percentile(&self.summary.render_us, p)? as f64 / 1000.0,
+                ))
+            })
+            .collect();
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(file_name(self.week));
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, serde_json::to_string_pretty(&self.summary)?)?;
+        fs::rename(&temporary, &path)
+    }
+}
+
+/// E.g. "usage-2024-W07.json"
+fn file_name(week: IsoWeek) -> String {
+    format!("usage-{}-W{:02}.json", week.year(), week.week())
+}
+
+/// `value` rounded down to two significant digits, e.g. 1234 to 1200
+fn significant(value: u64) -> u64 {
+    let mut scale = 1;
+    while value / scale >= 100 {
+        scale *= 10;
+    }
+    value / scale * scale
+}
+
+/// Value at percentile `p` of a histogram, `None` if it is empty
+fn percentile(histogram: &BTreeMap<u64, u64>, p: u64) -> Option<u64> {
+    let total: u64 = histogram.values().sum();
+    let rank = (total.checked_sub(1)? * p).div_ceil(100);
+    let mut seen = 0;
+    histogram.iter().find_map(|(value, count)| {
+        seen += count;
+        (seen > rank).then_some(*value)
+    })
+}
@@ -0,0 +1,101 @@
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS,
+    presets::{UTF8_BORDERS_ONLY, UTF8_FULL, UTF8_HORIZONTAL_ONLY},
+    CellAlignment, Table,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::header::Alignment;
+
+/// Characters a list of departures needs at least to get a column of its own
+const MIN_COLUMN_WIDTH: u16 = 100;
+
+/// Departure lists drawn side by side at most, unless `columns` says otherwise
+const MAX_COLUMNS: usize = 3;
+
+/// Borders and striping of the tables on the board, see `[table]` in the config
+#[derive(Debug, Clone, Default, Hash, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TableStyle {
+    pub separators: Separators,
+    pub corners: Corners,
+    /// Shade every other departure row
+    pub zebra: bool,
+    /// Alignment of the cells of a column, keyed by the default column name, left if unset
+    pub align: BTreeMap<String, Alignment>,
+    /// Departure lists side by side at most on wide screens, 1 for a single one
+    pub columns: Option<usize>,
+}
+
+/// Lines drawn between the cells of a table
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Separators {
+    /// Lines between all rows and columns
+    #[default]
+    Full,
+    /// Lines between rows only, without a left and right border
+    Horizontal,
+    /// Only a border around the table and below the header
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Corners {
+    #[default]
+    Round,
+    Square,
+}
+
+impl TableStyle {
+    /// Align the cells of the columns of `table`, called `names` by default
+    pub fn align(&self, table: &mut Table, names: &[&str]) {
+        for (index, name) in names.iter().enumerate() {
+            let (Some(align), Some(column)) = (self.align.get(*name), table.column_mut(index))
+            else {
+                continue;
+            };
+            column.set_cell_alignment(match align {
+                Alignment::Left => CellAlignment::Left,
+                Alignment::Center => CellAlignment::Center,
+                Alignment::Right => CellAlignment::Right,
+            });
+        }
+    }
+
+    /// Draw `table` with these borders
+    pub fn apply(&self, table: &mut Table) {
+        table.load_preset(match self.separators {
+            Separators::Full => UTF8_FULL,
+            Separators::Horizontal => UTF8_HORIZONTAL_ONLY,
+            Separators::None => UTF8_BORDERS_ONLY,
+        });
+        // a table without a left and right border has no corners to round
+        if self.corners == Corners::Round && self.separators != Separators::Horizontal {
+            table.apply_modifier(UTF8_ROUND_CORNERS);
+        }
+    }
+
+    /// Number of departure lists to draw side by side on a board `width` characters
+    /// wide, as many as fit and are filled with `departures` of `rows` each
+    pub fn columns(&self, width: u16, departures: usize, rows: usize) -> usize {
+        let fitting = (width / MIN_COLUMN_WIDTH) as usize;
+        let filled = departures.div_ceil(rows.max(1));
+        self.columns
+            .unwrap_or(MAX_COLUMNS)
+            .min(fitting)
+            .min(filled)
+            .max(1)
+    }
+
+    /// Number of rows fitting a table `height` lines high, with room for a second line per row
+    pub fn rows(&self, height: u16) -> usize {
+        let content_height = height.saturating_sub(5) as usize;
+        match self.separators {
+            Separators::Full | Separators::Horizontal => content_height / 3,
+            Separators::None => content_height / 2,
+        }
+    }
+}
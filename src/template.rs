@@ -0,0 +1,44 @@
+use std::cell::RefCell;
+
+/// Fill the `{name}` placeholders of `template` with the values `lookup` returns
+///
+/// `{{` and `}}` stand for literal braces, unknown placeholders are kept as they are.
+pub fn render(template: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            output.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        match rest[1..].find('}').filter(|_| rest.starts_with('{')) {
+            Some(end) => {
+                let placeholder = &rest[..end + 2];
+                match lookup(&placeholder[1..end + 1]) {
+                    Some(value) => output.push_str(&value),
+                    None => output.push_str(placeholder),
+                }
+                rest = &rest[end + 2..];
+            }
+            None => {
+                output.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Names of the placeholders in `template`, in the order they appear
+pub fn placeholders(template: &str) -> Vec<String> {
+    let names = RefCell::new(vec![]);
+    render(template, |name| {
+        names.borrow_mut().push(name.to_string());
+        None
+    });
+    names.into_inner()
+}
@@ -0,0 +1,290 @@
+use clap::ValueEnum;
+use comfy_table::Color;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::{
+    table_style::{Corners, Separators, TableStyle},
+    Line, WienerLinienTrafficInfo, WienerLinienVehicleType,
+};
+
+/// Departures at least this many minutes late get the delay color
+pub const DELAY_HIGHLIGHT: i64 = 2;
+
+/// Set of colors the board is drawn with, selected with `--palette`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    /// Official line colors
+    #[default]
+    Default,
+    /// Bright basic colors only, white lines
+    HighContrast,
+    /// Okabe-Ito colors, distinguishable with red-green color blindness
+    Deuteranopia,
+    /// Amber text with boxed line names, like the displays at Wiener Linien stations
+    WienerLinien,
+}
+
+/// Palette and table style together, selected with `--theme`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+pub enum ThemePreset {
+    /// Looks like the displays at Wiener Linien stations
+    #[serde(rename = "wl")]
+    #[value(name = "wl")]
+    WienerLinien,
+}
+
+impl ThemePreset {
+    /// Palette of the preset
+    pub fn palette(self) -> Palette {
+        match self {
+            ThemePreset::WienerLinien => Palette::WienerLinien,
+        }
+    }
+
+    /// Set the borders of `table` the preset draws with, keeping the rest of it
+    pub fn apply(self, table: &mut TableStyle) {
+        match self {
+            ThemePreset::WienerLinien => {
+                // the station displays only have a frame
+                table.separators = Separators::None;
+                table.corners = Corners::Square;
+                table.zebra = false;
+            }
+        }
+    }
+}
+
+/// How severe a disruption is, from the priority the API reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Major,
+    Minor,
+}
+
+impl Severity {
+    pub fn of(info: &WienerLinienTrafficInfo) -> Self {
+        match info.priority.as_deref() {
+            Some("1") => Severity::Major,
+            _ => Severity::Minor,
+        }
+    }
+}
+
+/// Colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorSupport {
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Guess from the environment, honoring `NO_COLOR` (see <https://no-color.org>)
+    pub fn detect() -> Self {
+        if no_color() {
+            return ColorSupport::None;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        if matches!(
+            env::var("COLORTERM").as_deref(),
+            Ok("truecolor") | Ok("24bit")
+        ) {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Ansi256
+        } else if term.is_empty() || term == "dumb" {
+            ColorSupport::None
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+
+    /// The closest color the terminal can show, `None` without color support
+    fn degrade(self, color: Color) -> Option<Color> {
+        match (self, color) {
+            (ColorSupport::None, _) => None,
+            (ColorSupport::TrueColor, color) => Some(color),
+            (ColorSupport::Ansi256, Color::Rgb { r, g, b }) => Some(Color::AnsiValue(
+                16 + 36 * cube_level(r) + 6 * cube_level(g) + cube_level(b),
+            )),
+            (ColorSupport::Ansi16, Color::Rgb { r, g, b }) => Some(nearest_basic(r, g, b)),
+            (_, color) => Some(color),
+        }
+    }
+}
+
+/// Whether `NO_COLOR` is set to something non-empty
+pub fn no_color() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Index of a color component on the 6 level axis of the 256 color cube
+fn cube_level(component: u8) -> u8 {
+    ((component as u16 * 5 + 127) / 255) as u8
+}
+
+/// The basic terminal color closest to an RGB color
+fn nearest_basic(r: u8, g: u8, b: u8) -> Color {
+    const BASIC: &[(Color, (u8, u8, u8))] = &[
+        (Color::Black, (0, 0, 0)),
+        (Color::DarkRed, (128, 0, 0)),
+        (Color::DarkGreen, (0, 128, 0)),
+        (Color::DarkYellow, (128, 128, 0)),
+        (Color::DarkBlue, (0, 0, 128)),
+        (Color::DarkMagenta, (128, 0, 128)),
+        (Color::DarkCyan, (0, 128, 128)),
+        (Color::Grey, (192, 192, 192)),
+        (Color::DarkGrey, (128, 128, 128)),
+        (Color::Red, (255, 0, 0)),
+        (Color::Green, (0, 255, 0)),
+        (Color::Yellow, (255, 255, 0)),
+        (Color::Blue, (0, 0, 255)),
+        (Color::Magenta, (255, 0, 255)),
+        (Color::Cyan, (0, 255, 255)),
+        (Color::White, (255, 255, 255)),
+    ];
+    let distance = |(br, bg, bb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, br) + d(g, bg) + d(b, bb)
+    };
+    BASIC
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map_or(Color::White, |(color, _)| *color)
+}
+
+// Okabe-Ito palette
+const VERMILLION: Color = rgb(0xd5, 0x5e, 0x00);
+const ORANGE: Color = rgb(0xe6, 0x9f, 0x00);
+const SKY_BLUE: Color = rgb(0x56, 0xb4, 0xe9);
+const BLUISH_GREEN: Color = rgb(0x00, 0x9e, 0x73);
+const YELLOW: Color = rgb(0xf0, 0xe4, 0x42);
+const BLUE: Color = rgb(0x00, 0x72, 0xb2);
+const REDDISH_PURPLE: Color = rgb(0xcc, 0x79, 0xa7);
+
+// LED color of the Wiener Linien station displays
+const AMBER: Color = rgb(0xff, 0xb0, 0x00);
+
+/// Colors used on the board, the one place deciding what gets which color
+///
+/// Colors are reduced to what the terminal supports, or left out entirely.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub palette: Palette,
+    pub support: ColorSupport,
+}
+
+impl Theme {
+    pub fn new(palette: Palette, support: ColorSupport) -> Self {
+        Theme { palette, support }
+    }
+
+    /// Color of a line's name, a color configured for the line wins over the palette
+    pub fn line(&self, line: &Line) -> Option<Color> {
+        if let Some([r, g, b]) = line.color {
+            return self.support.degrade(rgb(r, g, b));
+        }
+        let color = match self.palette {
+            Palette::Default => match (&line.vehicle_type, line.name.as_str()) {
+                (WienerLinienVehicleType::Metro, "U1") => rgb(0xe3, 0x00, 0x0f),
+                (WienerLinienVehicleType::Metro, "U2") => rgb(0xa7, 0x62, 0xa4),
+                (WienerLinienVehicleType::Metro, "U3") => rgb(0xee, 0x7d, 0x00),
+                (WienerLinienVehicleType::Metro, "U4") => rgb(0x00, 0x96, 0x40),
+                (WienerLinienVehicleType::Metro, "U5") => rgb(0x00, 0x97, 0x8f),
+                (WienerLinienVehicleType::Metro, "U6") => rgb(0x9d, 0x6b, 0x2f),
+                (WienerLinienVehicleType::Metro, _) => return None,
+                (WienerLinienVehicleType::Tram, _) => rgb(0xe3, 0x00, 0x0f),
+                (WienerLinienVehicleType::CityBus, _) => rgb(0x00, 0x6a, 0xb3),
+                (WienerLinienVehicleType::NightBus, _) => rgb(0xff, 0xd5, 0x00),
+                (WienerLinienVehicleType::Train, _) => rgb(0xe2, 0x00, 0x2a),
+            },
+            Palette::HighContrast => Color::White,
+            Palette::Deuteranopia => match (&line.vehicle_type, line.name.as_str()) {
+                (WienerLinienVehicleType::Metro, "U1") => VERMILLION,
+                (WienerLinienVehicleType::Metro, "U2") => REDDISH_PURPLE,
+                (WienerLinienVehicleType::Metro, "U3") => ORANGE,
+                (WienerLinienVehicleType::Metro, "U4") => BLUISH_GREEN,
+                (WienerLinienVehicleType::Metro, "U5") => SKY_BLUE,
+                (WienerLinienVehicleType::Metro, "U6") => YELLOW,
+                (WienerLinienVehicleType::Metro, _) => return None,
+                (WienerLinienVehicleType::Tram, _) => VERMILLION,
+                (WienerLinienVehicleType::CityBus, _) => BLUE,
+                (WienerLinienVehicleType::NightBus, _) => SKY_BLUE,
+                (WienerLinienVehicleType::Train, _) => REDDISH_PURPLE,
+            },
+            Palette::WienerLinien => AMBER,
+        };
+        self.support.degrade(color)
+    }
+
+    /// Color of text without a color of its own, the terminal's if `None`
+    pub fn text(&self) -> Option<Color> {
+        match self.palette {
+            Palette::WienerLinien => self.support.degrade(AMBER),
+            _ => None,
+        }
+    }
+
+    /// Whether line names are drawn as boxes filled with their color
+    pub fn line_boxes(&self) -> bool {
+        self.palette == Palette::WienerLinien && self.support != ColorSupport::None
+    }
+
+    /// Color of the departure time of a delayed departure
+    pub fn delay(&self) -> Option<Color> {
+        self.support.degrade(match self.palette {
+            Palette::Default => Color::Yellow,
+            Palette::HighContrast => Color::Yellow,
+            Palette::Deuteranopia => ORANGE,
+            Palette::WienerLinien => Color::White,
+        })
+    }
+
+    /// Color of the departure time of a departure leaving earlier than planned
+    pub fn early(&self) -> Option<Color> {
+        self.support.degrade(match self.palette {
+            Palette::Default => Color::Magenta,
+            Palette::HighContrast => Color::Cyan,
+            Palette::Deuteranopia => SKY_BLUE,
+            Palette::WienerLinien => Color::Cyan,
+        })
+    }
+
+    /// Background of every other departure row with zebra striping
+    pub fn stripe(&self) -> Option<Color> {
+        match self.support {
+            // the dark grey would come out black, like the background
+            ColorSupport::Ansi16 => Some(Color::DarkGrey),
+            support => support.degrade(rgb(0x30, 0x30, 0x30)),
+        }
+    }
+
+    /// Color of the clock shown during quiet hours
+    pub fn quiet(&self) -> Option<Color> {
+        self.support.degrade(match self.palette {
+            Palette::HighContrast => Color::Grey,
+            Palette::Default | Palette::Deuteranopia | Palette::WienerLinien => Color::DarkGrey,
+        })
+    }
+
+    /// Color of a disruption's title
+    pub fn disruption(&self, severity: Severity) -> Option<Color> {
+        self.support.degrade(match (self.palette, severity) {
+            (Palette::Default, Severity::Major) => Color::Red,
+            (Palette::Default, Severity::Minor) => Color::Yellow,
+            (Palette::HighContrast, Severity::Major) => Color::Red,
+            (Palette::HighContrast, Severity::Minor) => Color::White,
+            (Palette::Deuteranopia, Severity::Major) => VERMILLION,
+            (Palette::Deuteranopia, Severity::Minor) => SKY_BLUE,
+            (Palette::WienerLinien, Severity::Major) => Color::Red,
+            (Palette::WienerLinien, Severity::Minor) => AMBER,
+        })
+    }
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
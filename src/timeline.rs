@@ -0,0 +1,76 @@
+use chrono::{DateTime, Duration, Local};
+use crossterm::style::Stylize;
+
+use crate::{theme::Theme, Departure};
+
+/// Minutes ahead the timeline shows
+const HORIZON: i64 = 60;
+
+/// Minutes between the times on the axis
+const TICK: i64 = 15;
+
+/// Departures of the next hour as marks on a timeline per line and destination,
+/// so gaps in the service stand out
+///
+/// The first line is the axis with the time every quarter hour, each further
+/// line belongs to one line and destination in the order of their next departure.
+pub fn render(
+    departures: &[&Departure],
+    now: &DateTime<Local>,
+    width: u16,
+    height: u16,
+    theme: &Theme,
+) -> String {
+    let mut rows: Vec<(String, Vec<&Departure>)> = vec![];
+    for dep in departures.iter().filter(|dep| dep.countdown < HORIZON) {
+        let label = format!("{} {}", dep.line.display_name(), dep.destination_name);
+        match rows.iter_mut().find(|(existing, _)| *existing == label) {
+            Some((_, deps)) => deps.push(dep),
+            None => rows.push((label, vec![dep])),
+        }
+    }
+
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(width as usize / 3);
+    let track_width = (width as usize).saturating_sub(label_width + 1).max(1);
+    let column = |minutes: i64| minutes.max(0) as usize * track_width / HORIZON as usize;
+
+    let mut axis = vec![' '; track_width];
+    for tick in (0..HORIZON).step_by(TICK as usize) {
+        let time = (*now + Duration::minutes(tick)).format("%H:%M").to_string();
+        for (offset, c) in time.chars().enumerate() {
+            if let Some(slot) = axis.get_mut(column(tick) + offset) {
+                *slot = c;
+            }
+        }
+    }
+    let mut lines = vec![format!(
+        "{} {}",
+        " ".repeat(label_width),
+        axis.into_iter().collect::<String>()
+    )];
+
+    for (label, deps) in rows.iter().take((height as usize).saturating_sub(1)) {
+        let mut track = vec![false; track_width];
+        for dep in deps {
+            if let Some(slot) = track.get_mut(column(dep.countdown)) {
+                *slot = true;
+            }
+        }
+        let mark = match theme.line(&deps[0].line) {
+            Some(color) => "█".with(color).to_string(),
+            None => "█".to_string(),
+        };
+        let track: String = track
+            .into_iter()
+            .map(|departs| if departs { mark.as_str() } else { "·" })
+            .collect();
+        let label: String = label.chars().take(label_width).collect();
+        lines.push(format!("{:<label_width$} {}", label, track));
+    }
+    lines.join("\n")
+}
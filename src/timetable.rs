@@ -0,0 +1,363 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    time::Instant,
+};
+
+/// How far ahead departures are looked up, in minutes
+const HORIZON: i64 = 90;
+
+/// Columns of calendar.txt, from Monday on
+const WEEKDAY_COLUMNS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Static timetable of the monitored stops, read from an extracted GTFS feed
+///
+/// Stops of the feed are matched to the configured stop IDs by their
+/// `stop_code`, or by their `stop_id` if it is a number.
+#[derive(Debug)]
+pub struct Timetable {
+    stops: HashMap<i32, String>,
+    trips: Vec<Trip>,
+    stop_times: Vec<StopTime>,
+    services: HashMap<String, Service>,
+}
+
+#[derive(Debug)]
+struct Trip {
+    line: String,
+    /// GTFS route type, 0 tram, 1 subway, 2 rail, 3 bus
+    route_type: u16,
+    headsign: String,
+    service: String,
+}
+
+#[derive(Debug)]
+struct StopTime {
+    stop: i32,
+    trip: usize,
+    /// Seconds after midnight of the service day, past 24 hours for trips running into the next day
+    departure: i64,
+}
+
+#[derive(Debug, Default)]
+struct Service {
+    weekdays: [bool; 7],
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    added: HashSet<NaiveDate>,
+    removed: HashSet<NaiveDate>,
+}
+
+impl Service {
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        if self.added.contains(&date) {
+            return true;
+        }
+        !self.removed.contains(&date)
+            && self.weekdays[date.weekday().num_days_from_monday() as usize]
+            && self.start.is_some_and(|start| start <= date)
+            && self.end.is_some_and(|end| date <= end)
+    }
+}
+
+impl Timetable {
+    /// Read the departures of `stops` from the GTFS feed in `dir`
+    pub fn load(dir: &Path, stops: &[i32]) -> Result<Self> {
+        let mut stop_ids = HashMap::new();
+        let mut stop_names = HashMap::new();
+        read_csv(&dir.join("stops.txt"), true, |row| {
+            let stop = row
+                .get("stop_code")
+                .and_then(|code| code.parse().ok())
+                .or_else(|| row.get("stop_id")?.parse().ok())
+                .filter(|stop| stops.contains(stop));
+            if let (Some(stop), Some(id)) = (stop, row.get("stop_id")) {
+                stop_ids.insert(id.to_string(), stop);
+                stop_names.insert(stop, row.get("stop_name").unwrap_or_default().to_string());
+            }
+        })?;
+        if stop_ids.is_empty() {
+            bail!("None of the stops is in the timetable");
+        }
+
+        let mut routes = HashMap::new();
+        read_csv(&dir.join("routes.txt"), true, |row| {
+            if let Some(id) = row.get("route_id") {
+                let name = row
+                    .get("route_short_name")
+                    .filter(|name| !name.is_empty())
+                    .or_else(|| row.get("route_long_name"))
+                    .unwrap_or(id);
+                let route_type = row.get("route_type").and_then(|t| t.parse().ok());
+                routes.insert(id.to_string(), (name.to_string(), route_type.unwrap_or(3)));
+            }
+        })?;
+
+        let mut trips = vec![];
+        let mut trip_ids = HashMap::new();
+        read_csv(&dir.join("trips.txt"), true, |row| {
+            let route = row.get("route_id").and_then(|id| routes.get(id));
+            if let (Some(id), Some((line, route_type))) = (row.get("trip_id"), route) {
+                trip_ids.insert(id.to_string(), trips.len());
+                trips.push(Trip {
+                    line: line.clone(),
+                    route_type: *route_type,
+                    headsign: row.get("trip_headsign").unwrap_or_default().to_string(),
+                    service: row.get("service_id").unwrap_or_default().to_string(),
+                });
+            }
+        })?;
+
+        let mut stop_times = vec![];
+        read_csv(&dir.join("stop_times.txt"), true, |row| {
+            let stop = row.get("stop_id").and_then(|id| stop_ids.get(id));
+            let trip = row.get("trip_id").and_then(|id| trip_ids.get(id));
+            let departure = row
+                .get("departure_time")
+                .or_else(|| row.get("arrival_time"))
+                .and_then(seconds_of_day);
+            if let (Some(stop), Some(trip), Some(departure)) = (stop, trip, departure) {
+                stop_times.push(StopTime {
+                    stop: *stop,
+                    trip: *trip,
+                    departure,
+                });
+            }
+        })?;
+
+        // feeds need at least one of the calendar files
+        let mut services: HashMap<String, Service> = HashMap::new();
+        read_csv(&dir.join("calendar.txt"), false, |row| {
+            let Some(id) = row.get("service_id") else {
+                return;
+            };
+            let service = services.entry(id.to_string()).or_default();
+            for (day, column) in WEEKDAY_COLUMNS.iter().enumerate() {
+                service.weekdays[day] = row.get(column) == Some("1");
+            }
+            service.start = row.get("start_date").and_then(date);
+            service.end = row.get("end_date").and_then(date);
+        })?;
+        read_csv(&dir.join("calendar_dates.txt"), false, |row| {
+            let (Some(id), Some(day)) = (row.get("service_id"), row.get("date").and_then(date))
+            else {
+                return;
+            };
+            let service = services.entry(id.to_string()).or_default();
+            match row.get("exception_type") {
+                Some("1") => service.added.insert(day),
+                Some("2") => service.removed.insert(day),
+                _ => false,
+            };
+        })?;
+
+        Ok(Timetable {
+            stops: stop_names,
+            trips,
+            stop_times,
+            services,
+        })
+    }
+
+    /// A Wiener Linien monitor response with the departures of `stops` at `now`
+    pub fn response(&self, stops: &[i32], now: NaiveDateTime) -> String {
+        // line, destination and route type per stop, each with its departure times
+        let mut lines: BTreeMap<(i32, &str, &str, u16), Vec<NaiveDateTime>> = BTreeMap::new();
+        for day in [now.date() - Duration::days(1), now.date()] {
+            let midnight = day.and_hms_opt(0, 0, 0).unwrap_or(now);
+            for stop_time in self.stop_times.iter().filter(|s| stops.contains(&s.stop)) {
+                let trip = &self.trips[stop_time.trip];
+                let time = midnight + Duration::seconds(stop_time.departure);
+                if time < now
+                    || time > now + Duration::minutes(HORIZON)
+                    || !self
+                        .services
+                        .get(&trip.service)
+                        .is_some_and(|s| s.runs_on(day))
+                {
+                    continue;
+                }
+                lines
+                    .entry((stop_time.stop, &trip.line, &trip.headsign, trip.route_type))
+                    .or_default()
+                    .push(time);
+            }
+        }
+
+        let monitors: Vec<_> = stops
+            .iter()
+            .filter_map(|stop| Some((stop, self.stops.get(stop)?)))
+            .map(|(stop, name)| {
+                let stop_lines: Vec<_> = lines
+                    .iter_mut()
+                    .filter(|((line_stop, ..), _)| line_stop == stop)
+                    .map(|((_, line, headsign, route_type), times)| {
+                        times.sort();
+                        json!({
+                            "name": line,
+                            "towards": headsign,
+                            "type": vehicle_type(line, *route_type),
+                            "departures": {
+                                "departure": times.iter().map(|time| json!({
+                                    "departureTime": {
+                                        "timePlanned": timestamp(*time),
+                                        "countdown": (*time - now).num_minutes(),
+                                    }
+                                })).collect::<Vec<_>>()
+                            },
+                        })
+                    })
+                    .collect();
+                json!({
+                    "locationStop": {
+                        "properties": { "title": name, "attributes": { "rbl": stop } }
+                    },
+                    "lines": stop_lines,
+                })
+            })
+            .collect();
+
+        json!({
+            "data": { "monitors": monitors },
+            "message": { "value": "OK", "messageCode": 1 },
+        })
+        .to_string()
+    }
+}
+
+/// The timetable shown as of a time set with `--at`, which then passes as usual
+#[derive(Debug)]
+pub struct TimeTravel {
+    pub timetable: Timetable,
+    at: NaiveDateTime,
+    started: Instant,
+}
+
+impl TimeTravel {
+    pub fn new(timetable: Timetable, at: NaiveDateTime) -> Self {
+        TimeTravel {
+            timetable,
+            at,
+            started: Instant::now(),
+        }
+    }
+
+    /// The time the board shows
+    pub fn now(&self) -> DateTime<Local> {
+        let at = Local
+            .from_local_datetime(&self.at)
+            .earliest()
+            .unwrap_or_else(Local::now);
+        at + Duration::from_std(self.started.elapsed()).unwrap_or_else(|_| Duration::zero())
+    }
+}
+
+/// Vehicle type of the Wiener Linien API for a GTFS route type
+fn vehicle_type(line: &str, route_type: u16) -> &'static str {
+    match route_type {
+        0 | 900..=999 => "ptTram",
+        1 | 400..=499 => "ptMetro",
+        2 | 100..=199 => "ptTrain",
+        _ if line.starts_with('N') => "ptBusNight",
+        _ => "ptBusCity",
+    }
+}
+
+fn timestamp(time: NaiveDateTime) -> String {
+    match Local.from_local_datetime(&time).earliest() {
+        Some(time) => time.format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string(),
+        None => time.format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+    }
+}
+
+/// "HH:MM:SS" as seconds, hours may go past 24
+fn seconds_of_day(time: &str) -> Option<i64> {
+    let mut parts = time.trim().splitn(3, ':').map(|part| part.parse::<i64>());
+    let (hours, minutes, seconds) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    Some(hours * 3600 + minutes * 60 + seconds)
+}
+
+/// "YYYYMMDD" as a date
+fn date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y%m%d").ok()
+}
+
+/// Row of a CSV file, its fields looked up by the column names of the header
+struct Row<'a> {
+    columns: &'a HashMap<String, usize>,
+    fields: Vec<String>,
+}
+
+impl Row<'_> {
+    fn get(&self, column: &str) -> Option<&str> {
+        self.columns
+            .get(column)
+            .and_then(|index| self.fields.get(*index))
+            .map(String::as_str)
+    }
+}
+
+/// Call `f` with every row of the CSV file at `path`, a missing file is only an error if it is `required`
+fn read_csv(path: &Path, required: bool, mut f: impl FnMut(&Row)) -> Result<()> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) if !required => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", path.display())),
+    };
+    let mut lines = BufReader::new(file).lines();
+    let header = match lines.next() {
+        Some(header) => header.with_context(|| format!("Failed to read {}", path.display()))?,
+        None => return Ok(()),
+    };
+    let columns: HashMap<String, usize> = split_csv(header.trim_start_matches('\u{feff}'))
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| (name.trim().to_string(), index))
+        .collect();
+    for line in lines {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        f(&Row {
+            columns: &columns,
+            fields: split_csv(&line),
+        });
+    }
+    Ok(())
+}
+
+/// Fields of a CSV line, quoted fields may contain commas and doubled quotes
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
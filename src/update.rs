@@ -0,0 +1,110 @@
+use anyhow::{bail, Context, Result};
+use reqwest::header::USER_AGENT;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{env, fs, os::unix::fs::PermissionsExt};
+
+/// Latest release, as the GitHub API reports it
+const RELEASE_URL: &str = "https://api.github.com/repos/mdartmann/oeffimonitor-cli/releases/latest";
+
+/// Release asset listing the SHA-256 checksum of every binary, as `sha256sum` writes it
+const CHECKSUMS: &str = "SHA256SUMS";
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Replace the running binary with the one of the latest GitHub release, if it is newer
+///
+/// The binary for this platform is the asset `oeffimonitor-cli-<arch>-<os>`,
+/// e.g. `oeffimonitor-cli-aarch64-linux`, and has to match its checksum in
+/// `SHA256SUMS`. With `check_only`, the newer version is only printed.
+pub async fn self_update(check_only: bool) -> Result<()> {
+    let release: Release = get(RELEASE_URL)
+        .await
+        .context("Failed to request the latest release")?
+        .json()
+        .await
+        .context("Failed to read the latest release")?;
+    let current = env!("CARGO_PKG_VERSION");
+    if version(&release.tag_name) <= version(current) {
+        println!("{} is the latest version", current);
+        return Ok(());
+    }
+    if check_only {
+        println!("{} is available, running {}", release.tag_name, current);
+        return Ok(());
+    }
+
+    let name = format!(
+        "{}-{}-{}",
+        env!("CARGO_PKG_NAME"),
+        env::consts::ARCH,
+        env::consts::OS
+    );
+    let asset = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .with_context(|| format!("{} has no {}", release.tag_name, name))
+    };
+    let checksums = get(&asset(CHECKSUMS)?.browser_download_url)
+        .await?
+        .text()
+        .await?;
+    let expected = checksums
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, file)| file.trim().trim_start_matches('*') == name)
+        .map(|(checksum, _)| checksum.to_lowercase())
+        .with_context(|| format!("{} lists no checksum of {}", CHECKSUMS, name))?;
+    let binary = get(&asset(&name)?.browser_download_url)
+        .await
+        .with_context(|| format!("Failed to download {}", name))?
+        .bytes()
+        .await?;
+    let actual: String = Sha256::digest(&binary)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    if actual != expected {
+        bail!("Checksum of {} doesn't match, not updating", name);
+    }
+
+    // written next to the binary, so renaming replaces it at once
+    let path = env::current_exe().context("Failed to find the running binary")?;
+    let new = path.with_extension("new");
+    fs::write(&new, &binary).with_context(|| format!("Failed to write {}", new.display()))?;
+    fs::set_permissions(&new, fs::Permissions::from_mode(0o755))?;
+    fs::rename(&new, &path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    println!("Updated from {} to {}", current, release.tag_name);
+    Ok(())
+}
+
+async fn get(url: &str) -> Result<reqwest::Response> {
+    // the GitHub API refuses requests without a user agent
+    Ok(reqwest::Client::new()
+        .get(url)
+        .header(USER_AGENT, env!("CARGO_PKG_NAME"))
+        .send()
+        .await?
+        .error_for_status()?)
+}
+
+/// Numbers of a version like "v1.2.3", for comparing
+fn version(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration};
+
+use crate::{Departure, WienerLinienVehicleType};
+
+/// What a view puts on the board
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewKind {
+    #[default]
+    Departures,
+    Disruptions,
+    /// Connections of the configured `[journey]`
+    Journey,
+}
+
+/// A named board, e.g. "Trams" showing only tram departures
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct View {
+    pub name: String,
+    #[serde(default)]
+    pub kind: ViewKind,
+    /// Vehicle types shown on a departures view, all if empty
+    #[serde(default)]
+    pub vehicle_types: Vec<WienerLinienVehicleType>,
+    /// Stops whose departures this view shows, the global `stops` if empty
+    #[serde(default)]
+    pub stops: Vec<i32>,
+    /// Seconds between API requests for this view, overrides the global `refresh`
+    ///
+    /// Views showing the same stops share their requests, made as often as the
+    /// most frequently refreshed of them asks for.
+    pub refresh: Option<u64>,
+}
+
+impl View {
+    /// Whether a departure belongs on this view
+    pub fn shows(&self, dep: &Departure) -> bool {
+        self.vehicle_types.is_empty() || self.vehicle_types.contains(&dep.line.vehicle_type)
+    }
+}
+
+/// Views cycled through on a single screen, switching every `interval` seconds
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Carousel {
+    pub interval: u64,
+    pub views: Vec<View>,
+}
+
+impl Default for Carousel {
+    fn default() -> Self {
+        Carousel {
+            interval: 10,
+            views: vec![],
+        }
+    }
+}
+
+impl Carousel {
+    /// The view to show after `elapsed` time, `None` if carousel mode is off
+    pub fn current(&self, elapsed: Duration) -> Option<PageIndicator<'_>> {
+        if self.views.is_empty() {
+            return None;
+        }
+        let index = (elapsed.as_secs() / self.interval.max(1)) as usize % self.views.len();
+        Some(PageIndicator {
+            view: &self.views[index],
+            index,
+            count: self.views.len(),
+        })
+    }
+}
+
+/// Position of the current view in carousel mode, shown in the footer
+pub struct PageIndicator<'a> {
+    pub view: &'a View,
+    pub index: usize,
+    pub count: usize,
+}
+
+impl fmt::Display for PageIndicator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.view.name)?;
+        for i in 0..self.count {
+            f.write_str(if i == self.index { "●" } else { "○" })?;
+        }
+        Ok(())
+    }
+}
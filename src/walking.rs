@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::Departure;
+
+/// Walking times to the monitored stops, shown as when to leave for a departure
+#[derive(Debug, Clone, Default, Hash, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WalkingSettings {
+    /// Minutes it takes to walk to a stop, keyed by stop ID
+    pub stops: BTreeMap<String, i64>,
+    /// Order departures by when to leave instead of when they depart
+    pub sort: bool,
+}
+
+impl WalkingSettings {
+    /// Whether any walking time is configured, i.e. the board gets a "Leave in" column
+    pub fn is_enabled(&self) -> bool {
+        !self.stops.is_empty()
+    }
+
+    /// Minutes until one has to leave to catch `dep`, `None` without a walking time for its stop
+    pub fn leave_in(&self, dep: &Departure) -> Option<i64> {
+        let minutes = self.stops.get(&dep.stop_id?.to_string())?;
+        Some(dep.countdown - minutes)
+    }
+}
@@ -0,0 +1,401 @@
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+use crate::pixel::{text_width, Canvas, GLYPH_HEIGHT};
+
+/// Open-Meteo forecast API, free and without a key
+const WEATHER_URL: &str = "https://api.open-meteo.com/v1/forecast";
+/// How often the weather is requested
+const WEATHER_REFRESH: Duration = Duration::from_secs(15 * 60);
+
+/// Board composed of widgets in rows and columns, instead of the single departure table
+#[derive(Debug, Clone, Hash, Deserialize, Serialize)]
+pub struct Layout {
+    pub rows: Vec<LayoutRow>,
+}
+
+/// Layouts selectable with `--layout` instead of configuring one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LayoutPreset {
+    /// For monitors mounted upright: a big clock above the departures, disruptions below them
+    Portrait,
+}
+
+impl LayoutPreset {
+    pub fn layout(self) -> Layout {
+        let row = |weight, widget| LayoutRow {
+            weight,
+            columns: vec![LayoutCell { weight: 1, widget }],
+        };
+        match self {
+            LayoutPreset::Portrait => Layout {
+                rows: vec![
+                    row(1, Widget::BigClock),
+                    row(5, Widget::Departures),
+                    row(2, Widget::Disruptions),
+                ],
+            },
+        }
+    }
+}
+
+/// Row of the layout, splitting the height with the other rows by weight
+#[derive(Debug, Clone, Hash, Deserialize, Serialize)]
+pub struct LayoutRow {
+    #[serde(default = "default_weight")]
+    pub weight: u16,
+    pub columns: Vec<LayoutCell>,
+}
+
+/// Widget in a row, splitting the width with the other columns by weight
+#[derive(Debug, Clone, Hash, Deserialize, Serialize)]
+pub struct LayoutCell {
+    #[serde(default = "default_weight")]
+    pub weight: u16,
+    #[serde(flatten)]
+    pub widget: Widget,
+}
+
+fn default_weight() -> u16 {
+    1
+}
+
+/// Something to show in a cell of the layout, selected by `type`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Widget {
+    /// The departure board of the current view
+    Departures,
+    /// All current disruptions
+    Disruptions,
+    /// Time, and the date if configured in `[clock]`
+    Clock,
+    /// Hours and minutes as large as the cell allows, drawn with braille characters
+    #[serde(rename = "big_clock")]
+    BigClock,
+    /// Current temperature and conditions at a place
+    Weather { latitude: f64, longitude: f64 },
+    /// Text given inline, or read from a file or URL every `refresh` seconds,
+    /// e.g. house announcements
+    Text {
+        #[serde(default)]
+        text: String,
+        file: Option<PathBuf>,
+        url: Option<String>,
+        #[serde(default = "default_text_refresh")]
+        refresh: u64,
+    },
+}
+
+fn default_text_refresh() -> u64 {
+    300
+}
+
+impl Hash for Widget {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Widget::Weather {
+                latitude,
+                longitude,
+            } => (latitude.to_bits(), longitude.to_bits()).hash(state),
+            Widget::Text {
+                text,
+                file,
+                url,
+                refresh,
+            } => (text, file, url, refresh).hash(state),
+            Widget::Departures | Widget::Disruptions | Widget::Clock | Widget::BigClock => {}
+        }
+    }
+}
+
+impl Layout {
+    /// All widgets, in the order their indices are counted in
+    pub fn widgets(&self) -> impl Iterator<Item = &Widget> {
+        self.rows
+            .iter()
+            .flat_map(|row| row.columns.iter().map(|cell| &cell.widget))
+    }
+
+    /// Compose the widgets into `height` lines of `width` characters
+    ///
+    /// `render` draws the widget with the given index into an area of the given width and height.
+    pub fn render<E>(
+        &self,
+        width: u16,
+        height: u16,
+        mut render: impl FnMut(usize, &Widget, u16, u16) -> Result<String, E>,
+    ) -> Result<String, E> {
+        let mut lines = vec![];
+        let mut index = 0;
+        let heights = split(height, self.rows.iter().map(|row| row.weight));
+        for (row, row_height) in self.rows.iter().zip(heights) {
+            let widths = split(width, row.columns.iter().map(|cell| cell.weight));
+            let mut row_lines = vec![String::new(); row_height as usize];
+            for (cell, cell_width) in row.columns.iter().zip(widths) {
+                let content = render(index, &cell.widget, cell_width, row_height)?;
+                let mut content_lines = content.lines();
+                for line in row_lines.iter_mut() {
+                    line.push_str(&fit(content_lines.next().unwrap_or_default(), cell_width));
+                }
+                index += 1;
+            }
+            lines.extend(row_lines);
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+/// `now` as hours and minutes in the largest pixel font fitting `width` by `height` characters, centered
+pub fn big_clock(now: &DateTime<Local>, width: u16, height: u16) -> String {
+    let time = now.format("%H:%M").to_string();
+    // braille characters hold 2x4 pixels
+    let (pixels_wide, pixels_high) = (width as usize * 2, height as usize * 4);
+    let scale = (pixels_wide / (text_width(&time) + 1))
+        .min(pixels_high / GLYPH_HEIGHT)
+        .max(1);
+    let mut canvas = Canvas::new(pixels_wide, pixels_high);
+    canvas.text_scaled(
+        pixels_wide.saturating_sub(text_width(&time) * scale) / 2,
+        pixels_high.saturating_sub(GLYPH_HEIGHT * scale) / 2,
+        &time,
+        scale,
+    );
+    canvas.to_braille()
+}
+
+/// Split `total` into parts proportional to `weights`, the last part gets the rounding rest
+fn split(total: u16, weights: impl Iterator<Item = u16>) -> Vec<u16> {
+    let weights: Vec<u32> = weights.map(u32::from).collect();
+    let sum = weights.iter().sum::<u32>().max(1);
+    let mut parts: Vec<u16> = weights
+        .iter()
+        .map(|weight| (total as u32 * weight / sum) as u16)
+        .collect();
+    let rest = total - parts.iter().sum::<u16>();
+    if let Some(last) = parts.last_mut() {
+        *last += rest;
+    }
+    parts
+}
+
+/// `count` areas next to each other, splitting `width` evenly
+///
+/// `render` draws the area with the given index and width.
+pub fn side_by_side<E>(
+    width: u16,
+    count: usize,
+    mut render: impl FnMut(usize, u16) -> Result<String, E>,
+) -> Result<String, E> {
+    let widths = split(width, std::iter::repeat_n(1, count));
+    let mut columns = vec![];
+    for (index, width) in widths.iter().enumerate() {
+        columns.push(render(index, *width)?);
+    }
+    let height = columns.iter().map(|column| column.lines().count()).max();
+    Ok((0..height.unwrap_or_default())
+        .map(|y| {
+            columns
+                .iter()
+                .zip(&widths)
+                .map(|(column, width)| fit(column.lines().nth(y).unwrap_or_default(), *width))
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Put `corner` to the right of the last lines of `content`, which are cut or padded to `width`
+pub fn corner(content: &str, corner: &[String], width: u16) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.len().saturating_sub(corner.len());
+    lines
+        .iter()
+        .enumerate()
+        .map(
+            |(y, line)| match y.checked_sub(start).and_then(|i| corner.get(i)) {
+                Some(corner) => fit(line, width) + corner.as_str(),
+                None => line.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Pad or cut `line` to `width` visible characters, skipping over ANSI escape sequences
+fn fit(line: &str, width: u16) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut visible = 0;
+    let mut styled = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // copy the sequence up to its final letter, it takes no space
+            output.push(c);
+            for c in chars.by_ref() {
+                output.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            styled = true;
+            continue;
+        }
+        if visible == width {
+            break;
+        }
+        output.push(c);
+        visible += 1;
+    }
+    if styled {
+        output.push_str("\x1b[0m");
+    }
+    output.push_str(&" ".repeat((width - visible) as usize));
+    output
+}
+
+/// Text of widgets that fetch their content in the background, by widget index
+#[derive(Default)]
+pub struct WidgetFeeds {
+    feeds: Vec<Option<Feed>>,
+}
+
+struct Feed {
+    text: Arc<Mutex<Option<String>>>,
+    task: JoinHandle<()>,
+}
+
+impl WidgetFeeds {
+    /// Start fetching for the widgets of `layout` that need it
+    pub fn new(layout: Option<&Layout>) -> Self {
+        let feeds = layout
+            .into_iter()
+            .flat_map(Layout::widgets)
+            .map(|widget| match widget {
+                &Widget::Weather {
+                    latitude,
+                    longitude,
+                } => Some(Feed::spawn(WEATHER_REFRESH, move || {
+                    weather(latitude, longitude)
+                })),
+                Widget::Text {
+                    file: Some(file),
+                    refresh,
+                    ..
+                } => {
+                    let file = file.clone();
+                    Some(Feed::spawn(Duration::from_secs(*refresh), move || {
+                        read_file(file.clone())
+                    }))
+                }
+                Widget::Text {
+                    url: Some(url),
+                    refresh,
+                    ..
+                } => {
+                    let url = url.clone();
+                    Some(Feed::spawn(Duration::from_secs(*refresh), move || {
+                        fetch_text(url.clone())
+                    }))
+                }
+                _ => None,
+            })
+            .collect();
+        WidgetFeeds { feeds }
+    }
+
+    /// Latest text of every widget, `None` for widgets without a feed or before it arrived
+    pub fn texts(&self) -> Vec<Option<String>> {
+        self.feeds
+            .iter()
+            .map(|feed| {
+                feed.as_ref()
+                    .and_then(|feed| feed.text.lock().ok()?.clone())
+            })
+            .collect()
+    }
+}
+
+impl Feed {
+    fn spawn<F>(interval: Duration, fetch: impl Fn() -> F + Send + 'static) -> Self
+    where
+        F: std::future::Future<Output = anyhow::Result<String>> + Send,
+    {
+        let text = Arc::new(Mutex::new(None));
+        let shared = text.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                // a failed refresh keeps showing the previous text
+                if let Ok(new_text) = fetch().await {
+                    if let Ok(mut text) = shared.lock() {
+                        *text = Some(new_text);
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+        Feed { text, task }
+    }
+}
+
+impl Drop for Feed {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[derive(Deserialize)]
+struct WeatherResponse {
+    current_weather: CurrentWeather,
+}
+
+#[derive(Deserialize)]
+struct CurrentWeather {
+    temperature: f64,
+    weathercode: u8,
+}
+
+async fn read_file(path: PathBuf) -> anyhow::Result<String> {
+    Ok(tokio::fs::read_to_string(path).await?)
+}
+
+async fn fetch_text(url: String) -> anyhow::Result<String> {
+    Ok(reqwest::get(url).await?.error_for_status()?.text().await?)
+}
+
+/// E.g. "12.5 °C, rain"
+async fn weather(latitude: f64, longitude: f64) -> anyhow::Result<String> {
+    let response: WeatherResponse = reqwest::Client::new()
+        .get(WEATHER_URL)
+        .query(&[
+            ("latitude", latitude.to_string()),
+            ("longitude", longitude.to_string()),
+            ("current_weather", "true".to_string()),
+        ])
+        .send()
+        .await?
+        .json()
+        .await?;
+    let weather = response.current_weather;
+    // WMO weather interpretation codes
+    let conditions = match weather.weathercode {
+        0 => "clear",
+        1..=3 => "cloudy",
+        45 | 48 => "fog",
+        51..=57 => "drizzle",
+        61..=67 | 80..=82 => "rain",
+        71..=77 | 85 | 86 => "snow",
+        95..=99 => "thunderstorm",
+        _ => "unknown",
+    };
+    Ok(format!("{:.1} °C, {}", weather.temperature, conditions))
+}
@@ -0,0 +1,120 @@
+use iso8601_timestamp::Timestamp;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+use crate::occupancy::Occupancy;
+
+#[derive(Debug, Deserialize)]
+pub struct WienerLinienResponse<'a> {
+    #[serde(borrow)]
+    pub data: Option<WienerLinienResponseData<'a>>,
+    pub message: Option<WienerLinienMessage>,
+}
+
+/// Status of a response, errors like exceeded rate limits are reported here
+#[derive(Debug, Deserialize)]
+pub struct WienerLinienMessage {
+    pub value: String,
+    #[serde(rename = "messageCode")]
+    pub message_code: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WienerLinienResponseData<'a> {
+    /// Left unparsed in the response text, so a malformed monitor is skipped on
+    /// its own and only one monitor at a time is held in memory
+    #[serde(borrow)]
+    pub monitors: Option<Vec<&'a RawValue>>,
+    #[serde(rename = "trafficInfos")]
+    pub traffic_infos: Option<Vec<WienerLinienTrafficInfo>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WienerLinienMonitor {
+    #[serde(rename = "locationStop")]
+    pub location_stop: WienerLinienLocationStop,
+    pub lines: Vec<WienerLinienLine>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WienerLinienLocationStop {
+    //    geometry: StopGeometry,
+    #[serde(rename = "properties")]
+    pub properties: StopProperties,
+}
+//#[derive(Debug, Clone, Deserialize)]
+//struct StopGeometry {
+//coordinates: [f32; 2],
+//}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StopProperties {
+    pub title: String,
+    #[serde(default)]
+    pub attributes: StopAttributes,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StopAttributes {
+    pub rbl: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WienerLinienLine {
+    pub name: String,
+    #[serde(rename = "towards")]
+    pub destination: String,
+    #[serde(rename = "type")]
+    pub vehicle_type: String,
+    /// Platform ("Steig") the line leaves from at this stop
+    #[serde(default)]
+    pub platform: Option<String>,
+    /// Set while the line is stuck in a traffic jam
+    #[serde(default)]
+    pub trafficjam: bool,
+    pub departures: WienerLinienLineDepartures,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WienerLinienLineDepartures {
+    pub departure: Vec<WienerLinienLineDeparture>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WienerLinienLineDeparture {
+    #[serde(rename = "departureTime")]
+    pub departure_time: WienerLinienLineDepartureTime,
+    #[serde(default)]
+    pub occupancy: Option<Occupancy>,
+    /// The vehicle serving the departure, if the API already knows it
+    #[serde(default)]
+    pub vehicle: Option<WienerLinienVehicle>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WienerLinienVehicle {
+    #[serde(default)]
+    pub trafficjam: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WienerLinienLineDepartureTime {
+    #[serde(rename = "timePlanned")]
+    pub time_planned: Timestamp,
+    #[serde(rename = "timeReal")]
+    pub time_real: Option<Timestamp>,
+    pub countdown: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WienerLinienTrafficInfo {
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "relatedLines", default)]
+    pub related_lines: Vec<String>,
+}